@@ -0,0 +1,14 @@
+CREATE TABLE loans(
+  id uuid DEFAULT gen_random_uuid() NOT NULL,
+  PRIMARY KEY (id),
+  book_id uuid NOT NULL REFERENCES books(id),
+  user_id uuid NOT NULL REFERENCES users(id),
+  checked_out_at timestamptz NOT NULL,
+  due_at timestamptz NOT NULL,
+  returned_at timestamptz,
+  renewals INTEGER NOT NULL DEFAULT 0
+);
+
+-- A book can only be in one active (not yet returned) loan at a time
+CREATE UNIQUE INDEX loans_active_book_id ON loans (book_id) WHERE returned_at IS NULL;
+CREATE INDEX loans_user_id ON loans (user_id);
@@ -0,0 +1,36 @@
+CREATE TABLE items(
+  id uuid DEFAULT gen_random_uuid() NOT NULL,
+  PRIMARY KEY (id),
+  book_id uuid NOT NULL REFERENCES books(id),
+  barcode TEXT NOT NULL UNIQUE,
+  condition TEXT NOT NULL,
+  shelf_location TEXT NOT NULL,
+  status TEXT NOT NULL DEFAULT 'available',
+  created_at timestamptz NOT NULL,
+  CONSTRAINT items_status_check CHECK (status IN ('available', 'on_loan', 'lost', 'in_repair'))
+);
+
+CREATE INDEX items_book_id ON items (book_id);
+
+-- Until now every book row stood for a single physical copy
+INSERT INTO items (book_id, barcode, condition, shelf_location, status, created_at)
+SELECT
+  books.id,
+  'LEGACY-' || books.id,
+  'good',
+  'unshelved',
+  CASE
+    WHEN EXISTS (SELECT 1 FROM loans WHERE loans.book_id = books.id AND loans.returned_at IS NULL)
+    THEN 'on_loan'
+    ELSE 'available'
+  END,
+  books.created_at
+FROM books;
+
+ALTER TABLE loans ADD COLUMN item_id uuid REFERENCES items(id);
+UPDATE loans SET item_id = items.id FROM items WHERE items.book_id = loans.book_id;
+ALTER TABLE loans ALTER COLUMN item_id SET NOT NULL;
+
+-- Several copies of the same book can now be on loan at once
+DROP INDEX loans_active_book_id;
+CREATE UNIQUE INDEX loans_active_item_id ON loans (item_id) WHERE returned_at IS NULL;
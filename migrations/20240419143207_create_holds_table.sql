@@ -0,0 +1,22 @@
+-- Copies that came back for a patron with a hold are set aside for pickup
+ALTER TABLE items
+  DROP CONSTRAINT items_status_check,
+  ADD CONSTRAINT items_status_check CHECK (status IN ('available', 'on_loan', 'on_hold', 'lost', 'in_repair'));
+
+CREATE TABLE holds(
+  id uuid DEFAULT gen_random_uuid() NOT NULL,
+  PRIMARY KEY (id),
+  book_id uuid NOT NULL REFERENCES books(id),
+  user_id uuid NOT NULL REFERENCES users(id),
+  item_id uuid REFERENCES items(id),
+  status TEXT NOT NULL DEFAULT 'waiting',
+  placed_at timestamptz NOT NULL,
+  ready_at timestamptz,
+  expires_at timestamptz,
+  closed_at timestamptz,
+  CONSTRAINT holds_status_check CHECK (status IN ('waiting', 'ready', 'fulfilled', 'cancelled', 'expired'))
+);
+
+CREATE UNIQUE INDEX holds_active_book_user ON holds (book_id, user_id) WHERE status IN ('waiting', 'ready');
+CREATE INDEX holds_waiting_queue ON holds (book_id, placed_at) WHERE status = 'waiting';
+CREATE INDEX holds_user_id ON holds (user_id);
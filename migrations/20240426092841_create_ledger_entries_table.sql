@@ -0,0 +1,15 @@
+CREATE TABLE ledger_entries(
+  id uuid DEFAULT gen_random_uuid() NOT NULL,
+  PRIMARY KEY (id),
+  user_id uuid NOT NULL REFERENCES users(id),
+  loan_id uuid REFERENCES loans(id),
+  kind TEXT NOT NULL,
+  -- Fines are charged as positive amounts, payments and waivers credited as negative ones
+  amount_cents BIGINT NOT NULL,
+  note TEXT NOT NULL,
+  created_at timestamptz NOT NULL,
+  CONSTRAINT ledger_entries_kind_check CHECK (kind IN ('fine', 'payment', 'waiver'))
+);
+
+CREATE INDEX ledger_entries_user_id ON ledger_entries (user_id);
+CREATE INDEX ledger_entries_loan_id ON ledger_entries (loan_id);
@@ -0,0 +1,9 @@
+-- Keyset pagination walks these in (sort column, id) order
+CREATE INDEX books_title_id ON books (title, id);
+CREATE INDEX books_created_at_id ON books (created_at, id);
+CREATE INDEX books_genre ON books (genre);
+CREATE INDEX books_author_id ON books (author_id);
+
+CREATE INDEX authors_name_id ON authors (name, id);
+CREATE INDEX authors_created_at_id ON authors (created_at, id);
+CREATE INDEX authors_nationality ON authors (nationality);
@@ -0,0 +1,47 @@
+ALTER TABLE books ADD COLUMN search_vector tsvector NOT NULL DEFAULT ''::tsvector;
+
+CREATE FUNCTION books_search_vector(title TEXT, genre TEXT, author_name TEXT) RETURNS tsvector
+LANGUAGE SQL IMMUTABLE AS $$
+  SELECT setweight(to_tsvector('english', coalesce(title, '')), 'A')
+    || setweight(to_tsvector('english', coalesce(author_name, '')), 'B')
+    || setweight(to_tsvector('english', coalesce(genre, '')), 'C')
+$$;
+
+-- A generated column cannot read the author's name from another table, so triggers keep the
+-- vector current instead: on the book itself, and on every book of an author who is renamed
+CREATE FUNCTION books_refresh_search_vector() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+  NEW.search_vector := books_search_vector(
+    NEW.title,
+    NEW.genre,
+    (SELECT name FROM authors WHERE id = NEW.author_id)
+  );
+  RETURN NEW;
+END
+$$;
+
+CREATE TRIGGER books_search_vector_update
+  BEFORE INSERT OR UPDATE OF title, genre, author_id ON books
+  FOR EACH ROW EXECUTE FUNCTION books_refresh_search_vector();
+
+CREATE FUNCTION authors_refresh_books_search_vector() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+  UPDATE books
+  SET search_vector = books_search_vector(title, genre, NEW.name)
+  WHERE author_id = NEW.id;
+  RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER authors_search_vector_update
+  AFTER UPDATE OF name ON authors
+  FOR EACH ROW EXECUTE FUNCTION authors_refresh_books_search_vector();
+
+UPDATE books
+SET search_vector = books_search_vector(books.title, books.genre, authors.name)
+FROM authors
+WHERE authors.id = books.author_id;
+
+CREATE INDEX books_search_vector ON books USING GIN (search_vector);
@@ -0,0 +1,4 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX authors_name_trgm ON authors USING GIN (name gin_trgm_ops);
+CREATE INDEX books_title_trgm ON books USING GIN (title gin_trgm_ops);
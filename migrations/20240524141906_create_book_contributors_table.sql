@@ -0,0 +1,71 @@
+CREATE TABLE book_contributors(
+  book_id uuid NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+  author_id uuid NOT NULL REFERENCES authors(id),
+  role TEXT NOT NULL DEFAULT 'author',
+  position INTEGER NOT NULL,
+  PRIMARY KEY (book_id, author_id, role),
+  UNIQUE (book_id, position),
+  CONSTRAINT book_contributors_role_check CHECK (role IN ('author', 'editor', 'translator', 'illustrator'))
+);
+
+CREATE INDEX book_contributors_author_id ON book_contributors (author_id);
+
+-- Every book so far had exactly one author
+INSERT INTO book_contributors (book_id, author_id, role, position)
+SELECT id, author_id, 'author', 0 FROM books;
+
+-- The search vector now covers every contributor's name, in credit order
+DROP TRIGGER books_search_vector_update ON books;
+DROP TRIGGER authors_search_vector_update ON authors;
+DROP FUNCTION books_refresh_search_vector();
+DROP FUNCTION authors_refresh_books_search_vector();
+
+ALTER TABLE books DROP COLUMN author_id;
+
+CREATE FUNCTION books_contributor_names(book_id uuid) RETURNS TEXT
+LANGUAGE SQL STABLE AS $$
+  SELECT string_agg(authors.name, ', ' ORDER BY book_contributors.position)
+  FROM book_contributors
+  JOIN authors ON authors.id = book_contributors.author_id
+  WHERE book_contributors.book_id = $1
+$$;
+
+CREATE FUNCTION books_refresh_search_vector() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+  NEW.search_vector := books_search_vector(NEW.title, NEW.genre, books_contributor_names(NEW.id));
+  RETURN NEW;
+END
+$$;
+
+CREATE TRIGGER books_search_vector_update
+  BEFORE INSERT OR UPDATE OF title, genre ON books
+  FOR EACH ROW EXECUTE FUNCTION books_refresh_search_vector();
+
+CREATE FUNCTION book_contributors_refresh_search_vector() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+  UPDATE books
+  SET search_vector = books_search_vector(title, genre, books_contributor_names(id))
+  WHERE id IN (OLD.book_id, NEW.book_id);
+  RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER book_contributors_search_vector_update
+  AFTER INSERT OR UPDATE OR DELETE ON book_contributors
+  FOR EACH ROW EXECUTE FUNCTION book_contributors_refresh_search_vector();
+
+CREATE FUNCTION authors_refresh_books_search_vector() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+  UPDATE books
+  SET search_vector = books_search_vector(title, genre, books_contributor_names(id))
+  WHERE id IN (SELECT book_id FROM book_contributors WHERE author_id = NEW.id);
+  RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER authors_search_vector_update
+  AFTER UPDATE OF name ON authors
+  FOR EACH ROW EXECUTE FUNCTION authors_refresh_books_search_vector();
@@ -0,0 +1,10 @@
+-- ISBN-10s are stored converted to ISBN-13, so a book has one canonical ISBN
+ALTER TABLE books
+  ADD COLUMN isbn_13 TEXT,
+  ADD COLUMN publisher TEXT,
+  ADD COLUMN publication_year INTEGER,
+  ADD COLUMN language TEXT,
+  ADD COLUMN page_count INTEGER,
+  ADD COLUMN edition TEXT,
+  ADD COLUMN description TEXT,
+  ADD CONSTRAINT books_isbn_13_key UNIQUE (isbn_13);
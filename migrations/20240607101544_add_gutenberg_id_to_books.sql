@@ -0,0 +1,4 @@
+-- Project Gutenberg's ebook number, set on books imported from Gutendex so reruns skip them
+ALTER TABLE books
+  ADD COLUMN gutenberg_id INTEGER,
+  ADD CONSTRAINT books_gutenberg_id_key UNIQUE (gutenberg_id);
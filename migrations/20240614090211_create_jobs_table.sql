@@ -0,0 +1,20 @@
+-- Background work picked up by the worker pool. Failed attempts go back to 'queued' with a
+-- later run_at until max_attempts is spent.
+CREATE TABLE jobs(
+  id uuid DEFAULT gen_random_uuid() NOT NULL,
+  PRIMARY KEY (id),
+  kind TEXT NOT NULL,
+  payload JSONB NOT NULL DEFAULT '{}',
+  status TEXT NOT NULL DEFAULT 'queued',
+  attempts INTEGER NOT NULL DEFAULT 0,
+  max_attempts INTEGER NOT NULL,
+  run_at timestamptz NOT NULL,
+  result JSONB,
+  last_error TEXT,
+  created_at timestamptz NOT NULL,
+  started_at timestamptz,
+  finished_at timestamptz,
+  CONSTRAINT jobs_status_check CHECK (status IN ('queued', 'running', 'succeeded', 'failed', 'cancelled'))
+);
+
+CREATE INDEX jobs_queue ON jobs (run_at) WHERE status = 'queued';
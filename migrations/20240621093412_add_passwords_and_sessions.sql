@@ -0,0 +1,13 @@
+-- Users registered without a password (say at the front desk) can't log in until they set one
+ALTER TABLE users ADD COLUMN password_hash TEXT;
+
+-- A login. Clients hold the id signed with the session secret, logging out deletes the row.
+CREATE TABLE sessions(
+  id uuid DEFAULT gen_random_uuid() NOT NULL,
+  PRIMARY KEY (id),
+  user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  created_at timestamptz NOT NULL,
+  expires_at timestamptz NOT NULL
+);
+
+CREATE INDEX sessions_user_id ON sessions (user_id);
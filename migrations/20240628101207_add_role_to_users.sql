@@ -0,0 +1,4 @@
+-- Patrons borrow, librarians run the catalog and the front desk, admins also hand out roles
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'patron';
+ALTER TABLE users
+  ADD CONSTRAINT users_role_check CHECK (role IN ('patron', 'librarian', 'admin'));
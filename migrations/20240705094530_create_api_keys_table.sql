@@ -0,0 +1,16 @@
+-- Keys for kiosks and scripts that can't log in. Only a hash of each key is kept, the key
+-- itself is shown once when it's created.
+CREATE TABLE api_keys(
+  id uuid DEFAULT gen_random_uuid() NOT NULL,
+  PRIMARY KEY (id),
+  name TEXT NOT NULL,
+  prefix TEXT NOT NULL,
+  key_hash TEXT NOT NULL UNIQUE,
+  scope TEXT NOT NULL,
+  created_by uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  created_at timestamptz NOT NULL,
+  expires_at timestamptz,
+  revoked_at timestamptz,
+  last_used_at timestamptz,
+  CONSTRAINT api_keys_scope_check CHECK (scope IN ('catalog_read', 'circulation', 'admin'))
+);
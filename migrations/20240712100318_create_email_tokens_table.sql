@@ -0,0 +1,17 @@
+ALTER TABLE users ADD COLUMN email_verified_at timestamptz;
+
+-- Emailed to prove the user owns their address, kept hashed like API keys. Each one works
+-- once, before it expires.
+CREATE TABLE email_tokens(
+  id uuid DEFAULT gen_random_uuid() NOT NULL,
+  PRIMARY KEY (id),
+  user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  purpose TEXT NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  created_at timestamptz NOT NULL,
+  expires_at timestamptz NOT NULL,
+  used_at timestamptz,
+  CONSTRAINT email_tokens_purpose_check CHECK (purpose IN ('verify_email', 'reset_password'))
+);
+
+CREATE INDEX email_tokens_user_id ON email_tokens (user_id);
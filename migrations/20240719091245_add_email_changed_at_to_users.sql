@@ -0,0 +1,3 @@
+-- Set when an account's email changes, so a password reset isn't mailed to a new address
+-- before its owner has confirmed it
+ALTER TABLE users ADD COLUMN email_changed_at timestamptz;
@@ -0,0 +1,5 @@
+-- Copies go with their book, like its contributors. Loans and holds still keep a copy or
+-- book with circulation history from being deleted.
+ALTER TABLE items DROP CONSTRAINT items_book_id_fkey;
+ALTER TABLE items
+  ADD CONSTRAINT items_book_id_fkey FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE;
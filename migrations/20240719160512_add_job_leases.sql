@@ -0,0 +1,8 @@
+-- A running job's worker renews locked_at while it works. Once that lapses the worker is
+-- taken to have died and the job is queued again, or failed if its attempts are spent.
+ALTER TABLE jobs ADD COLUMN locked_at timestamptz;
+UPDATE jobs SET locked_at = started_at WHERE status = 'running';
+CREATE INDEX jobs_leases ON jobs (locked_at) WHERE status = 'running';
+
+-- Job kinds carry no parameters, a Gutendex import reads its limits from the configuration
+ALTER TABLE jobs DROP COLUMN payload;
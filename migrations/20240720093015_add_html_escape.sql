@@ -0,0 +1,6 @@
+-- Search snippets are HTML, so book data is escaped before <mark> tags go in around matches
+CREATE FUNCTION html_escape(source text) RETURNS TEXT
+LANGUAGE SQL IMMUTABLE AS $$
+  SELECT replace(replace(replace(replace(replace(
+    $1, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), '"', '&quot;'), '''', '&#39;')
+$$;
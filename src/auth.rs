@@ -0,0 +1,599 @@
+use crate::configuration::AuthConfig;
+use crate::errors::AppError;
+use actix_web::{
+    cookie::{time::Duration, Cookie, SameSite},
+    dev::Payload,
+    http::header::AUTHORIZATION,
+    web::Data,
+    FromRequest, HttpRequest,
+};
+use argon2::password_hash::{
+    rand_core::{OsRng, RngCore},
+    PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+};
+use argon2::Argon2;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+pub const SESSION_COOKIE: &str = "session";
+// Tells API keys apart from session tokens in the same `Authorization` header
+pub const API_KEY_PREFIX: &str = "mlk_";
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn internal(e: impl std::fmt::Display) -> AppError {
+    AppError::Internal(e.to_string())
+}
+
+// Argon2 is slow on purpose, so it runs off the async workers
+pub async fn hash_password(password: String) -> Result<String, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+    })
+    .await
+    .map_err(internal)?
+    .map_err(internal)
+}
+
+// Unknown emails and users without a password are checked against a throwaway hash, so a
+// failed login takes as long whether or not the account exists
+pub async fn verify_password(password: String, hash: Option<String>) -> Result<bool, AppError> {
+    static THROWAWAY_HASH: OnceLock<String> = OnceLock::new();
+
+    tokio::task::spawn_blocking(move || {
+        let known = hash.is_some();
+        let hash = match hash {
+            Some(hash) => hash,
+            None => THROWAWAY_HASH
+                .get_or_init(|| {
+                    let salt = SaltString::generate(&mut OsRng);
+                    Argon2::default()
+                        .hash_password(Uuid::new_v4().as_bytes(), &salt)
+                        .map(|hash| hash.to_string())
+                        .unwrap_or_default()
+                })
+                .clone(),
+        };
+        let verified = PasswordHash::new(&hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        });
+        known && verified
+    })
+    .await
+    .map_err(internal)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn signature(session_id: Uuid, secret: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC takes any key size");
+    mac.update(session_id.as_bytes());
+    mac
+}
+
+// The session id and its HMAC, hex encoded, so tokens can't be guessed or made up without
+// the secret and forged ones are turned away before touching the database
+pub fn session_token(session_id: Uuid, secret: &str) -> String {
+    let tag = hex(&signature(session_id, secret).finalize().into_bytes());
+    format!("{}.{}", session_id, tag)
+}
+
+// The session id, if `token` was signed with `secret`
+pub fn verify_session_token(token: &str, secret: &str) -> Option<Uuid> {
+    let (session_id, tag) = token.split_once('.')?;
+    let session_id = Uuid::parse_str(session_id).ok()?;
+    let tag = (0..tag.len())
+        .step_by(2)
+        .map(|i| {
+            tag.get(i..i + 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        })
+        .collect::<Option<Vec<u8>>>()?;
+
+    signature(session_id, secret).verify_slice(&tag).ok()?;
+    Some(session_id)
+}
+
+// 256 random bits, so unlike passwords a plain SHA-256 is enough to store them and tokens can
+// be looked up by their hash
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex(&bytes)
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+pub fn generate_api_key() -> String {
+    format!("{}{}", API_KEY_PREFIX, random_token())
+}
+
+pub fn session_cookie(token: String, config: &AuthConfig) -> Cookie<'static> {
+    Cookie::build(SESSION_COOKIE, token)
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(config.secure_cookie)
+        .max_age(Duration::hours(config.session_ttl_hours))
+        .finish()
+}
+
+pub fn expired_session_cookie() -> Cookie<'static> {
+    let mut cookie = Cookie::build(SESSION_COOKIE, "").path("/").finish();
+    cookie.make_removal();
+    cookie
+}
+
+// Ordered by what they're allowed to do, each role can do everything the ones before it can
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Patron,
+    Librarian,
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Patron, Role::Librarian, Role::Admin];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Patron => "patron",
+            Role::Librarian => "librarian",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Role::ALL
+            .into_iter()
+            .find(|role| role.as_str() == value)
+            .ok_or(())
+    }
+}
+
+// What an API key may be used for, on top of what the admin who created it can do
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiKeyScope {
+    CatalogRead,
+    Circulation,
+    Admin,
+}
+
+impl ApiKeyScope {
+    pub const ALL: [ApiKeyScope; 3] = [
+        ApiKeyScope::CatalogRead,
+        ApiKeyScope::Circulation,
+        ApiKeyScope::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::CatalogRead => "catalog_read",
+            ApiKeyScope::Circulation => "circulation",
+            ApiKeyScope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for ApiKeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ApiKeyScope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == value)
+            .ok_or(())
+    }
+}
+
+pub enum Credential {
+    Session(Uuid),
+    ApiKey { id: Uuid, scope: ApiKeyScope },
+}
+
+// The logged in user behind a request, found from an `Authorization: Bearer` token or else
+// the session cookie. Handlers taking one answer 401 to anyone else. Requests made with an
+// API key act as the admin who created it.
+pub struct CurrentUser {
+    pub id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub role: Role,
+    pub credential: Credential,
+}
+
+impl CurrentUser {
+    pub fn is_staff(&self) -> bool {
+        self.role >= Role::Librarian
+    }
+
+    fn out_of_scope(&self, allowed: &[ApiKeyScope]) -> Result<(), AppError> {
+        match self.credential {
+            Credential::ApiKey { scope, .. } if !allowed.contains(&scope) => Err(
+                AppError::Forbidden(format!("API key scope {} does not allow this", scope)),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    // Staff only work takes an admin scoped key
+    pub fn require(&self, role: Role) -> Result<(), AppError> {
+        self.out_of_scope(&[ApiKeyScope::Admin])?;
+        match self.role >= role {
+            true => Ok(()),
+            false => Err(AppError::Forbidden(format!("Requires the {} role", role))),
+        }
+    }
+
+    // Patrons may only see and act on their own account, loans and holds, staff on anyone's
+    pub fn require_self_or_staff(&self, user_id: Uuid) -> Result<(), AppError> {
+        self.out_of_scope(&[ApiKeyScope::Circulation, ApiKeyScope::Admin])?;
+        match self.id == user_id || self.is_staff() {
+            true => Ok(()),
+            false => Err(AppError::Forbidden(
+                "Patrons can only act on their own account".into(),
+            )),
+        }
+    }
+
+    // Accounts are edited by their owner, or by staff ranked above the owner. Only admins
+    // change someone else's email, since a reset password goes to it, and API keys never edit
+    // accounts at all.
+    pub fn require_account_editor(
+        &self,
+        user_id: Uuid,
+        role: Role,
+        changes_email: bool,
+    ) -> Result<(), AppError> {
+        if let Credential::ApiKey { .. } = self.credential {
+            return Err(AppError::Forbidden("API keys can't edit accounts".into()));
+        }
+        if self.id == user_id {
+            return Ok(());
+        }
+        self.require_self_or_staff(user_id)?;
+        if role >= self.role {
+            return Err(AppError::Forbidden(
+                "Staff can only edit accounts ranked below their own".into(),
+            ));
+        }
+        match changes_email && self.role < Role::Admin {
+            true => Err(AppError::Forbidden(
+                "Only admins can change someone else's email".into(),
+            )),
+            false => Ok(()),
+        }
+    }
+}
+
+impl FromRequest for CurrentUser {
+    type Error = AppError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let token = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(String::from)
+            .or_else(|| {
+                request
+                    .cookie(SESSION_COOKIE)
+                    .map(|cookie| cookie.value().to_string())
+            });
+        let config = request.app_data::<Data<AuthConfig>>().cloned();
+        let db_pool = request.app_data::<Data<PgPool>>().cloned();
+
+        Box::pin(async move {
+            let (Some(config), Some(db_pool)) = (config, db_pool) else {
+                return Err(internal("Authentication is not configured"));
+            };
+            let token = token.ok_or_else(|| AppError::Unauthorized("Log in first".into()))?;
+            match token.starts_with(API_KEY_PREFIX) {
+                true => api_key_user(&token, &db_pool).await,
+                false => session_user(&token, &config, &db_pool).await,
+            }
+        })
+    }
+}
+
+pub(crate) fn parse_role(role: String) -> Result<Role, AppError> {
+    role.parse()
+        .map_err(|_| internal(format!("Unknown role {}", role)))
+}
+
+async fn session_user(
+    token: &str,
+    config: &AuthConfig,
+    db_pool: &PgPool,
+) -> Result<CurrentUser, AppError> {
+    let session_id = verify_session_token(token, config.session_secret.expose_secret())
+        .ok_or_else(|| AppError::Unauthorized("Invalid session token".into()))?;
+
+    let user = sqlx::query!(
+        "SELECT users.id, users.name, users.email, users.role
+                FROM sessions
+                JOIN users ON users.id = sessions.user_id
+                WHERE sessions.id = $1 AND sessions.expires_at > $2",
+        session_id,
+        Utc::now()
+    )
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Session expired or logged out".into()))?;
+
+    Ok(CurrentUser {
+        id: user.id,
+        name: user.name,
+        email: user.email,
+        role: parse_role(user.role)?,
+        credential: Credential::Session(session_id),
+    })
+}
+
+async fn api_key_user(key: &str, db_pool: &PgPool) -> Result<CurrentUser, AppError> {
+    let now = Utc::now();
+    let record = sqlx::query!(
+        "UPDATE api_keys SET last_used_at = $2
+        FROM users
+        WHERE users.id = api_keys.created_by
+        AND api_keys.key_hash = $1
+        AND api_keys.revoked_at IS NULL
+        AND (api_keys.expires_at IS NULL OR api_keys.expires_at > $2)
+        RETURNING api_keys.id, api_keys.scope, users.id AS user_id, users.name, users.email,
+            users.role",
+        hash_token(key),
+        now
+    )
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Invalid, expired or revoked API key".into()))?;
+
+    Ok(CurrentUser {
+        id: record.user_id,
+        name: record.name,
+        email: record.email,
+        role: parse_role(record.role)?,
+        credential: Credential::ApiKey {
+            id: record.id,
+            scope: record
+                .scope
+                .parse()
+                .map_err(|_| internal(format!("Unknown API key scope {}", record.scope)))?,
+        },
+    })
+}
+
+fn require_role(
+    request: &HttpRequest,
+    payload: &mut Payload,
+    role: Role,
+) -> impl Future<Output = Result<CurrentUser, AppError>> {
+    let user = CurrentUser::from_request(request, payload);
+    async move {
+        let user = user.await?;
+        user.require(role)?;
+        Ok(user)
+    }
+}
+
+// Guards for staff only handlers: a librarian or admin, anyone else gets a 403
+pub struct Librarian(pub CurrentUser);
+
+impl FromRequest for Librarian {
+    type Error = AppError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(request: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user = require_role(request, payload, Role::Librarian);
+        Box::pin(async move { user.await.map(Librarian) })
+    }
+}
+
+pub struct Admin(pub CurrentUser);
+
+impl FromRequest for Admin {
+    type Error = AppError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(request: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user = require_role(request, payload, Role::Admin);
+        Box::pin(async move { user.await.map(Admin) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-secret";
+
+    #[test]
+    fn session_token_round_trip() {
+        let session_id = Uuid::new_v4();
+        let token = session_token(session_id, SECRET);
+        assert_eq!(verify_session_token(&token, SECRET), Some(session_id));
+    }
+
+    #[test]
+    fn forged_session_tokens() {
+        let token = session_token(Uuid::new_v4(), SECRET);
+        assert_eq!(verify_session_token(&token, "another-secret"), None);
+
+        let other_session = Uuid::new_v4().to_string();
+        let (_, tag) = token.split_once('.').unwrap();
+        let swapped = format!("{}.{}", other_session, tag);
+        assert_eq!(verify_session_token(&swapped, SECRET), None);
+
+        assert_eq!(verify_session_token("not-a-token", SECRET), None);
+        assert_eq!(
+            verify_session_token(&token[..token.len() - 1], SECRET),
+            None
+        );
+    }
+
+    fn user_with(role: Role) -> CurrentUser {
+        CurrentUser {
+            id: Uuid::new_v4(),
+            name: "Nora Seed".into(),
+            email: "nora@example.com".into(),
+            role,
+            credential: Credential::Session(Uuid::new_v4()),
+        }
+    }
+
+    fn key_with(scope: ApiKeyScope) -> CurrentUser {
+        CurrentUser {
+            credential: Credential::ApiKey {
+                id: Uuid::new_v4(),
+                scope,
+            },
+            ..user_with(Role::Admin)
+        }
+    }
+
+    #[test]
+    fn roles_round_trip() {
+        for role in Role::ALL {
+            assert_eq!(role.as_str().parse(), Ok(role));
+        }
+        assert_eq!("superuser".parse::<Role>(), Err(()));
+    }
+
+    #[test]
+    fn higher_roles_can_do_more() {
+        let librarian = user_with(Role::Librarian);
+        assert!(librarian.require(Role::Patron).is_ok());
+        assert!(librarian.require(Role::Librarian).is_ok());
+        let error = librarian.require(Role::Admin).unwrap_err();
+        assert_eq!(error.code(), "forbidden");
+        assert_eq!(error.to_string(), "Requires the admin role");
+    }
+
+    #[test]
+    fn patrons_act_for_themselves() {
+        let patron = user_with(Role::Patron);
+        assert!(patron.require_self_or_staff(patron.id).is_ok());
+        assert!(patron.require_self_or_staff(Uuid::new_v4()).is_err());
+        assert!(user_with(Role::Librarian)
+            .require_self_or_staff(Uuid::new_v4())
+            .is_ok());
+    }
+
+    #[test]
+    fn accounts_are_edited_from_above() {
+        let librarian = user_with(Role::Librarian);
+        let admin = user_with(Role::Admin);
+        let someone = Uuid::new_v4();
+        assert!(librarian
+            .require_account_editor(librarian.id, Role::Librarian, true)
+            .is_ok());
+        assert!(librarian
+            .require_account_editor(someone, Role::Patron, false)
+            .is_ok());
+        let error = librarian
+            .require_account_editor(someone, Role::Patron, true)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Only admins can change someone else's email"
+        );
+        assert!(librarian
+            .require_account_editor(someone, Role::Librarian, false)
+            .is_err());
+        assert!(admin
+            .require_account_editor(someone, Role::Librarian, true)
+            .is_ok());
+        assert!(admin
+            .require_account_editor(someone, Role::Admin, false)
+            .is_err());
+        assert!(user_with(Role::Patron)
+            .require_account_editor(someone, Role::Patron, false)
+            .is_err());
+
+        let key = key_with(ApiKeyScope::Admin);
+        let error = key
+            .require_account_editor(someone, Role::Patron, false)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "API keys can't edit accounts");
+    }
+
+    #[test]
+    fn api_keys_are_narrowed_by_scope() {
+        let catalog = key_with(ApiKeyScope::CatalogRead);
+        assert!(catalog.require(Role::Librarian).is_err());
+        let error = catalog.require_self_or_staff(Uuid::new_v4()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "API key scope catalog_read does not allow this"
+        );
+
+        let kiosk = key_with(ApiKeyScope::Circulation);
+        assert!(kiosk.require_self_or_staff(Uuid::new_v4()).is_ok());
+        assert!(kiosk.require(Role::Librarian).is_err());
+
+        assert!(key_with(ApiKeyScope::Admin).require(Role::Admin).is_ok());
+    }
+
+    #[test]
+    fn tokens_hash_consistently() {
+        let key = generate_api_key();
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert_eq!(key.len(), API_KEY_PREFIX.len() + 64);
+        assert_ne!(key, generate_api_key());
+        assert_eq!(hash_token(&key), hash_token(&key));
+        assert_eq!(hash_token(&key).len(), 64);
+        assert_eq!(
+            "circulation".parse::<ApiKeyScope>(),
+            Ok(ApiKeyScope::Circulation)
+        );
+    }
+
+    #[tokio::test]
+    async fn password_hashes() {
+        let hash = hash_password("correct horse".into()).await.unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password("correct horse".into(), Some(hash.clone()))
+            .await
+            .unwrap());
+        assert!(!verify_password("battery staple".into(), Some(hash))
+            .await
+            .unwrap());
+        assert!(!verify_password("correct horse".into(), None).await.unwrap());
+    }
+}
@@ -1,33 +1,274 @@
-#[derive(serde::Deserialize)]
+use secrecy::{ExposeSecret, Secret};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(serde::Deserialize, Debug)]
 pub struct ApplicationConfigs {
     pub server_address: String,
     pub database: DatabaseConfig,
+    pub circulation: CirculationConfig,
+    pub metadata: MetadataConfig,
+    pub gutendex: GutendexConfig,
+    pub jobs: JobsConfig,
+    pub auth: AuthConfig,
+    pub mailer: MailerConfig,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, Debug)]
 pub struct DatabaseConfig {
     pub username: String,
-    pub password: String,
+    pub password: Secret<String>,
     pub port: u16,
     pub host: String,
     pub name: String,
 }
 
 impl DatabaseConfig {
-    pub fn database_url(&self) -> String {
-        format!(
+    pub fn database_url(&self) -> Secret<String> {
+        Secret::new(format!(
             "postgres://{}:{}@{}:{}/{}",
-            self.username, self.password, self.host, self.port, self.name
-        )
+            self.username,
+            self.password.expose_secret(),
+            self.host,
+            self.port,
+            self.name
+        ))
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct CirculationConfig {
+    pub daily_fine_cents: i64,
+    pub max_balance_cents: i64,
+}
+
+// Where ISBN lookups go. The fixture provider reads a local JSON file instead, for working
+// offline and in tests.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum MetadataConfig {
+    OpenLibrary { base_url: String },
+    Fixture { path: String },
+}
+
+// The Gutendex catalog importer follows `next` links from `base_url` for at most `max_pages`
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct GutendexConfig {
+    pub base_url: String,
+    pub max_pages: u32,
+}
+
+// Background job workers: how many run, how often an idle one looks for work, and how
+// failed jobs are retried. A running job whose worker hasn't renewed its lease for
+// `lease_timeout_ms` is taken back, as the worker must have died.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct JobsConfig {
+    pub workers: usize,
+    pub poll_interval_ms: u64,
+    pub max_attempts: i32,
+    pub retry_backoff_ms: u64,
+    pub lease_timeout_ms: u64,
+}
+
+// Session tokens are signed with `session_secret` and last `session_ttl_hours`. The session
+// cookie is only sent over HTTPS when `secure_cookie` is set. Emailed tokens confirming an
+// address or resetting a password expire after their own TTLs.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct AuthConfig {
+    pub session_secret: Secret<String>,
+    pub session_ttl_hours: i64,
+    pub secure_cookie: bool,
+    pub verify_email_ttl_hours: i64,
+    pub password_reset_ttl_minutes: i64,
+}
+
+// Mail is sent as `from` through `transport`
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct MailerConfig {
+    pub from: String,
+    #[serde(flatten)]
+    pub transport: MailTransportConfig,
+}
+
+// SMTP relays over STARTTLS. The file transport writes each message as JSON into `directory`
+// instead of sending it, for development and tests.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(tag = "transport", rename_all = "snake_case")]
+pub enum MailTransportConfig {
+    Smtp {
+        host: String,
+        port: u16,
+        username: String,
+        password: Secret<String>,
+    },
+    File {
+        directory: String,
+    },
+}
+
+// Which overlay in `configuration/` is layered over `base.yaml`, picked by `APP_ENVIRONMENT`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Environment {
+    Local,
+    Production,
+}
+
+impl Environment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Environment::Local => "local",
+            Environment::Production => "production",
+        }
+    }
+}
+
+impl TryFrom<String> for Environment {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "local" => Ok(Environment::Local),
+            "production" => Ok(Environment::Production),
+            other => Err(format!(
+                "{} is not a supported environment. Use either `local` or `production`.",
+                other
+            )),
+        }
     }
 }
 
+// Reads `configuration/base.yaml`, then the overlay for `APP_ENVIRONMENT` (`local` unless
+// set), then any `APP_` prefixed environment variable, with `__` between nested keys:
+// `APP_DATABASE__PASSWORD` sets `database.password`
 pub fn get_configuration() -> Result<ApplicationConfigs, config::ConfigError> {
+    let directory = std::env::current_dir()
+        .map_err(|e| config::ConfigError::Foreign(Box::new(e)))?
+        .join("configuration");
+    let environment: Environment = std::env::var("APP_ENVIRONMENT")
+        .unwrap_or_else(|_| Environment::Local.as_str().into())
+        .try_into()
+        .map_err(config::ConfigError::Message)?;
+
+    load_configuration(&directory, environment, None)
+}
+
+// `variables` stands in for the process environment when given
+fn load_configuration(
+    directory: &Path,
+    environment: Environment,
+    variables: Option<HashMap<String, String>>,
+) -> Result<ApplicationConfigs, config::ConfigError> {
     let settings = config::Config::builder()
-        .add_source(config::File::new(
-            "configuration.yaml",
-            config::FileFormat::Yaml,
+        .add_source(config::File::from(directory.join("base.yaml")))
+        .add_source(config::File::from(
+            directory.join(format!("{}.yaml", environment.as_str())),
         ))
+        .add_source(
+            config::Environment::with_prefix("APP")
+                .prefix_separator("_")
+                .separator("__")
+                .try_parsing(true)
+                .source(variables),
+        )
         .build()?;
     settings.try_deserialize::<ApplicationConfigs>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("configuration")
+    }
+
+    fn variables(pairs: &[(&str, &str)]) -> Option<HashMap<String, String>> {
+        Some(
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn environments_parse_case_insensitively() {
+        assert_eq!(
+            Environment::try_from("Production".to_string()),
+            Ok(Environment::Production)
+        );
+        assert_eq!(
+            Environment::try_from("local".to_string()),
+            Ok(Environment::Local)
+        );
+        assert!(Environment::try_from("staging".to_string()).is_err());
+    }
+
+    #[test]
+    fn overlays_layer_over_the_base() {
+        let config = load_configuration(&directory(), Environment::Local, variables(&[])).unwrap();
+        assert_eq!(config.server_address, "127.0.0.1:8080");
+        assert_eq!(config.database.name, "midnight_library");
+        assert!(!config.auth.secure_cookie);
+        assert!(matches!(
+            config.mailer.transport,
+            MailTransportConfig::File { .. }
+        ));
+    }
+
+    #[test]
+    fn environment_variables_override_any_key() {
+        let config = load_configuration(
+            &directory(),
+            Environment::Local,
+            variables(&[
+                ("APP_DATABASE__PASSWORD", "hunter2"),
+                ("APP_DATABASE__PORT", "6543"),
+                ("APP_JOBS__WORKERS", "8"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.database.password.expose_secret(), "hunter2");
+        assert_eq!(config.database.port, 6543);
+        assert_eq!(config.jobs.workers, 8);
+    }
+
+    #[test]
+    fn production_needs_its_secrets_from_the_environment() {
+        assert!(load_configuration(&directory(), Environment::Production, variables(&[])).is_err());
+
+        let config = load_configuration(
+            &directory(),
+            Environment::Production,
+            variables(&[
+                ("APP_DATABASE__PASSWORD", "hunter2"),
+                ("APP_AUTH__SESSION_SECRET", "a-long-production-secret"),
+                ("APP_MAILER__PASSWORD", "smtp-password"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.server_address, "0.0.0.0:8080");
+        assert!(config.auth.secure_cookie);
+        match config.mailer.transport {
+            MailTransportConfig::Smtp { port, password, .. } => {
+                assert_eq!(port, 587);
+                assert_eq!(password.expose_secret(), "smtp-password");
+            }
+            MailTransportConfig::File { .. } => panic!("production should send mail over SMTP"),
+        }
+    }
+
+    #[test]
+    fn secrets_are_redacted_in_debug_output() {
+        let config = load_configuration(
+            &directory(),
+            Environment::Local,
+            variables(&[("APP_DATABASE__PASSWORD", "hunter2")]),
+        )
+        .unwrap();
+        let debug = format!("{:?}", config);
+        assert!(!debug.contains("hunter2"));
+        assert!(!debug.contains("local-development-secret"));
+        assert!(!debug.contains(config.database.database_url().expose_secret()));
+    }
+}
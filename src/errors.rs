@@ -0,0 +1,192 @@
+use crate::routes::{AuthorMatch, RowError};
+use crate::validations::ValidationErrors;
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use serde_json::json;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AppError {
+    MalformedRequest(String),
+    Validation(ValidationErrors),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    UnknownAuthor(String, Vec<AuthorMatch>),
+    AmbiguousAuthor(String, Vec<AuthorMatch>),
+    Conflict(String),
+    ImportRejected(Vec<RowError>),
+    Upstream(String),
+    Database(sqlx::Error),
+    Internal(String),
+}
+
+impl AppError {
+    // Stable identifier for clients to match on, unlike the human readable detail
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::MalformedRequest(_) => "malformed_request",
+            AppError::Validation(_) => "validation_failed",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::UnknownAuthor(..) => "author_not_found",
+            AppError::AmbiguousAuthor(..) => "ambiguous_author",
+            AppError::Conflict(_) => "conflict",
+            AppError::ImportRejected(_) => "import_rejected",
+            AppError::Upstream(_) => "upstream_unavailable",
+            AppError::Database(_) | AppError::Internal(_) => "internal_error",
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Validation(errors) => write!(f, "{}", errors),
+            AppError::MalformedRequest(message)
+            | AppError::Unauthorized(message)
+            | AppError::Forbidden(message)
+            | AppError::NotFound(message)
+            | AppError::Conflict(message)
+            | AppError::Upstream(message)
+            | AppError::Internal(message) => write!(f, "{}", message),
+            AppError::UnknownAuthor(name, candidates) if candidates.is_empty() => {
+                write!(f, "No author named \"{}\"", name)
+            }
+            AppError::UnknownAuthor(name, _) => {
+                write!(
+                    f,
+                    "No author named \"{}\", did you mean one of the candidates?",
+                    name
+                )
+            }
+            AppError::AmbiguousAuthor(name, candidates) => write!(
+                f,
+                "{} authors are named \"{}\", pick one of the candidates by author_id",
+                candidates.len(),
+                name
+            ),
+            AppError::ImportRejected(rows) => {
+                write!(f, "{} rows could not be imported, so none were", rows.len())
+            }
+            AppError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => AppError::NotFound("Record not found".into()),
+            sqlx::Error::Database(ref db_error) if db_error.is_unique_violation() => {
+                AppError::Conflict("Record already exists".into())
+            }
+            sqlx::Error::Database(ref db_error) if db_error.is_foreign_key_violation() => {
+                AppError::Conflict("Record is still referenced by other records".into())
+            }
+            e => AppError::Database(e),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::MalformedRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Validation(_) | AppError::ImportRejected(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) | AppError::UnknownAuthor(..) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) | AppError::AmbiguousAuthor(..) => StatusCode::CONFLICT,
+            AppError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // RFC 7807 problem details
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        // Database and internal errors can carry table names or library details, so they never
+        // reach the client
+        let detail = match self {
+            AppError::Database(_) | AppError::Internal(_) => {
+                "An unexpected error occurred".to_string()
+            }
+            e => e.to_string(),
+        };
+
+        let mut problem = json!({
+            "type": "about:blank",
+            "title": status.canonical_reason().unwrap_or_default(),
+            "status": status.as_u16(),
+            "detail": detail,
+            "code": self.code()
+        });
+        match self {
+            AppError::Validation(errors) => problem["errors"] = json!(errors),
+            AppError::UnknownAuthor(_, candidates) | AppError::AmbiguousAuthor(_, candidates) => {
+                problem["candidates"] = json!(candidates)
+            }
+            AppError::ImportRejected(rows) => problem["rows"] = json!(rows),
+            _ => {}
+        }
+
+        let mut response = HttpResponse::build(status);
+        if let AppError::Unauthorized(_) = self {
+            response.insert_header(("WWW-Authenticate", "Bearer"));
+        }
+        response
+            .content_type("application/problem+json")
+            .body(problem.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validations::Violation;
+
+    #[test]
+    fn row_not_found_is_not_found() {
+        let error = AppError::from(sqlx::Error::RowNotFound);
+        assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(error.code(), "not_found");
+    }
+
+    #[test]
+    fn validation_is_unprocessable() {
+        let error = AppError::Validation(ValidationErrors::single("title", Violation::empty()));
+        assert_eq!(error.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error.to_string(), "title must not be empty");
+    }
+
+    #[test]
+    fn unknown_author_suggests_candidates() {
+        let error = AppError::UnknownAuthor("Herman Melvile".into(), Vec::new());
+        assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(error.code(), "author_not_found");
+        assert_eq!(error.to_string(), r#"No author named "Herman Melvile""#);
+    }
+
+    #[test]
+    fn database_error_detail_is_hidden() {
+        let error = AppError::from(sqlx::Error::PoolTimedOut);
+        let response = error.error_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            response.headers().get("Content-Type").unwrap(),
+            "application/problem+json"
+        );
+    }
+}
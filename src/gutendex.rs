@@ -0,0 +1,100 @@
+use crate::configuration::GutendexConfig;
+use crate::errors::AppError;
+use crate::marc::direct_order;
+use serde::Deserialize;
+
+// A page of the Gutendex catalog, `next` links to the following one until the last
+#[derive(Deserialize, Debug)]
+pub struct GutendexPage {
+    pub next: Option<String>,
+    #[serde(default)]
+    pub results: Vec<GutendexBook>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GutendexBook {
+    pub id: i32,
+    pub title: String,
+    #[serde(default)]
+    pub authors: Vec<GutendexPerson>,
+    #[serde(default)]
+    pub translators: Vec<GutendexPerson>,
+    #[serde(default)]
+    pub subjects: Vec<String>,
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GutendexPerson {
+    pub name: String,
+}
+
+impl GutendexPerson {
+    // Gutenberg files people as "Last, First" like library catalogs do, the catalog shows
+    // them as "First Last"
+    pub fn display_name(&self) -> String {
+        direct_order(&self.name)
+    }
+}
+
+pub struct Gutendex {
+    client: reqwest::Client,
+    base_url: String,
+    pub max_pages: u32,
+}
+
+impl Gutendex {
+    pub fn new(config: &GutendexConfig, client: reqwest::Client) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').into(),
+            max_pages: config.max_pages,
+        }
+    }
+
+    pub fn first_page(&self) -> String {
+        format!("{}/books/", self.base_url)
+    }
+
+    pub async fn page(&self, url: &str) -> Result<GutendexPage, AppError> {
+        self.client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| AppError::Upstream(e.to_string()))?
+            .json::<GutendexPage>()
+            .await
+            .map_err(|e| AppError::Upstream(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person(name: &str) -> GutendexPerson {
+        GutendexPerson { name: name.into() }
+    }
+
+    #[test]
+    fn display_names() {
+        assert_eq!(person("Melville, Herman").display_name(), "Herman Melville");
+        assert_eq!(
+            person("King, Martin Luther, Jr.").display_name(),
+            "Martin Luther King, Jr."
+        );
+        assert_eq!(person("Homer").display_name(), "Homer");
+    }
+
+    #[test]
+    fn page_with_missing_lists() {
+        let page: GutendexPage = serde_json::from_str(
+            r#"{"next": null, "results": [{"id": 2701, "title": "Moby Dick; Or, The Whale"}]}"#,
+        )
+        .unwrap();
+        assert!(page.next.is_none());
+        assert!(page.results[0].authors.is_empty());
+    }
+}
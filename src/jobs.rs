@@ -0,0 +1,267 @@
+use crate::configuration::JobsConfig;
+use crate::errors::AppError;
+use crate::gutendex::Gutendex;
+use crate::routes::run_gutendex_import;
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub const GUTENDEX_IMPORT: &str = "gutendex_import";
+
+pub async fn enqueue(kind: &str, config: &JobsConfig, db_pool: &PgPool) -> Result<Uuid, AppError> {
+    let now = Utc::now();
+    let job = sqlx::query!(
+        "INSERT INTO jobs (kind, max_attempts, run_at, created_at)
+        VALUES ($1, $2, $3, $3)
+        RETURNING id",
+        kind,
+        config.max_attempts,
+        now
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    Ok(job.id)
+}
+
+// Long jobs look at this between steps, a cancelled job stops at the next one
+pub async fn is_cancelled(job_id: Uuid, db_pool: &PgPool) -> Result<bool, AppError> {
+    let status = sqlx::query_scalar!("SELECT status FROM jobs WHERE id = $1", job_id)
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(status == "cancelled")
+}
+
+// What a running job has done so far, shown on its status until it finishes
+pub async fn record_progress(
+    job_id: Uuid,
+    result: Value,
+    db_pool: &PgPool,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "UPDATE jobs SET result = $2 WHERE id = $1 AND status = 'running'",
+        job_id,
+        result
+    )
+    .execute(db_pool)
+    .await?;
+
+    Ok(())
+}
+
+struct ClaimedJob {
+    id: Uuid,
+    kind: String,
+    attempts: i32,
+    max_attempts: i32,
+}
+
+#[derive(Clone)]
+struct Worker {
+    db_pool: PgPool,
+    gutendex: Arc<Gutendex>,
+    config: JobsConfig,
+}
+
+pub fn start_workers(db_pool: PgPool, gutendex: Arc<Gutendex>, config: JobsConfig) {
+    let worker = Worker {
+        db_pool,
+        gutendex,
+        config,
+    };
+    for _ in 0..worker.config.workers {
+        tokio::spawn(worker.clone().run());
+    }
+}
+
+impl Worker {
+    async fn run(self) {
+        let poll_interval = std::time::Duration::from_millis(self.config.poll_interval_ms);
+        loop {
+            match self.claim().await {
+                Ok(Some(job)) => self.execute(job).await,
+                Ok(None) => tokio::time::sleep(poll_interval).await,
+                Err(e) => {
+                    tracing::error!(error = %e, "Job worker failed to claim a job");
+                    tokio::time::sleep(poll_interval).await;
+                }
+            }
+        }
+    }
+
+    fn lease_timeout(&self) -> Duration {
+        Duration::milliseconds(self.config.lease_timeout_ms as i64)
+    }
+
+    // Jobs whose worker died mid-run go back in the queue, or fail if that was their last try
+    async fn release_abandoned(&self) -> Result<(), AppError> {
+        let now = Utc::now();
+        let released = sqlx::query_scalar!(
+            "UPDATE jobs
+            SET
+                status = CASE WHEN attempts < max_attempts THEN 'queued' ELSE 'failed' END,
+                last_error = 'The worker running this job stopped before it finished',
+                run_at = $1,
+                finished_at = CASE WHEN attempts < max_attempts THEN NULL ELSE $1::timestamptz END,
+                locked_at = NULL
+            WHERE status = 'running' AND locked_at < $2
+            RETURNING id",
+            now,
+            now - self.lease_timeout()
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        for job_id in released {
+            tracing::warn!(%job_id, "Released a job whose worker stopped renewing its lease");
+        }
+        Ok(())
+    }
+
+    // SKIP LOCKED lets every worker poll the same queue without taking the same job twice
+    async fn claim(&self) -> Result<Option<ClaimedJob>, AppError> {
+        self.release_abandoned().await?;
+        let now = Utc::now();
+        let job = sqlx::query_as!(
+            ClaimedJob,
+            "UPDATE jobs
+            SET status = 'running', attempts = attempts + 1, started_at = $1, locked_at = $1
+            WHERE id = (
+                SELECT id FROM jobs
+                WHERE status = 'queued' AND run_at <= $1
+                ORDER BY run_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, kind, attempts, max_attempts",
+            now
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(job)
+    }
+
+    // Renews the job's lease a few times per timeout for as long as it runs. The attempt
+    // number fences the update, so a worker whose job was already taken back leaves it alone.
+    fn keep_leased(&self, job: &ClaimedJob) -> tokio::task::JoinHandle<()> {
+        let db_pool = self.db_pool.clone();
+        let (job_id, attempts) = (job.id, job.attempts);
+        let every = std::time::Duration::from_millis(self.config.lease_timeout_ms / 3);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(every).await;
+                let renewed = sqlx::query!(
+                    "UPDATE jobs SET locked_at = $3
+                    WHERE id = $1 AND attempts = $2 AND status = 'running'",
+                    job_id,
+                    attempts,
+                    Utc::now()
+                )
+                .execute(&db_pool)
+                .await;
+                if let Err(e) = renewed {
+                    tracing::error!(%job_id, error = %e, "Job worker failed to renew its lease");
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, job: ClaimedJob) {
+        let lease = self.keep_leased(&job);
+        let outcome = match job.kind.as_str() {
+            GUTENDEX_IMPORT => run_gutendex_import(&self.gutendex, job.id, &self.db_pool)
+                .await
+                .map(|summary| json!(summary)),
+            kind => Err(AppError::MalformedRequest(format!(
+                "Unknown job kind {}",
+                kind
+            ))),
+        };
+
+        lease.abort();
+
+        if let Err(e) = self.finish(&job, outcome).await {
+            tracing::error!(job_id = %job.id, error = %e, "Job worker failed to record a job");
+        }
+    }
+
+    // Only a job still running this attempt is updated, so a cancellation made meanwhile
+    // stands and a job taken back from this worker isn't overwritten
+    async fn finish(
+        &self,
+        job: &ClaimedJob,
+        outcome: Result<Value, AppError>,
+    ) -> Result<(), AppError> {
+        let now = Utc::now();
+        match outcome {
+            Ok(result) => {
+                sqlx::query!(
+                    "UPDATE jobs
+                    SET status = 'succeeded', result = $2, last_error = NULL, finished_at = $3,
+                        locked_at = NULL
+                    WHERE id = $1 AND attempts = $4 AND status = 'running'",
+                    job.id,
+                    result,
+                    now,
+                    job.attempts
+                )
+                .execute(&self.db_pool)
+                .await?;
+            }
+            Err(e) if job.attempts < job.max_attempts => {
+                let run_at = now + retry_backoff(self.config.retry_backoff_ms, job.attempts);
+                sqlx::query!(
+                    "UPDATE jobs SET status = 'queued', last_error = $2, run_at = $3, locked_at = NULL
+                    WHERE id = $1 AND attempts = $4 AND status = 'running'",
+                    job.id,
+                    e.to_string(),
+                    run_at,
+                    job.attempts
+                )
+                .execute(&self.db_pool)
+                .await?;
+            }
+            Err(e) => {
+                sqlx::query!(
+                    "UPDATE jobs SET status = 'failed', last_error = $2, finished_at = $3, locked_at = NULL
+                    WHERE id = $1 AND attempts = $4 AND status = 'running'",
+                    job.id,
+                    e.to_string(),
+                    now,
+                    job.attempts
+                )
+                .execute(&self.db_pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Doubles after every failed attempt: base, 2 * base, 4 * base, ...
+fn retry_backoff(base_ms: u64, attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    Duration::milliseconds(base_ms as i64 * 2_i64.pow(exponent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles() {
+        assert_eq!(retry_backoff(500, 1), Duration::milliseconds(500));
+        assert_eq!(retry_backoff(500, 2), Duration::milliseconds(1000));
+        assert_eq!(retry_backoff(500, 4), Duration::milliseconds(4000));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(retry_backoff(1, 100), retry_backoff(1, 17));
+    }
+}
@@ -1,4 +1,12 @@
+pub mod auth;
 pub mod configuration;
+pub mod errors;
+pub mod gutendex;
+pub mod jobs;
+pub mod mailer;
+pub mod marc;
+pub mod metadata;
+pub mod opds;
 pub mod routes;
 pub mod startup;
 pub mod validations;
@@ -0,0 +1,168 @@
+use crate::configuration::{MailTransportConfig, MailerConfig};
+use crate::errors::AppError;
+use async_trait::async_trait;
+use chrono::Utc;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+// A plain text message to one recipient
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+pub fn mailer(config: &MailerConfig) -> Result<Arc<dyn Mailer>, Error> {
+    let from: Mailbox = config
+        .from
+        .parse()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, format!("mailer.from: {}", e)))?;
+
+    match &config.transport {
+        MailTransportConfig::Smtp {
+            host,
+            port,
+            username,
+            password,
+        } => {
+            let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?
+                .port(*port)
+                .credentials(Credentials::new(
+                    username.clone(),
+                    password.expose_secret().clone(),
+                ))
+                .build();
+            Ok(Arc::new(SmtpMailer { transport, from }))
+        }
+        MailTransportConfig::File { directory } => Ok(Arc::new(FileMailer {
+            directory: directory.into(),
+            from: from.to_string(),
+        })),
+    }
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let to: Mailbox = email
+            .to
+            .parse()
+            .map_err(|e| AppError::Internal(format!("Bad recipient {}: {}", email.to, e)))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|_| AppError::Upstream("Email could not be sent".into()))?;
+        Ok(())
+    }
+}
+
+// Captures mail as one JSON file per message, named so they sort in the order they were sent
+pub struct FileMailer {
+    directory: PathBuf,
+    from: String,
+}
+
+#[derive(Serialize)]
+struct CapturedEmail<'a> {
+    from: &'a str,
+    #[serde(flatten)]
+    email: &'a Email,
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let internal = |e: Error| AppError::Internal(e.to_string());
+        let captured = CapturedEmail {
+            from: &self.from,
+            email: &email,
+        };
+        let name = format!(
+            "{}-{}.json",
+            Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+            Uuid::new_v4()
+        );
+
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .map_err(internal)?;
+        tokio::fs::write(
+            self.directory.join(name),
+            serde_json::to_vec_pretty(&captured).map_err(|e| internal(e.into()))?,
+        )
+        .await
+        .map_err(internal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_config(directory: &std::path::Path) -> MailerConfig {
+        MailerConfig {
+            from: "Midnight Library <library@example.com>".into(),
+            transport: MailTransportConfig::File {
+                directory: directory.to_string_lossy().into(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn file_mailer_captures_messages() {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let mailer = mailer(&file_config(&directory)).unwrap();
+        let email = Email {
+            to: "nora@example.com".into(),
+            subject: "Welcome".into(),
+            body: "Hello".into(),
+        };
+        mailer.send(email.clone()).await.unwrap();
+
+        let entry = std::fs::read_dir(&directory)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let captured: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(entry.path()).unwrap()).unwrap();
+        assert_eq!(captured["from"], "Midnight Library <library@example.com>");
+        assert_eq!(serde_json::from_value::<Email>(captured).unwrap(), email);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn sender_must_be_an_address() {
+        let mut config = file_config(&std::env::temp_dir());
+        config.from = "not an address".into();
+        assert!(mailer(&config).is_err());
+    }
+}
@@ -1,17 +1,25 @@
 use std::net::TcpListener;
 
 use midnight_library::{configuration::get_configuration, startup::run};
+use secrecy::ExposeSecret;
 use sqlx::PgPool;
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> Result<(), std::io::Error> {
+    // `RUST_LOG` picks what is logged, info and up by default
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
     let config = get_configuration().expect("Failed to read configuration.");
 
     let tcp_listener =
-        TcpListener::bind(config.server_address).expect("Failed to bind random port");
+        TcpListener::bind(&config.server_address).expect("Failed to bind random port");
 
-    let db_pool = PgPool::connect_lazy(config.database.database_url().as_str())
+    let db_pool = PgPool::connect_lazy(config.database.database_url().expose_secret())
         .expect("Failed to connect to Postgres.");
 
-    run(tcp_listener, db_pool)?.await
+    run(tcp_listener, db_pool, config)?.await
 }
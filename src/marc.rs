@@ -0,0 +1,580 @@
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::fmt;
+
+// ISO 2709 separators
+const SUBFIELD_DELIMITER: u8 = 0x1F;
+const FIELD_TERMINATOR: u8 = 0x1E;
+const RECORD_TERMINATOR: u8 = 0x1D;
+
+const LEADER_LENGTH: usize = 24;
+// New, language material, monograph, Unicode. Lengths and addresses are filled in on write.
+const DEFAULT_LEADER: &str = "00000nam a2200000 i 4500";
+pub const MARCXML_NAMESPACE: &str = "http://www.loc.gov/MARC21/slim";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarcError(pub String);
+
+impl fmt::Display for MarcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<quick_xml::Error> for MarcError {
+    fn from(e: quick_xml::Error) -> Self {
+        MarcError(format!("Unreadable MARCXML: {}", e))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    // 001 to 009, a bare value
+    Control {
+        tag: String,
+        value: String,
+    },
+    Data {
+        tag: String,
+        indicators: [char; 2],
+        subfields: Vec<(char, String)>,
+    },
+}
+
+impl Field {
+    pub fn tag(&self) -> &str {
+        match self {
+            Field::Control { tag, .. } | Field::Data { tag, .. } => tag,
+        }
+    }
+
+    pub fn subfield(&self, code: char) -> Option<&str> {
+        match self {
+            Field::Data { subfields, .. } => subfields
+                .iter()
+                .find(|(subfield, _)| *subfield == code)
+                .map(|(_, value)| value.as_str()),
+            Field::Control { .. } => None,
+        }
+    }
+
+    pub fn indicator(&self, position: usize) -> char {
+        match self {
+            Field::Data { indicators, .. } => indicators[position],
+            Field::Control { .. } => ' ',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub leader: String,
+    pub fields: Vec<Field>,
+}
+
+impl Default for Record {
+    fn default() -> Self {
+        Self {
+            leader: DEFAULT_LEADER.into(),
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl Record {
+    pub fn control(&mut self, tag: &str, value: &str) {
+        self.fields.push(Field::Control {
+            tag: tag.into(),
+            value: value.into(),
+        });
+    }
+
+    pub fn data(&mut self, tag: &str, indicators: [char; 2], subfields: &[(char, &str)]) {
+        self.fields.push(Field::Data {
+            tag: tag.into(),
+            indicators,
+            subfields: subfields
+                .iter()
+                .map(|(code, value)| (*code, value.to_string()))
+                .collect(),
+        });
+    }
+
+    pub fn fields<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Field> {
+        self.fields.iter().filter(move |field| field.tag() == tag)
+    }
+
+    // The first `code` subfield of the first `tag` field that has one
+    pub fn first<'a>(&'a self, tag: &'a str, code: char) -> Option<&'a str> {
+        self.fields(tag).find_map(|field| field.subfield(code))
+    }
+}
+
+// A .mrc file is records back to back, each saying how long it is
+pub fn read_iso2709(bytes: &[u8]) -> Result<Vec<Record>, MarcError> {
+    let mut records = Vec::new();
+    let mut rest = bytes;
+
+    loop {
+        // Some exports put a line break between records
+        while let Some((first, tail)) = rest.split_first() {
+            if !first.is_ascii_whitespace() {
+                break;
+            }
+            rest = tail;
+        }
+        if rest.is_empty() {
+            return Ok(records);
+        }
+
+        let length = number(rest, 0..5)
+            .filter(|length| (LEADER_LENGTH + 2..=rest.len()).contains(length))
+            .ok_or_else(|| bad_record(records.len(), "has an invalid record length"))?;
+        records.push(read_record(&rest[..length]).map_err(|e| bad_record(records.len(), &e.0))?);
+        rest = &rest[length..];
+    }
+}
+
+fn read_record(bytes: &[u8]) -> Result<Record, MarcError> {
+    if bytes.last() != Some(&RECORD_TERMINATOR) {
+        return Err(MarcError("does not end with a record terminator".into()));
+    }
+    let leader = std::str::from_utf8(&bytes[..LEADER_LENGTH])
+        .map_err(|_| MarcError("has a leader that is not text".into()))?;
+    let base_address = number(bytes, 12..17)
+        .filter(|base| (LEADER_LENGTH + 1..bytes.len()).contains(base))
+        .ok_or_else(|| MarcError("has an invalid base address".into()))?;
+
+    let directory = &bytes[LEADER_LENGTH..base_address - 1];
+    if directory.len() % 12 != 0 {
+        return Err(MarcError("has a malformed directory".into()));
+    }
+
+    let mut record = Record {
+        leader: leader.into(),
+        fields: Vec::new(),
+    };
+    for entry in directory.chunks(12) {
+        let tag = String::from_utf8_lossy(&entry[..3]).to_string();
+        let (length, start) = number(entry, 3..7)
+            .zip(number(entry, 7..12))
+            .ok_or_else(|| MarcError(format!("has a malformed directory entry for {}", tag)))?;
+        let data = bytes
+            .get(base_address + start..base_address + start + length)
+            .ok_or_else(|| MarcError(format!("has field {} past its end", tag)))?;
+        let data = data.strip_suffix(&[FIELD_TERMINATOR]).unwrap_or(data);
+
+        if tag.starts_with("00") {
+            record.fields.push(Field::Control {
+                tag,
+                value: String::from_utf8_lossy(data).to_string(),
+            });
+        } else {
+            record.fields.push(data_field(tag, data)?);
+        }
+    }
+
+    Ok(record)
+}
+
+fn data_field(tag: String, data: &[u8]) -> Result<Field, MarcError> {
+    if data.len() < 2 {
+        return Err(MarcError(format!("has field {} without indicators", tag)));
+    }
+    let indicators = [data[0] as char, data[1] as char];
+    let subfields = data[2..]
+        .split(|byte| *byte == SUBFIELD_DELIMITER)
+        .skip(1)
+        .filter_map(|subfield| {
+            let value = String::from_utf8_lossy(subfield);
+            let mut chars = value.chars();
+            chars.next().map(|code| (code, chars.as_str().to_string()))
+        })
+        .collect();
+
+    Ok(Field::Data {
+        tag,
+        indicators,
+        subfields,
+    })
+}
+
+pub fn write_iso2709(record: &Record) -> Result<Vec<u8>, MarcError> {
+    let mut directory = Vec::new();
+    let mut data = Vec::new();
+
+    for field in &record.fields {
+        if field.tag().len() != 3 || !field.tag().is_ascii() {
+            return Err(MarcError(format!(
+                "Field tag {:?} is not three characters",
+                field.tag()
+            )));
+        }
+        let start = data.len();
+        match field {
+            Field::Control { value, .. } => data.extend_from_slice(value.as_bytes()),
+            Field::Data {
+                indicators,
+                subfields,
+                ..
+            } => {
+                for indicator in indicators {
+                    data.push(ascii_byte(*indicator, "an indicator", field.tag())?);
+                }
+                for (code, value) in subfields {
+                    data.push(SUBFIELD_DELIMITER);
+                    data.push(ascii_byte(*code, "a subfield code", field.tag())?);
+                    data.extend_from_slice(value.as_bytes());
+                }
+            }
+        }
+        data.push(FIELD_TERMINATOR);
+
+        let length = data.len() - start;
+        if length > 9999 {
+            return Err(MarcError(format!(
+                "Field {} is too long for MARC",
+                field.tag()
+            )));
+        }
+        directory
+            .extend_from_slice(format!("{:0>3}{:04}{:05}", field.tag(), length, start).as_bytes());
+    }
+    directory.push(FIELD_TERMINATOR);
+
+    let base_address = LEADER_LENGTH + directory.len();
+    let length = base_address + data.len() + 1;
+    if length > 99999 {
+        return Err(MarcError("Record is too long for MARC".into()));
+    }
+
+    if !record.leader.is_ascii() {
+        return Err(MarcError("Leader is not ASCII".into()));
+    }
+    let mut leader = record.leader.as_bytes().to_vec();
+    leader.resize(LEADER_LENGTH, b' ');
+    leader[..5].copy_from_slice(format!("{:05}", length).as_bytes());
+    leader[9] = b'a';
+    leader[10..12].copy_from_slice(b"22");
+    leader[12..17].copy_from_slice(format!("{:05}", base_address).as_bytes());
+    leader[20..24].copy_from_slice(b"4500");
+
+    let mut bytes = leader;
+    bytes.extend(directory);
+    bytes.extend(data);
+    bytes.push(RECORD_TERMINATOR);
+    Ok(bytes)
+}
+
+// Reads a <collection> or a lone <record>, with or without a namespace prefix
+pub fn read_marcxml(xml: &str) -> Result<Vec<Record>, MarcError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut records = Vec::new();
+    let mut record: Option<Record> = None;
+    let mut field: Option<Field> = None;
+    let mut subfield: Option<char> = None;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                text.clear();
+                match element.local_name().as_ref() {
+                    b"record" => record = Some(Record::default()),
+                    b"controlfield" => {
+                        field = Some(Field::Control {
+                            tag: attribute(&element, "tag")?,
+                            value: String::new(),
+                        })
+                    }
+                    b"datafield" => {
+                        let indicator = |name| -> Result<char, MarcError> {
+                            Ok(attribute(&element, name)?.chars().next().unwrap_or(' '))
+                        };
+                        field = Some(Field::Data {
+                            tag: attribute(&element, "tag")?,
+                            indicators: [indicator("ind1")?, indicator("ind2")?],
+                            subfields: Vec::new(),
+                        })
+                    }
+                    b"subfield" => subfield = attribute(&element, "code")?.chars().next(),
+                    _ => {}
+                }
+            }
+            Event::Text(content) => text.push_str(&content.unescape()?),
+            Event::CData(content) => text.push_str(&String::from_utf8_lossy(&content)),
+            Event::End(element) => match element.local_name().as_ref() {
+                b"leader" => {
+                    if let Some(record) = record.as_mut() {
+                        record.leader = format!("{:<24.24}", text);
+                    }
+                }
+                b"subfield" => {
+                    if let (Some(Field::Data { subfields, .. }), Some(code)) =
+                        (field.as_mut(), subfield.take())
+                    {
+                        subfields.push((code, text.clone()));
+                    }
+                }
+                b"controlfield" | b"datafield" => {
+                    if let Some(mut field) = field.take() {
+                        if let Field::Control { value, .. } = &mut field {
+                            *value = text.clone();
+                        }
+                        if let Some(record) = record.as_mut() {
+                            record.fields.push(field);
+                        }
+                    }
+                }
+                b"record" => records.extend(record.take()),
+                _ => {}
+            },
+            Event::Eof if record.is_some() => {
+                return Err(MarcError(format!(
+                    "Record {} ends before its </record>",
+                    records.len() + 1
+                )))
+            }
+            Event::Eof => return Ok(records),
+            _ => {}
+        }
+    }
+}
+
+pub fn write_marcxml(records: &[Record]) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<collection xmlns=\"{}\">\n",
+        MARCXML_NAMESPACE
+    );
+    for record in records {
+        xml.push_str("  <record>\n");
+        xml.push_str(&format!(
+            "    <leader>{}</leader>\n",
+            escape(&record.leader)
+        ));
+        for field in &record.fields {
+            match field {
+                Field::Control { tag, value } => xml.push_str(&format!(
+                    "    <controlfield tag=\"{}\">{}</controlfield>\n",
+                    escape(tag),
+                    escape(value)
+                )),
+                Field::Data {
+                    tag,
+                    indicators,
+                    subfields,
+                } => {
+                    xml.push_str(&format!(
+                        "    <datafield tag=\"{}\" ind1=\"{}\" ind2=\"{}\">\n",
+                        escape(tag),
+                        escape(&indicators[0].to_string()),
+                        escape(&indicators[1].to_string())
+                    ));
+                    for (code, value) in subfields {
+                        xml.push_str(&format!(
+                            "      <subfield code=\"{}\">{}</subfield>\n",
+                            escape(&code.to_string()),
+                            escape(value)
+                        ));
+                    }
+                    xml.push_str("    </datafield>\n");
+                }
+            }
+        }
+        xml.push_str("  </record>\n");
+    }
+    xml.push_str("</collection>\n");
+    xml
+}
+
+// "Melville, Herman" to "Herman Melville", keeping a suffix such as "Jr." at the end
+pub fn direct_order(inverted: &str) -> String {
+    match inverted.split_once(", ") {
+        Some((last, rest)) => match rest.split_once(", ") {
+            Some((given, suffix)) => format!("{} {}, {}", given, last, suffix),
+            None => format!("{} {}", rest, last),
+        },
+        None => inverted.into(),
+    }
+}
+
+// "Herman Melville" to "Melville, Herman". Names that already carry a comma or are a single
+// word have no surname to move and come back as None.
+pub fn inverted_order(direct: &str) -> Option<String> {
+    if direct.contains(',') {
+        return None;
+    }
+    let (given, last) = direct.rsplit_once(' ')?;
+    Some(format!("{}, {}", last, given))
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<String, MarcError> {
+    element
+        .try_get_attribute(name)?
+        .map(|attribute| attribute.unescape_value().map(|value| value.to_string()))
+        .transpose()?
+        .ok_or_else(|| MarcError(format!("Unreadable MARCXML: a field has no {}", name)))
+}
+
+// Indicators and subfield codes take a single byte each in the record
+fn ascii_byte(character: char, what: &str, tag: &str) -> Result<u8, MarcError> {
+    match character.is_ascii() {
+        true => Ok(character as u8),
+        false => Err(MarcError(format!(
+            "Field {} has {} {:?} that is not ASCII",
+            tag, what, character
+        ))),
+    }
+}
+
+fn number(bytes: &[u8], range: std::ops::Range<usize>) -> Option<usize> {
+    std::str::from_utf8(bytes.get(range)?).ok()?.parse().ok()
+}
+
+fn bad_record(index: usize, problem: &str) -> MarcError {
+    MarcError(format!("Record {} {}", index + 1, problem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moby_dick() -> Record {
+        let mut record = Record::default();
+        record.control("001", "ocm00012345");
+        record.data("020", [' ', ' '], &[('a', "9780142437247 (pbk.)")]);
+        record.data(
+            "100",
+            ['1', ' '],
+            &[('a', "Melville, Herman,"), ('d', "1819-1891.")],
+        );
+        record.data(
+            "245",
+            ['1', '0'],
+            &[
+                ('a', "Moby-Dick, or, The whale /"),
+                ('c', "Herman Melville."),
+            ],
+        );
+        record.data("650", [' ', '0'], &[('a', "Whaling"), ('v', "Fiction.")]);
+        record
+    }
+
+    #[test]
+    fn iso2709_round_trip() {
+        let record = moby_dick();
+        let bytes = write_iso2709(&record).unwrap();
+
+        assert_eq!(&bytes[..5], format!("{:05}", bytes.len()).as_bytes());
+        assert_eq!(bytes.last(), Some(&RECORD_TERMINATOR));
+        let read = read_iso2709(&bytes).unwrap().remove(0);
+        assert_eq!(&read.leader[5..12], &record.leader[5..12]);
+        assert_eq!(read.fields, record.fields);
+    }
+
+    #[test]
+    fn iso2709_counts_bytes_not_characters() {
+        let mut record = Record::default();
+        record.data("245", ['0', '0'], &[('a', "Les Misérables — tome I")]);
+        let bytes = write_iso2709(&record).unwrap();
+
+        assert_eq!(
+            read_iso2709(&bytes).unwrap()[0].first("245", 'a'),
+            Some("Les Misérables — tome I")
+        );
+    }
+
+    #[test]
+    fn iso2709_rejects_non_ascii_codes() {
+        let mut record = Record::default();
+        record.data("245", ['0', '0'], &[('é', "Moby-Dick")]);
+        assert!(write_iso2709(&record).is_err());
+
+        let mut record = Record::default();
+        record.data("245", ['ö', '0'], &[('a', "Moby-Dick")]);
+        assert!(write_iso2709(&record).is_err());
+
+        let mut record = moby_dick();
+        record.leader = "00000nam é".into();
+        assert!(write_iso2709(&record).is_err());
+    }
+
+    #[test]
+    fn iso2709_pads_a_short_leader() {
+        let mut record = moby_dick();
+        record.leader = "00000nam".into();
+        let bytes = write_iso2709(&record).unwrap();
+
+        let read = read_iso2709(&bytes).unwrap().remove(0);
+        assert_eq!(&read.leader[5..9], "nam ");
+        assert_eq!(read.fields, record.fields);
+    }
+
+    #[test]
+    fn iso2709_several_records() {
+        let mut bytes = write_iso2709(&moby_dick()).unwrap();
+        bytes.push(b'\n');
+        bytes.extend(write_iso2709(&Record::default()).unwrap());
+
+        assert_eq!(read_iso2709(&bytes).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn iso2709_truncated() {
+        let bytes = write_iso2709(&moby_dick()).unwrap();
+        let error = read_iso2709(&bytes[..bytes.len() - 10]).unwrap_err();
+        assert_eq!(error.0, "Record 1 has an invalid record length");
+    }
+
+    #[test]
+    fn truncated_marcxml() {
+        let error = read_marcxml("<collection><record><leader>").unwrap_err();
+        assert_eq!(error.0, "Record 1 ends before its </record>");
+    }
+
+    #[test]
+    fn name_order() {
+        assert_eq!(direct_order("Melville, Herman"), "Herman Melville");
+        assert_eq!(
+            direct_order("King, Martin Luther, Jr."),
+            "Martin Luther King, Jr."
+        );
+        assert_eq!(
+            inverted_order("Herman Melville").as_deref(),
+            Some("Melville, Herman")
+        );
+        assert_eq!(inverted_order("Homer"), None);
+        assert_eq!(inverted_order("Martin Luther King, Jr."), None);
+    }
+
+    #[test]
+    fn marcxml_round_trip() {
+        let record = moby_dick();
+        let xml = write_marcxml(std::slice::from_ref(&record));
+
+        assert!(xml.contains(r#"<datafield tag="245" ind1="1" ind2="0">"#));
+        assert_eq!(read_marcxml(&xml).unwrap(), vec![record]);
+    }
+
+    #[test]
+    fn marcxml_with_prefix() {
+        let xml = r#"<marc:record xmlns:marc="http://www.loc.gov/MARC21/slim">
+            <marc:leader>00000nam a2200000 a 4500</marc:leader>
+            <marc:datafield tag="245" ind1="0" ind2="0">
+                <marc:subfield code="a">Tom &amp; Jerry</marc:subfield>
+            </marc:datafield>
+        </marc:record>"#;
+
+        let records = read_marcxml(xml).unwrap();
+        assert_eq!(records[0].first("245", 'a'), Some("Tom & Jerry"));
+    }
+
+    #[test]
+    fn marcxml_field_without_tag() {
+        let xml = r#"<record><datafield ind1="0" ind2="0"></datafield></record>"#;
+        assert!(read_marcxml(xml).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::configuration::MetadataConfig;
+use crate::errors::AppError;
+use crate::validations::book::ValidatedIsbn13;
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+// What a provider knows about an edition, enough to catalog it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IsbnRecord {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub publisher: Option<String>,
+    pub publication_year: Option<i32>,
+    pub page_count: Option<i32>,
+    pub subjects: Vec<String>,
+}
+
+#[async_trait]
+pub trait MetadataProvider: Send + Sync {
+    // None when the provider has never heard of the ISBN
+    async fn lookup_isbn(&self, isbn: &ValidatedIsbn13) -> Result<Option<IsbnRecord>, AppError>;
+}
+
+pub fn metadata_provider(
+    config: &MetadataConfig,
+    client: reqwest::Client,
+) -> Result<Arc<dyn MetadataProvider>, std::io::Error> {
+    match config {
+        MetadataConfig::OpenLibrary { base_url } => Ok(Arc::new(OpenLibraryProvider {
+            client,
+            base_url: base_url.trim_end_matches('/').into(),
+        })),
+        MetadataConfig::Fixture { path } => Ok(Arc::new(FixtureProvider::load(path)?)),
+    }
+}
+
+// Speaks the Open Library books API: GET /api/books?bibkeys=ISBN:...&format=json&jscmd=data
+pub struct OpenLibraryProvider {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[async_trait]
+impl MetadataProvider for OpenLibraryProvider {
+    async fn lookup_isbn(&self, isbn: &ValidatedIsbn13) -> Result<Option<IsbnRecord>, AppError> {
+        let bibkey = format!("ISBN:{}", isbn.as_ref());
+
+        let response = self
+            .client
+            .get(format!("{}/api/books", self.base_url))
+            .query(&[
+                ("bibkeys", bibkey.as_str()),
+                ("format", "json"),
+                ("jscmd", "data"),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| AppError::Upstream(e.to_string()))?;
+
+        let body = response
+            .json::<Value>()
+            .await
+            .map_err(|e| AppError::Upstream(e.to_string()))?;
+
+        Ok(open_library_record(&body[&bibkey]))
+    }
+}
+
+// Answers from a JSON file shaped like an Open Library response, keyed by "ISBN:<isbn 13>",
+// so lookups can be exercised without the network
+pub struct FixtureProvider {
+    records: Value,
+}
+
+impl FixtureProvider {
+    pub fn load(path: &str) -> Result<Self, std::io::Error> {
+        let records = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(Self { records })
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for FixtureProvider {
+    async fn lookup_isbn(&self, isbn: &ValidatedIsbn13) -> Result<Option<IsbnRecord>, AppError> {
+        Ok(open_library_record(
+            &self.records[format!("ISBN:{}", isbn.as_ref())],
+        ))
+    }
+}
+
+fn open_library_record(data: &Value) -> Option<IsbnRecord> {
+    let names = |field: &str| -> Vec<String> {
+        data[field]
+            .as_array()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|entry| entry["name"].as_str())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    Some(IsbnRecord {
+        title: data["title"].as_str()?.into(),
+        authors: names("authors"),
+        publisher: names("publishers").into_iter().next(),
+        publication_year: data["publish_date"].as_str().and_then(publication_year),
+        page_count: data["number_of_pages"]
+            .as_i64()
+            .and_then(|pages| i32::try_from(pages).ok()),
+        subjects: names("subjects"),
+    })
+}
+
+// Publish dates are free text ("1851", "October 18, 1851", "c1990"), the year is what we keep
+pub fn publication_year(date: &str) -> Option<i32> {
+    let year = Regex::new(r"(\d{4})").ok()?;
+    year.captures(date)?.get(1)?.as_str().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn open_library_data() {
+        let data = json!({
+            "title": "Moby Dick",
+            "authors": [{"name": "Herman Melville", "url": "https://openlibrary.org/authors/OL1A"}],
+            "publishers": [{"name": "Penguin Classics"}, {"name": "Penguin"}],
+            "publish_date": "March 2003",
+            "number_of_pages": 720,
+            "subjects": [{"name": "Whaling"}]
+        });
+
+        let record = open_library_record(&data).unwrap();
+        assert_eq!(record.title, "Moby Dick");
+        assert_eq!(record.authors, ["Herman Melville"]);
+        assert_eq!(record.publisher.as_deref(), Some("Penguin Classics"));
+        assert_eq!(record.publication_year, Some(2003));
+        assert_eq!(record.page_count, Some(720));
+        assert_eq!(record.subjects, ["Whaling"]);
+    }
+
+    #[test]
+    fn unknown_isbn() {
+        assert!(open_library_record(&Value::Null).is_none());
+    }
+
+    #[test]
+    fn publication_years() {
+        assert_eq!(publication_year("October 18, 1851"), Some(1851));
+        assert_eq!(publication_year("c1990"), Some(1990));
+        assert_eq!(publication_year("unknown"), None);
+    }
+}
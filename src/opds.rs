@@ -0,0 +1,278 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use quick_xml::escape::escape;
+
+pub const ACQUISITION_FEED: &str = "application/atom+xml;profile=opds-catalog;kind=acquisition";
+pub const NAVIGATION_FEED: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
+pub const OPENSEARCH_DESCRIPTION: &str = "application/opensearchdescription+xml";
+
+pub const REL_BORROW: &str = "http://opds-spec.org/acquisition/borrow";
+pub const REL_FACET: &str = "http://opds-spec.org/facet";
+pub const REL_SUBSECTION: &str = "subsection";
+
+const NAMESPACES: &str = r#"xmlns="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/terms/" xmlns:opds="http://opds-spec.org/2010/catalog" xmlns:thr="http://purl.org/syndication/thread/1.0""#;
+
+#[derive(Default)]
+pub struct Link {
+    pub rel: String,
+    pub href: String,
+    pub kind: String,
+    pub title: Option<String>,
+    pub facet_group: Option<String>,
+    pub active_facet: bool,
+    pub count: Option<i64>,
+}
+
+impl Link {
+    pub fn new(rel: &str, href: &str, kind: &str) -> Self {
+        Self {
+            rel: rel.into(),
+            href: href.into(),
+            kind: kind.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn titled(mut self, title: &str) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    // Narrows an acquisition feed down, clients group these by `facet_group`
+    pub fn facet(group: &str, title: &str, href: &str, count: Option<i64>, active: bool) -> Self {
+        Self {
+            facet_group: Some(group.into()),
+            active_facet: active,
+            count,
+            ..Self::new(REL_FACET, href, ACQUISITION_FEED).titled(title)
+        }
+    }
+
+    fn to_xml(&self, indent: &str) -> String {
+        let mut xml = format!(
+            r#"{}<link rel="{}" href="{}" type="{}""#,
+            indent,
+            escape(&self.rel),
+            escape(&self.href),
+            escape(&self.kind)
+        );
+        if let Some(title) = &self.title {
+            xml.push_str(&format!(r#" title="{}""#, escape(title)));
+        }
+        if let Some(group) = &self.facet_group {
+            xml.push_str(&format!(r#" opds:facetGroup="{}""#, escape(group)));
+        }
+        if self.active_facet {
+            xml.push_str(r#" opds:activeFacet="true""#);
+        }
+        if let Some(count) = self.count {
+            xml.push_str(&format!(r#" thr:count="{}""#, count));
+        }
+        xml.push_str("/>\n");
+        xml
+    }
+}
+
+pub struct Person {
+    pub name: String,
+    pub uri: Option<String>,
+}
+
+impl Person {
+    fn to_xml(&self, element: &str) -> String {
+        let uri = self
+            .uri
+            .as_ref()
+            .map(|uri| format!("<uri>{}</uri>", escape(uri)))
+            .unwrap_or_default();
+        format!(
+            "    <{element}><name>{}</name>{}</{element}>\n",
+            escape(&self.name),
+            uri
+        )
+    }
+}
+
+// A book in an acquisition feed, or a link to another feed in a navigation feed
+#[derive(Default)]
+pub struct Entry {
+    pub id: String,
+    pub title: String,
+    pub updated: DateTime<Utc>,
+    pub authors: Vec<Person>,
+    pub contributors: Vec<Person>,
+    pub categories: Vec<String>,
+    pub identifier: Option<String>,
+    pub publisher: Option<String>,
+    pub issued: Option<i32>,
+    pub language: Option<String>,
+    pub summary: Option<String>,
+    pub content: Option<String>,
+    pub links: Vec<Link>,
+}
+
+impl Entry {
+    fn to_xml(&self) -> String {
+        let mut xml = String::from("  <entry>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", escape(&self.title)));
+        xml.push_str(&format!("    <id>{}</id>\n", escape(&self.id)));
+        xml.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            timestamp(&self.updated)
+        ));
+        for author in &self.authors {
+            xml.push_str(&author.to_xml("author"));
+        }
+        for contributor in &self.contributors {
+            xml.push_str(&contributor.to_xml("contributor"));
+        }
+        for category in &self.categories {
+            xml.push_str(&format!(
+                r#"    <category term="{0}" label="{0}"/>"#,
+                escape(category)
+            ));
+            xml.push('\n');
+        }
+        let dublin_core = [
+            ("identifier", self.identifier.clone()),
+            ("publisher", self.publisher.clone()),
+            ("issued", self.issued.map(|year| year.to_string())),
+            ("language", self.language.clone()),
+        ];
+        for (element, value) in dublin_core {
+            if let Some(value) = value {
+                xml.push_str(&format!(
+                    "    <dc:{element}>{}</dc:{element}>\n",
+                    escape(&value)
+                ));
+            }
+        }
+        if let Some(summary) = &self.summary {
+            xml.push_str(&format!("    <summary>{}</summary>\n", escape(summary)));
+        }
+        if let Some(content) = &self.content {
+            xml.push_str(&format!(
+                "    <content type=\"text\">{}</content>\n",
+                escape(content)
+            ));
+        }
+        for link in &self.links {
+            xml.push_str(&link.to_xml("    "));
+        }
+        xml.push_str("  </entry>\n");
+        xml
+    }
+}
+
+pub struct Feed {
+    pub id: String,
+    pub title: String,
+    pub updated: DateTime<Utc>,
+    pub links: Vec<Link>,
+    pub entries: Vec<Entry>,
+}
+
+impl Feed {
+    pub fn to_xml(&self) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed {}>\n",
+            NAMESPACES
+        );
+        xml.push_str(&format!("  <id>{}</id>\n", escape(&self.id)));
+        xml.push_str(&format!("  <title>{}</title>\n", escape(&self.title)));
+        xml.push_str(&format!(
+            "  <updated>{}</updated>\n",
+            timestamp(&self.updated)
+        ));
+        xml.push_str("  <author><name>Midnight Library</name></author>\n");
+        for link in &self.links {
+            xml.push_str(&link.to_xml("  "));
+        }
+        for entry in &self.entries {
+            xml.push_str(&entry.to_xml());
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+}
+
+// Tells OPDS clients how to search, `base_url` being where the catalog is served from since
+// templates have to be absolute
+pub fn opensearch_description(base_url: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/">
+  <ShortName>Midnight Library</ShortName>
+  <Description>Search the Midnight Library catalog by title, author or genre</Description>
+  <InputEncoding>UTF-8</InputEncoding>
+  <OutputEncoding>UTF-8</OutputEncoding>
+  <Url type="{}" template="{}/opds/search?q={{searchTerms}}"/>
+  <Url type="application/json" template="{}/api/v1/search?q={{searchTerms}}"/>
+</OpenSearchDescription>
+"#,
+        escape(ACQUISITION_FEED),
+        escape(base_url),
+        escape(base_url)
+    )
+}
+
+fn timestamp(value: &DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn updated() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 21, 9, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn feed_with_a_book() {
+        let feed = Feed {
+            id: "http://localhost/opds/books".into(),
+            title: "Books & more".into(),
+            updated: updated(),
+            links: vec![
+                Link::new("self", "/opds/books", ACQUISITION_FEED),
+                Link::facet(
+                    "Genre",
+                    "Fiction",
+                    "/opds/books?genre=Fiction",
+                    Some(3),
+                    true,
+                ),
+            ],
+            entries: vec![Entry {
+                id: "urn:uuid:0b6c5d1e-0000-0000-0000-000000000000".into(),
+                title: "Moby-Dick".into(),
+                updated: updated(),
+                authors: vec![Person {
+                    name: "Herman Melville".into(),
+                    uri: None,
+                }],
+                identifier: Some("urn:isbn:9780142437247".into()),
+                ..Default::default()
+            }],
+        };
+
+        let xml = feed.to_xml();
+        assert!(xml.contains("<title>Books &amp; more</title>"));
+        assert!(xml.contains("<updated>2024-06-21T09:30:00Z</updated>"));
+        assert!(xml.contains(
+            r#"<link rel="http://opds-spec.org/facet" href="/opds/books?genre=Fiction" type="application/atom+xml;profile=opds-catalog;kind=acquisition" title="Fiction" opds:facetGroup="Genre" opds:activeFacet="true" thr:count="3"/>"#
+        ));
+        assert!(xml.contains("<author><name>Herman Melville</name></author>"));
+        assert!(xml.contains("<dc:identifier>urn:isbn:9780142437247</dc:identifier>"));
+        assert!(!xml.contains("<dc:publisher>"));
+    }
+
+    #[test]
+    fn opensearch_template() {
+        let description = opensearch_description("http://localhost:8080");
+        assert!(
+            description.contains(r#"template="http://localhost:8080/opds/search?q={searchTerms}""#)
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use crate::auth::{hash_password, hash_token, random_token, CurrentUser};
+use crate::configuration::AuthConfig;
+use crate::errors::AppError;
+use crate::mailer::{Email, Mailer};
+use crate::validations::user::ValidatedPassword;
+use crate::validations::{ValidationErrors, Violation};
+use actix_web::{
+    web::{Data, Json},
+    HttpResponse,
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+pub const VERIFY_EMAIL: &str = "verify_email";
+pub const RESET_PASSWORD: &str = "reset_password";
+
+// Replaces any unused token the user already had for `purpose`, so only the latest email works
+async fn issue_email_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    purpose: &str,
+    ttl: Duration,
+) -> Result<String, sqlx::Error> {
+    let token = random_token();
+    let now = Utc::now();
+
+    sqlx::query!(
+        "DELETE FROM email_tokens WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL",
+        user_id,
+        purpose
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO email_tokens (user_id, purpose, token_hash, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5)",
+        user_id,
+        purpose,
+        hash_token(&token),
+        now,
+        now + ttl
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(token)
+}
+
+// Spends a live token, answering whose it was
+async fn use_email_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    token: &str,
+    purpose: &str,
+) -> Result<Uuid, AppError> {
+    sqlx::query_scalar!(
+        "UPDATE email_tokens SET used_at = $3
+        WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > $3
+        RETURNING user_id",
+        hash_token(token),
+        purpose,
+        Utc::now()
+    )
+    .fetch_optional(&mut **transaction)
+    .await?
+    .ok_or_else(|| {
+        AppError::Validation(ValidationErrors::single(
+            "token",
+            Violation::not_allowed("is invalid, expired or already used"),
+        ))
+    })
+}
+
+// Sent from inside the caller's transaction, so a mail that can't go out takes the change
+// that needed it down too
+pub async fn send_verification(
+    transaction: &mut Transaction<'_, Postgres>,
+    mailer: &dyn Mailer,
+    config: &AuthConfig,
+    user_id: Uuid,
+    name: &str,
+    email: &str,
+) -> Result<(), AppError> {
+    let ttl = Duration::hours(config.verify_email_ttl_hours);
+    let token = issue_email_token(transaction, user_id, VERIFY_EMAIL, ttl).await?;
+
+    mailer
+        .send(Email {
+            to: email.into(),
+            subject: "Confirm your Midnight Library email".into(),
+            body: format!(
+                "Hello {},\n\n\
+                To confirm this is your email address, send this token to \
+                POST /api/v1/verify_email within {} hours:\n\n{}\n\n\
+                If you didn't sign up to the Midnight Library, ignore this email.\n",
+                name, config.verify_email_ttl_hours, token
+            ),
+        })
+        .await
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EmailTokenData {
+    pub token: String,
+}
+
+pub async fn verify_email(
+    input: Json<EmailTokenData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let mut transaction = db_pool.begin().await?;
+    let user_id = use_email_token(&mut transaction, &input.token, VERIFY_EMAIL).await?;
+    sqlx::query!(
+        "UPDATE users SET email_verified_at = $2 WHERE id = $1",
+        user_id,
+        Utc::now()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": "Email verified successfully!"})))
+}
+
+pub async fn resend_verification(
+    user: CurrentUser,
+    db_pool: Data<PgPool>,
+    mailer: Data<dyn Mailer>,
+    auth_config: Data<AuthConfig>,
+) -> Result<HttpResponse, AppError> {
+    let verified_at =
+        sqlx::query_scalar!("SELECT email_verified_at FROM users WHERE id = $1", user.id)
+            .fetch_one(db_pool.get_ref())
+            .await?;
+    if verified_at.is_some() {
+        return Err(AppError::Conflict("Email already verified".into()));
+    }
+
+    let mut transaction = db_pool.begin().await?;
+    send_verification(
+        &mut transaction,
+        mailer.get_ref(),
+        &auth_config,
+        user.id,
+        &user.name,
+        &user.email,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": "Verification email sent successfully!"})))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PasswordResetRequestData {
+    pub email: String,
+}
+
+// Answers the same whether or not the email is registered, so it can't be used to probe for
+// accounts. An address that replaced a confirmed one gets no reset until it is confirmed too,
+// or whoever changed the email could take the account over.
+pub async fn request_password_reset(
+    input: Json<PasswordResetRequestData>,
+    db_pool: Data<PgPool>,
+    mailer: Data<dyn Mailer>,
+    auth_config: Data<AuthConfig>,
+) -> Result<HttpResponse, AppError> {
+    let user = sqlx::query!(
+        "SELECT id, name, email FROM users
+        WHERE email = $1 AND (email_verified_at IS NOT NULL OR email_changed_at IS NULL)",
+        input.email
+    )
+    .fetch_optional(db_pool.get_ref())
+    .await?;
+
+    if let Some(user) = user {
+        let minutes = auth_config.password_reset_ttl_minutes;
+        let mut transaction = db_pool.begin().await?;
+        let token = issue_email_token(
+            &mut transaction,
+            user.id,
+            RESET_PASSWORD,
+            Duration::minutes(minutes),
+        )
+        .await?;
+        mailer
+            .send(Email {
+                to: user.email,
+                subject: "Reset your Midnight Library password".into(),
+                body: format!(
+                    "Hello {},\n\n\
+                    To choose a new password, send this token along with it to \
+                    POST /api/v1/password_reset/confirm within {} minutes:\n\n{}\n\n\
+                    If you didn't ask to reset your password, ignore this email.\n",
+                    user.name, minutes, token
+                ),
+            })
+            .await?;
+        transaction.commit().await?;
+    }
+
+    Ok(HttpResponse::Ok().json(json!({"message": "Password reset requested successfully!"})))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PasswordResetData {
+    pub token: String,
+    pub password: String,
+}
+
+// Receiving the email proves the address too, and every session is ended in case the old
+// password was how someone else got in
+pub async fn reset_password(
+    input: Json<PasswordResetData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let PasswordResetData { token, password } = input.into_inner();
+    let password = ValidatedPassword::new(password).map_err(|violation| {
+        AppError::Validation(ValidationErrors::single("password", violation))
+    })?;
+    let password_hash = hash_password(password.as_ref().into()).await?;
+
+    let mut transaction = db_pool.begin().await?;
+    let user_id = use_email_token(&mut transaction, &token, RESET_PASSWORD).await?;
+    sqlx::query!(
+        "UPDATE users
+        SET password_hash = $2, email_verified_at = COALESCE(email_verified_at, $3)
+        WHERE id = $1",
+        user_id,
+        password_hash,
+        Utc::now()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!("DELETE FROM sessions WHERE user_id = $1", user_id)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": "Password reset successfully!"})))
+}
@@ -0,0 +1,113 @@
+use crate::auth::{generate_api_key, hash_token, Admin};
+use crate::errors::AppError;
+use crate::validations::api_key::NewApiKey;
+use actix_web::{
+    web::{Data, Json, Path},
+    HttpResponse,
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// Enough of a key to tell it apart in listings without being able to use it
+const KEY_PREFIX_LENGTH: usize = 12;
+
+#[derive(Serialize, Deserialize)]
+pub struct NewApiKeyData {
+    pub name: String,
+    pub scope: String,
+    pub expires_in_days: Option<i64>,
+}
+
+// The key is only ever in this response, it's stored hashed
+pub async fn create_api_key(
+    admin: Admin,
+    input: Json<NewApiKeyData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let new_key: NewApiKey = input.0.try_into().map_err(AppError::Validation)?;
+    let key = generate_api_key();
+    let created_at = Utc::now();
+    let expires_at = new_key
+        .expires_in_days
+        .map(|days| created_at + Duration::days(days));
+
+    let api_key_id = sqlx::query_scalar!(
+        "INSERT INTO api_keys (name, prefix, key_hash, scope, created_by, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id",
+        new_key.name.as_ref(),
+        &key[..KEY_PREFIX_LENGTH],
+        hash_token(&key),
+        new_key.scope.as_str(),
+        admin.0.id,
+        created_at,
+        expires_at
+    )
+    .fetch_one(db_pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "API key created successfully!",
+        "api_key_id": api_key_id,
+        "key": key,
+        "scope": new_key.scope.as_str(),
+        "expires_at": expires_at
+    })))
+}
+
+pub async fn api_keys_index(
+    _admin: Admin,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let rows = sqlx::query!(
+        "SELECT id, name, prefix, scope, created_by, created_at, expires_at, revoked_at,
+            last_used_at
+        FROM api_keys
+        ORDER BY created_at"
+    )
+    .fetch_all(db_pool.get_ref())
+    .await?;
+
+    let api_keys: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|row| {
+            json!({
+                "id": row.id,
+                "name": row.name,
+                "prefix": row.prefix,
+                "scope": row.scope,
+                "created_by": row.created_by,
+                "created_at": row.created_at,
+                "expires_at": row.expires_at,
+                "revoked_at": row.revoked_at,
+                "last_used_at": row.last_used_at
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(api_keys))
+}
+
+pub async fn revoke_api_key(
+    _admin: Admin,
+    path: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let result = sqlx::query!(
+        "UPDATE api_keys SET revoked_at = $2 WHERE id = $1 AND revoked_at IS NULL",
+        Uuid::parse_str(&path.into_inner()).unwrap_or_default(),
+        Utc::now()
+    )
+    .execute(db_pool.get_ref())
+    .await?;
+
+    match result.rows_affected() == 1 {
+        true => Ok(HttpResponse::Ok().json(json!({"message": "API key revoked successfully!"}))),
+        false => Err(AppError::NotFound(
+            "Active API key to be revoked not found".into(),
+        )),
+    }
+}
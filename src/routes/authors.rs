@@ -1,19 +1,104 @@
-use crate::validations::author::NewAuthor;
+use crate::auth::Librarian;
+use crate::errors::AppError;
+use crate::routes::paginated_response;
+use crate::validations::author::{AuthorListQuery, AuthorPatch, AuthorSuggestQuery, NewAuthor};
+use crate::validations::book::AuthorRef;
+use crate::validations::page::Cursor;
 use actix_web::{
-    web::{Data, Json, Path},
-    HttpResponse,
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use sqlx::PgPool;
+use serde_json::json;
+use sqlx::{PgConnection, PgExecutor, PgPool};
 use uuid::Uuid;
 
-pub async fn authors_index(db_pool: Data<PgPool>) -> HttpResponse {
-    let rows = sqlx::query!("SELECT id, name, nationality, created_at FROM authors")
-        .fetch_all(db_pool.get_ref())
-        .await
-        .expect("Failed to fetch saved authors.");
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct AuthorListParams {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub sort: Option<String>,
+    pub nationality: Option<String>,
+}
+
+pub struct AuthorRow {
+    pub id: Uuid,
+    pub name: String,
+    pub nationality: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// One page of authors plus the first row of the next one, see `next_page`
+pub async fn list_authors(
+    query: &AuthorListQuery,
+    db_pool: &PgPool,
+) -> Result<Vec<AuthorRow>, AppError> {
+    let limit = *query.limit.as_ref();
+    let after_id = query.cursor.as_ref().map(|cursor| cursor.id);
+
+    // Each sort gets its own statement so the keyset condition can use the matching index
+    let rows = match query.sort.as_ref() {
+        "name" => {
+            sqlx::query_as!(
+                AuthorRow,
+                "SELECT id, name, nationality, created_at
+                FROM authors
+                WHERE ($1::TEXT IS NULL OR nationality = $1)
+                AND ($2::TEXT IS NULL OR (name, id) > ($2, $3))
+                ORDER BY name, id
+                LIMIT $4",
+                query.nationality,
+                query.cursor.as_ref().map(|cursor| cursor.key.as_str()),
+                after_id,
+                limit + 1
+            )
+            .fetch_all(db_pool)
+            .await?
+        }
+        _ => {
+            sqlx::query_as!(
+                AuthorRow,
+                "SELECT id, name, nationality, created_at
+                FROM authors
+                WHERE ($1::TEXT IS NULL OR nationality = $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR (created_at, id) > ($2, $3))
+                ORDER BY created_at, id
+                LIMIT $4",
+                query.nationality,
+                query.cursor.as_ref().and_then(|cursor| cursor.timestamp()),
+                after_id,
+                limit + 1
+            )
+            .fetch_all(db_pool)
+            .await?
+        }
+    };
+
+    Ok(rows)
+}
+
+pub fn author_cursor(row: &AuthorRow, sort: &str) -> Cursor {
+    let key = match sort {
+        "name" => row.name.clone(),
+        _ => row.created_at.to_rfc3339(),
+    };
+    Cursor::new(sort, key, row.id)
+}
+
+pub async fn authors_index(
+    request: HttpRequest,
+    params: Query<AuthorListParams>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let query: AuthorListQuery = params.0.clone().try_into().map_err(AppError::Validation)?;
+    let rows = list_authors(&query, &db_pool).await?;
+
+    let sort = query.sort.as_ref();
+    let (mut response, rows) =
+        paginated_response(&request, &params.0, rows, *query.limit.as_ref(), |row| {
+            author_cursor(row, sort)
+        });
 
     let authors: Vec<serde_json::Value> = rows
         .into_iter()
@@ -27,31 +112,153 @@ pub async fn authors_index(db_pool: Data<PgPool>) -> HttpResponse {
         })
         .collect();
 
-    HttpResponse::Ok().json(authors)
+    Ok(response.json(authors))
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct AuthorSuggestParams {
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AuthorMatch {
+    pub id: Uuid,
+    pub name: String,
+    pub nationality: String,
+    pub similarity: f32,
+}
+
+// How many near matches a failed author lookup offers back
+const CANDIDATE_LIMIT: i64 = 5;
+
+pub async fn suggest_authors(
+    params: Query<AuthorSuggestParams>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let query: AuthorSuggestQuery = params
+        .into_inner()
+        .try_into()
+        .map_err(AppError::Validation)?;
+
+    let authors =
+        similar_authors(query.q.as_ref(), *query.limit.as_ref(), db_pool.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(authors))
+}
+
+// Authors whose name contains something close to `term`, so both typos and partially typed
+// names find them
+pub async fn similar_authors(
+    term: &str,
+    limit: i64,
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<AuthorMatch>, AppError> {
+    let authors = sqlx::query_as!(
+        AuthorMatch,
+        r#"
+        SELECT id, name, nationality, word_similarity($1, name) AS "similarity!"
+        FROM authors
+        WHERE $1 <% name
+        ORDER BY word_similarity($1, name) DESC, name, id
+        LIMIT $2
+        "#,
+        term,
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(authors)
+}
+
+// The author a book payload points at. Names are matched exactly; a miss answers with the
+// closest names and a shared name with every author carrying it, so the client can retry
+// with an id. Runs on the caller's connection, so authors added earlier in the same
+// transaction are found.
+pub async fn resolve_author(
+    author: &AuthorRef,
+    connection: &mut PgConnection,
+) -> Result<Uuid, AppError> {
+    match author {
+        AuthorRef::Id(author_id) => {
+            let author = sqlx::query!("SELECT id FROM authors WHERE id = $1", author_id.as_ref())
+                .fetch_optional(&mut *connection)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Author not found".into()))?;
+            Ok(author.id)
+        }
+        AuthorRef::Name(name) => find_author_by_name(name.as_ref(), connection).await,
+    }
+}
+
+async fn find_author_by_name(name: &str, connection: &mut PgConnection) -> Result<Uuid, AppError> {
+    let mut authors = sqlx::query_as!(
+        AuthorMatch,
+        r#"
+        SELECT id, name, nationality, 1::REAL AS "similarity!"
+        FROM authors
+        WHERE name = $1
+        ORDER BY created_at, id
+        "#,
+        name
+    )
+    .fetch_all(&mut *connection)
+    .await?;
+
+    match authors.len() {
+        0 => Err(AppError::UnknownAuthor(
+            name.into(),
+            similar_authors(name, CANDIDATE_LIMIT, connection).await?,
+        )),
+        1 => Ok(authors.remove(0).id),
+        _ => Err(AppError::AmbiguousAuthor(name.into(), authors)),
+    }
 }
 
-pub async fn show_author(input: Path<String>, db_pool: Data<PgPool>) -> HttpResponse {
+// Imports credit authors by name alone, so unknown names are added to the catalog first.
+// Authors may share a name, so instead of a unique index, imports adding the same name take
+// turns on a lock held until their transaction ends
+pub async fn create_missing_author(
+    name: &str,
+    connection: &mut PgConnection,
+) -> Result<(), AppError> {
+    sqlx::query!("SELECT pg_advisory_xact_lock(hashtext($1))", name)
+        .execute(&mut *connection)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO authors (name, nationality, created_at)
+        SELECT $1, 'Unknown', $2
+        WHERE NOT EXISTS (SELECT 1 FROM authors WHERE name = $1)",
+        name,
+        Utc::now()
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn show_author(
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
     let author_id = input.into_inner();
 
-    match sqlx::query!(
+    let author = sqlx::query!(
         "SELECT id, name, nationality, created_at FROM authors WHERE id = $1",
         Uuid::parse_str(&author_id).unwrap_or_default()
     )
-    .fetch_one(db_pool.get_ref())
-    .await
-    {
-        Ok(author) => {
-            let author_json = json!({
-                "id": author.id,
-                "name": author.name,
-                "nationality": author.nationality,
-                "created_at": author.created_at
-            });
-
-            HttpResponse::Ok().json(author_json)
-        }
-        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
-    }
+    .fetch_optional(db_pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Author not found".into()))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "id": author.id,
+        "name": author.name,
+        "nationality": author.nationality,
+        "created_at": author.created_at
+    })))
 }
 
 #[derive(Serialize, Deserialize)]
@@ -60,13 +267,14 @@ pub struct NewAuthorData {
     pub nationality: String,
 }
 
-pub async fn create_author(input: Json<NewAuthorData>, db_pool: Data<PgPool>) -> HttpResponse {
-    let new_author: NewAuthor = match input.0.try_into() {
-        Ok(value) => value,
-        Err(error) => return HttpResponse::BadRequest().body(error),
-    };
+pub async fn create_author(
+    _staff: Librarian,
+    input: Json<NewAuthorData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let new_author: NewAuthor = input.0.try_into().map_err(AppError::Validation)?;
 
-    match sqlx::query!(
+    let record = sqlx::query!(
         "INSERT INTO authors (name, nationality, created_at)
         VALUES ($1, $2, $3)
         RETURNING id",
@@ -75,78 +283,83 @@ pub async fn create_author(input: Json<NewAuthorData>, db_pool: Data<PgPool>) ->
         Utc::now()
     )
     .fetch_one(db_pool.get_ref())
-    .await
-    {
-        Ok(record) => HttpResponse::Ok().json(json!({
-            "message": "Author created successfully!",
-            "author_id": record.id
-        })),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+    .await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Author created successfully!",
+        "author_id": record.id
+    })))
 }
 
-#[derive(Deserialize)]
-pub struct AuthorId {
-    id: String,
+#[derive(Serialize, Deserialize)]
+pub struct AuthorPatchData {
+    pub name: Option<String>,
+    pub nationality: Option<String>,
 }
 
-pub async fn delete_author(input: Json<AuthorId>, db_pool: Data<PgPool>) -> HttpResponse {
-    match sqlx::query!(
-        "DELETE FROM authors WHERE id = $1",
-        Uuid::parse_str(&input.id).unwrap_or_default(),
+pub async fn update_author(
+    _staff: Librarian,
+    path: Path<String>,
+    input: Json<NewAuthorData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let author: NewAuthor = input.0.try_into().map_err(AppError::Validation)?;
+
+    apply_author_patch(path.into_inner(), author.into(), db_pool).await
+}
+
+pub async fn patch_author(
+    _staff: Librarian,
+    path: Path<String>,
+    input: Json<AuthorPatchData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let patch: AuthorPatch = input.0.try_into().map_err(AppError::Validation)?;
+
+    apply_author_patch(path.into_inner(), patch, db_pool).await
+}
+
+async fn apply_author_patch(
+    author_id: String,
+    patch: AuthorPatch,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let result = sqlx::query!(
+        "UPDATE authors
+        SET name = COALESCE($2, name), nationality = COALESCE($3, nationality)
+        WHERE id = $1",
+        Uuid::parse_str(&author_id).unwrap_or_default(),
+        patch.name.as_ref().map(|name| name.as_ref()),
+        patch
+            .nationality
+            .as_ref()
+            .map(|nationality| nationality.as_ref())
     )
     .execute(db_pool.get_ref())
-    .await
-    {
-        Ok(result) => match result.rows_affected() == 1 {
-            true => HttpResponse::Ok().json(json!({"message": "Author deleted successfully!"})),
-            false => {
-                HttpResponse::NotFound().json(json!({"message": "Author to be deleted not found"}))
-            }
-        },
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    .await?;
+
+    match result.rows_affected() == 1 {
+        true => Ok(HttpResponse::Ok().json(json!({"message": "Author updated successfully!"}))),
+        false => Err(AppError::NotFound("Author to be updated not found".into())),
     }
 }
 
-pub async fn seed_authors(db_pool: Data<PgPool>) -> HttpResponse {
-    let client = reqwest::Client::new();
-
-    let response = client
-        .get("https://gutendex.com/books/")
-        .header("Content-Type", "application/json")
-        .send()
-        .await
-        .expect("Failed to execute request.");
-
-    let response_body = response
-        .json::<Value>()
-        .await
-        .expect("Failed to deserialize response body.");
-
-    if let Some(books) = response_body["results"].as_array() {
-        for book in books.iter() {
-            let first_author = book["authors"]
-                .as_array()
-                .and_then(|authors| authors.first())
-                .and_then(|attributes| attributes["name"].as_str())
-                .unwrap_or("Default Author");
-
-            match sqlx::query!(
-                "INSERT INTO authors (name, nationality, created_at)
-                VALUES ($1, $2, $3)
-                RETURNING id",
-                first_author,
-                "Custom nationality",
-                Utc::now()
-            )
-            .fetch_one(db_pool.get_ref())
-            .await
-            {
-                Ok(value) => println!("{:?}", value),
-                Err(e) => println!("{e}"),
-            }
-        }
-    }
+pub async fn delete_author(
+    _staff: Librarian,
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let author_id = input.into_inner();
 
-    HttpResponse::Ok().json(response_body)
+    let result = sqlx::query!(
+        "DELETE FROM authors WHERE id = $1",
+        Uuid::parse_str(&author_id).unwrap_or_default(),
+    )
+    .execute(db_pool.get_ref())
+    .await?;
+
+    match result.rows_affected() == 1 {
+        true => Ok(HttpResponse::Ok().json(json!({"message": "Author deleted successfully!"}))),
+        false => Err(AppError::NotFound("Author to be deleted not found".into())),
+    }
 }
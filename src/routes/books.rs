@@ -1,145 +1,647 @@
 use actix_web::{
-    web::{Data, Json, Path},
-    HttpResponse,
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::validations::book::NewBook;
+use crate::auth::Librarian;
+use crate::errors::AppError;
+use crate::routes::{paginated_response, resolve_author, still_circulating};
+use crate::validations::book::{
+    BookListQuery, BookPatch, NewBook, NewContributor, ValidatedIsbn13,
+};
+use crate::validations::page::Cursor;
 
-pub async fn books_index(db_pool: Data<PgPool>) -> HttpResponse {
-    let rows = sqlx::query!(
-        r#"
-        SELECT
-            books.id,
-            books.title,
-            authors.name AS "authors_name",
-            books.genre,
-            books.created_at
-        FROM books
-        JOIN authors ON books.author_id = authors.id
-        "#
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct BookListParams {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub sort: Option<String>,
+    pub genre: Option<String>,
+    pub author_id: Option<String>,
+}
+
+pub struct BookRow {
+    pub id: Uuid,
+    pub title: String,
+    pub genre: String,
+    pub created_at: DateTime<Utc>,
+    pub isbn_13: Option<String>,
+    pub publisher: Option<String>,
+    pub publication_year: Option<i32>,
+    pub language: Option<String>,
+    pub page_count: Option<i32>,
+    pub edition: Option<String>,
+    pub description: Option<String>,
+    pub total_copies: i64,
+    pub available_copies: i64,
+}
+
+impl BookRow {
+    fn into_json(self, contributors: Vec<Contributor>) -> serde_json::Value {
+        let isbn_10 = self
+            .isbn_13
+            .clone()
+            .and_then(|isbn| ValidatedIsbn13::new(isbn).ok())
+            .and_then(|isbn| isbn.to_isbn_10())
+            .map(|isbn| isbn.as_ref().to_string());
+
+        let mut book = book_json(self.id, self.title, self.genre, contributors);
+        book["isbn_13"] = json!(self.isbn_13);
+        book["isbn_10"] = json!(isbn_10);
+        book["publisher"] = json!(self.publisher);
+        book["publication_year"] = json!(self.publication_year);
+        book["language"] = json!(self.language);
+        book["page_count"] = json!(self.page_count);
+        book["edition"] = json!(self.edition);
+        book["description"] = json!(self.description);
+        book["available_copies"] = json!(self.available_copies);
+        book["total_copies"] = json!(self.total_copies);
+        book["created_at"] = json!(self.created_at);
+        book
+    }
+}
+
+#[derive(Serialize)]
+pub struct Contributor {
+    pub author_id: Uuid,
+    pub name: String,
+    pub role: String,
+}
+
+struct ContributorRow {
+    book_id: Uuid,
+    author_id: Uuid,
+    name: String,
+    role: String,
+}
+
+// Contributors of several books at once, in credit order, so listings need one extra query
+// rather than one per book
+pub async fn book_contributors(
+    book_ids: &[Uuid],
+    db_pool: &PgPool,
+) -> Result<HashMap<Uuid, Vec<Contributor>>, AppError> {
+    let rows = sqlx::query_as!(
+        ContributorRow,
+        "SELECT book_contributors.book_id, authors.id AS author_id, authors.name, book_contributors.role
+        FROM book_contributors
+        JOIN authors ON authors.id = book_contributors.author_id
+        WHERE book_contributors.book_id = ANY($1)
+        ORDER BY book_contributors.book_id, book_contributors.position",
+        book_ids
     )
-    .fetch_all(db_pool.get_ref())
-    .await
-    .expect("Failed to fetch saved books.");
+    .fetch_all(db_pool)
+    .await?;
+
+    let mut contributors: HashMap<Uuid, Vec<Contributor>> = HashMap::new();
+    for row in rows {
+        contributors
+            .entry(row.book_id)
+            .or_default()
+            .push(Contributor {
+                author_id: row.author_id,
+                name: row.name,
+                role: row.role,
+            });
+    }
+
+    Ok(contributors)
+}
+
+// Books keep a single "author" for older clients: whoever is credited first
+pub fn book_json(
+    id: Uuid,
+    title: String,
+    genre: String,
+    contributors: Vec<Contributor>,
+) -> serde_json::Value {
+    json!({
+        "id": id,
+        "title": title,
+        "author": contributors.first().map(|contributor| contributor.name.clone()),
+        "contributors": contributors,
+        "genre": genre
+    })
+}
+
+// One page of books plus the first row of the next one, see `next_page`
+pub async fn list_books(query: &BookListQuery, db_pool: &PgPool) -> Result<Vec<BookRow>, AppError> {
+    let limit = *query.limit.as_ref();
+    let author_id = query
+        .author_id
+        .as_ref()
+        .map(|author_id| *author_id.as_ref());
+    let after_id = query.cursor.as_ref().map(|cursor| cursor.id);
+
+    // Each sort gets its own statement so the keyset condition can use the matching index
+    let rows = match query.sort.as_ref() {
+        "title" => {
+            sqlx::query_as!(
+                BookRow,
+                r#"
+                SELECT
+                    books.id,
+                    books.title,
+                    books.genre,
+                    books.created_at,
+                    books.isbn_13,
+                    books.publisher,
+                    books.publication_year,
+                    books.language,
+                    books.page_count,
+                    books.edition,
+                    books.description,
+                    (SELECT COUNT(*) FROM items WHERE items.book_id = books.id) AS "total_copies!",
+                    (
+                        SELECT COUNT(*) FROM items
+                        WHERE items.book_id = books.id AND items.status = 'available'
+                    ) AS "available_copies!"
+                FROM books
+                WHERE ($1::TEXT IS NULL OR books.genre = $1)
+                AND (
+                    $2::UUID IS NULL OR EXISTS (
+                        SELECT 1 FROM book_contributors
+                        WHERE book_contributors.book_id = books.id AND book_contributors.author_id = $2
+                    )
+                )
+                AND ($3::TEXT IS NULL OR (books.title, books.id) > ($3, $4))
+                ORDER BY books.title, books.id
+                LIMIT $5
+                "#,
+                query.genre,
+                author_id,
+                query.cursor.as_ref().map(|cursor| cursor.key.as_str()),
+                after_id,
+                limit + 1
+            )
+            .fetch_all(db_pool)
+            .await?
+        }
+        _ => {
+            sqlx::query_as!(
+                BookRow,
+                r#"
+                SELECT
+                    books.id,
+                    books.title,
+                    books.genre,
+                    books.created_at,
+                    books.isbn_13,
+                    books.publisher,
+                    books.publication_year,
+                    books.language,
+                    books.page_count,
+                    books.edition,
+                    books.description,
+                    (SELECT COUNT(*) FROM items WHERE items.book_id = books.id) AS "total_copies!",
+                    (
+                        SELECT COUNT(*) FROM items
+                        WHERE items.book_id = books.id AND items.status = 'available'
+                    ) AS "available_copies!"
+                FROM books
+                WHERE ($1::TEXT IS NULL OR books.genre = $1)
+                AND (
+                    $2::UUID IS NULL OR EXISTS (
+                        SELECT 1 FROM book_contributors
+                        WHERE book_contributors.book_id = books.id AND book_contributors.author_id = $2
+                    )
+                )
+                AND ($3::TIMESTAMPTZ IS NULL OR (books.created_at, books.id) > ($3, $4))
+                ORDER BY books.created_at, books.id
+                LIMIT $5
+                "#,
+                query.genre,
+                author_id,
+                query.cursor.as_ref().and_then(|cursor| cursor.timestamp()),
+                after_id,
+                limit + 1
+            )
+            .fetch_all(db_pool)
+            .await?
+        }
+    };
+
+    Ok(rows)
+}
+
+pub fn book_cursor(row: &BookRow, sort: &str) -> Cursor {
+    let key = match sort {
+        "title" => row.title.clone(),
+        _ => row.created_at.to_rfc3339(),
+    };
+    Cursor::new(sort, key, row.id)
+}
+
+pub async fn books_index(
+    request: HttpRequest,
+    params: Query<BookListParams>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let query: BookListQuery = params.0.clone().try_into().map_err(AppError::Validation)?;
+    let rows = list_books(&query, &db_pool).await?;
+
+    let sort = query.sort.as_ref();
+    let (mut response, rows) =
+        paginated_response(&request, &params.0, rows, *query.limit.as_ref(), |row| {
+            book_cursor(row, sort)
+        });
+
+    let book_ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+    let mut contributors = book_contributors(&book_ids, &db_pool).await?;
 
     let books: Vec<serde_json::Value> = rows
         .into_iter()
         .map(|row| {
-            json!({
-                "id": row.id,
-                "title": row.title,
-                "author": row.authors_name,
-                "genre": row.genre,
-                "created_at": row.created_at
-            })
+            let book_contributors = contributors.remove(&row.id).unwrap_or_default();
+            row.into_json(book_contributors)
         })
         .collect();
 
-    HttpResponse::Ok().json(books)
+    Ok(response.json(books))
 }
 
-pub async fn show_book(info: Path<String>, db_pool: Data<PgPool>) -> HttpResponse {
+pub async fn show_book(
+    info: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
     let book_id = info.into_inner();
-    match sqlx::query!(
+    let book = sqlx::query_as!(
+        BookRow,
         r#"
         SELECT
             books.id,
             books.title,
-            authors.name AS "authors_name",
             books.genre,
-            books.created_at
+            books.created_at,
+            books.isbn_13,
+            books.publisher,
+            books.publication_year,
+            books.language,
+            books.page_count,
+            books.edition,
+            books.description,
+            (SELECT COUNT(*) FROM items WHERE items.book_id = books.id) AS "total_copies!",
+            (
+                SELECT COUNT(*) FROM items
+                WHERE items.book_id = books.id AND items.status = 'available'
+            ) AS "available_copies!"
         FROM books
-        JOIN authors ON books.author_id = authors.id
         WHERE books.id = $1
         "#,
         Uuid::parse_str(&book_id).unwrap_or_default(),
     )
-    .fetch_one(db_pool.get_ref())
-    .await
-    {
-        Ok(book) => {
-            let book_json = json!({
-                "id": book.id,
-                "title": book.title,
-                "author": book.authors_name,
-                "genre": book.genre,
-                "created_at": book.created_at
-            });
+    .fetch_optional(db_pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Book not found".into()))?;
 
-            HttpResponse::Ok().json(book_json)
-        }
-        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
-    }
+    let contributors = book_contributors(&[book.id], &db_pool)
+        .await?
+        .remove(&book.id)
+        .unwrap_or_default();
+
+    Ok(HttpResponse::Ok().json(book.into_json(contributors)))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ContributorData {
+    pub author_id: Option<String>,
+    pub author: Option<String>,
+    pub role: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BookMetadataData {
+    pub isbn: Option<String>,
+    pub publisher: Option<String>,
+    pub publication_year: Option<i32>,
+    pub language: Option<String>,
+    pub page_count: Option<i32>,
+    pub edition: Option<String>,
+    pub description: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NewBookData {
     pub title: String,
-    pub author: String,
+    pub author: Option<String>,
+    pub author_id: Option<String>,
+    pub contributors: Option<Vec<ContributorData>>,
     pub genre: String,
+    #[serde(flatten)]
+    pub metadata: BookMetadataData,
 }
 
-pub async fn create_book(input: Json<NewBookData>, db_pool: Data<PgPool>) -> HttpResponse {
-    let new_book: NewBook = match input.0.try_into() {
-        Ok(value) => value,
-        Err(e) => return HttpResponse::BadRequest().body(e),
-    };
+pub async fn create_book(
+    _staff: Librarian,
+    input: Json<NewBookData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let new_book: NewBook = input.0.try_into().map_err(AppError::Validation)?;
+    let book_id = insert_book(&new_book, None, &db_pool).await?;
 
-    let author = match sqlx::query!(
-        "SELECT id, name, nationality, created_at FROM authors WHERE name = $1",
-        new_book.author.as_ref()
-    )
-    .fetch_one(db_pool.get_ref())
-    .await
-    {
-        Ok(author) => author,
-        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
-    };
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Book created successfully!",
+        "book_id": book_id
+    })))
+}
 
-    match sqlx::query!(
-        "INSERT INTO books (title, genre, author_id, created_at)
-        VALUES ($1, $2, $3, $4)
+pub async fn insert_book(
+    new_book: &NewBook,
+    gutenberg_id: Option<i32>,
+    db_pool: &PgPool,
+) -> Result<Uuid, AppError> {
+    let mut transaction = db_pool.begin().await?;
+    let book_id = insert_book_in(&mut transaction, new_book, gutenberg_id).await?;
+    transaction.commit().await?;
+
+    Ok(book_id)
+}
+
+pub async fn insert_book_in(
+    transaction: &mut Transaction<'_, Postgres>,
+    new_book: &NewBook,
+    gutenberg_id: Option<i32>,
+) -> Result<Uuid, AppError> {
+    let contributors = resolve_contributors(&new_book.contributors, transaction).await?;
+
+    let metadata = &new_book.metadata;
+    let record = sqlx::query!(
+        "INSERT INTO books (
+            title, genre, isbn_13, publisher, publication_year, language, page_count, edition,
+            description, gutenberg_id, created_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
         RETURNING id",
         new_book.title.as_ref(),
         new_book.genre.as_ref(),
-        author.id,
+        metadata.isbn.as_ref().map(|isbn| isbn.as_ref()),
+        metadata
+            .publisher
+            .as_ref()
+            .map(|publisher| publisher.as_ref()),
+        metadata
+            .publication_year
+            .as_ref()
+            .map(|year| *year.as_ref()),
+        metadata.language.as_ref().map(|language| language.as_ref()),
+        metadata
+            .page_count
+            .as_ref()
+            .map(|page_count| *page_count.as_ref()),
+        metadata.edition.as_ref().map(|edition| edition.as_ref()),
+        metadata
+            .description
+            .as_ref()
+            .map(|description| description.as_ref()),
+        gutenberg_id,
         Utc::now()
     )
-    .fetch_one(db_pool.get_ref())
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(book_conflict)?;
+
+    replace_contributors(transaction, record.id, &contributors).await?;
+
+    Ok(record.id)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BookPatchData {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub author_id: Option<String>,
+    pub contributors: Option<Vec<ContributorData>>,
+    pub genre: Option<String>,
+    #[serde(flatten)]
+    pub metadata: BookMetadataData,
+}
+
+pub async fn update_book(
+    _staff: Librarian,
+    path: Path<String>,
+    input: Json<NewBookData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let book: NewBook = input.0.try_into().map_err(AppError::Validation)?;
+    let mut transaction = db_pool.begin().await?;
+    let contributors = resolve_contributors(&book.contributors, &mut transaction).await?;
+
+    // Replaces the whole record, so metadata left out of the payload is cleared
+    let metadata = &book.metadata;
+    let book_id = sqlx::query_scalar!(
+        "UPDATE books
+        SET
+            title = $2,
+            genre = $3,
+            isbn_13 = $4,
+            publisher = $5,
+            publication_year = $6,
+            language = $7,
+            page_count = $8,
+            edition = $9,
+            description = $10
+        WHERE id = $1
+        RETURNING id",
+        Uuid::parse_str(&path.into_inner()).unwrap_or_default(),
+        book.title.as_ref(),
+        book.genre.as_ref(),
+        metadata.isbn.as_ref().map(|isbn| isbn.as_ref()),
+        metadata
+            .publisher
+            .as_ref()
+            .map(|publisher| publisher.as_ref()),
+        metadata
+            .publication_year
+            .as_ref()
+            .map(|year| *year.as_ref()),
+        metadata.language.as_ref().map(|language| language.as_ref()),
+        metadata
+            .page_count
+            .as_ref()
+            .map(|page_count| *page_count.as_ref()),
+        metadata.edition.as_ref().map(|edition| edition.as_ref()),
+        metadata
+            .description
+            .as_ref()
+            .map(|description| description.as_ref())
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(book_conflict)?
+    .ok_or_else(|| AppError::NotFound("Book to be updated not found".into()))?;
+    replace_contributors(&mut transaction, book_id, &contributors).await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": "Book updated successfully!"})))
+}
+
+pub async fn patch_book(
+    _staff: Librarian,
+    path: Path<String>,
+    input: Json<BookPatchData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let patch: BookPatch = input.0.try_into().map_err(AppError::Validation)?;
+
+    apply_book_patch(path.into_inner(), patch, db_pool).await
+}
+
+async fn apply_book_patch(
+    book_id: String,
+    patch: BookPatch,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let mut transaction = db_pool.begin().await?;
+
+    let contributors = match &patch.contributors {
+        Some(contributors) => Some(resolve_contributors(contributors, &mut transaction).await?),
+        None => None,
+    };
+
+    let metadata = &patch.metadata;
+    let book = sqlx::query!(
+        "UPDATE books
+        SET
+            title = COALESCE($2, title),
+            genre = COALESCE($3, genre),
+            isbn_13 = COALESCE($4, isbn_13),
+            publisher = COALESCE($5, publisher),
+            publication_year = COALESCE($6, publication_year),
+            language = COALESCE($7, language),
+            page_count = COALESCE($8, page_count),
+            edition = COALESCE($9, edition),
+            description = COALESCE($10, description)
+        WHERE id = $1
+        RETURNING id",
+        Uuid::parse_str(&book_id).unwrap_or_default(),
+        patch.title.as_ref().map(|title| title.as_ref()),
+        patch.genre.as_ref().map(|genre| genre.as_ref()),
+        metadata.isbn.as_ref().map(|isbn| isbn.as_ref()),
+        metadata
+            .publisher
+            .as_ref()
+            .map(|publisher| publisher.as_ref()),
+        metadata
+            .publication_year
+            .as_ref()
+            .map(|year| *year.as_ref()),
+        metadata.language.as_ref().map(|language| language.as_ref()),
+        metadata
+            .page_count
+            .as_ref()
+            .map(|page_count| *page_count.as_ref()),
+        metadata.edition.as_ref().map(|edition| edition.as_ref()),
+        metadata
+            .description
+            .as_ref()
+            .map(|description| description.as_ref())
+    )
+    .fetch_optional(&mut *transaction)
     .await
-    {
-        Ok(record) => HttpResponse::Ok().json(json!({
-            "message": "Book created successfully!",
-            "book_id": record.id
-        })),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    .map_err(book_conflict)?
+    .ok_or_else(|| AppError::NotFound("Book to be updated not found".into()))?;
+
+    if let Some(contributors) = &contributors {
+        replace_contributors(&mut transaction, book.id, contributors).await?;
     }
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": "Book updated successfully!"})))
 }
 
-#[derive(Deserialize)]
-pub struct BookId {
-    id: String,
+// Author ids and roles, in credit order
+async fn resolve_contributors(
+    contributors: &[NewContributor],
+    connection: &mut PgConnection,
+) -> Result<Vec<(Uuid, String)>, AppError> {
+    let mut resolved = Vec::new();
+    for contributor in contributors {
+        resolved.push((
+            resolve_author(&contributor.author, connection).await?,
+            contributor.role.as_ref().to_string(),
+        ));
+    }
+
+    Ok(resolved)
 }
 
-pub async fn delete_book(input: Json<BookId>, db_pool: Data<PgPool>) -> HttpResponse {
-    match sqlx::query!(
+// Swaps the book's whole contributor list for the given one
+async fn replace_contributors(
+    transaction: &mut Transaction<'_, Postgres>,
+    book_id: Uuid,
+    contributors: &[(Uuid, String)],
+) -> Result<(), AppError> {
+    let (author_ids, roles): (Vec<Uuid>, Vec<String>) = contributors.iter().cloned().unzip();
+    let positions: Vec<i32> = (0..contributors.len() as i32).collect();
+
+    sqlx::query!("DELETE FROM book_contributors WHERE book_id = $1", book_id)
+        .execute(&mut **transaction)
+        .await?;
+
+    sqlx::query!(
+        "INSERT INTO book_contributors (book_id, author_id, role, position)
+        SELECT $1, * FROM UNNEST($2::UUID[], $3::TEXT[], $4::INT[])",
+        book_id,
+        &author_ids,
+        &roles,
+        &positions
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(duplicate_contributor)?;
+
+    Ok(())
+}
+
+fn book_conflict(e: sqlx::Error) -> AppError {
+    let constraint = e
+        .as_database_error()
+        .and_then(|e| e.constraint())
+        .map(String::from);
+    match (AppError::from(e), constraint.as_deref()) {
+        (AppError::Conflict(_), Some("books_gutenberg_id_key")) => {
+            AppError::Conflict("This Project Gutenberg book was already imported".into())
+        }
+        (AppError::Conflict(_), _) => {
+            AppError::Conflict("A book with this ISBN already exists".into())
+        }
+        (e, _) => e,
+    }
+}
+
+fn duplicate_contributor(e: sqlx::Error) -> AppError {
+    match AppError::from(e) {
+        AppError::Conflict(_) => {
+            AppError::Conflict("The same author is credited twice in the same role".into())
+        }
+        e => e,
+    }
+}
+
+pub async fn delete_book(
+    _staff: Librarian,
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let book_id = input.into_inner();
+
+    let result = sqlx::query!(
         "DELETE FROM books WHERE id = $1",
-        Uuid::parse_str(&input.id).unwrap_or_default(),
+        Uuid::parse_str(&book_id).unwrap_or_default(),
     )
     .execute(db_pool.get_ref())
     .await
-    {
-        Ok(result) => match result.rows_affected() == 1 {
-            true => HttpResponse::Ok().json(json!({"message": "Book deleted successfully!"})),
-            false => {
-                HttpResponse::NotFound().json(json!({"message": "Book to be deleted not found"}))
-            }
-        },
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    .map_err(|e| still_circulating(e, "Book"))?;
+
+    match result.rows_affected() == 1 {
+        true => Ok(HttpResponse::Ok().json(json!({"message": "Book deleted successfully!"}))),
+        false => Err(AppError::NotFound("Book to be deleted not found".into())),
     }
 }
@@ -0,0 +1,461 @@
+use actix_web::{
+    web::{Bytes, Data, Query},
+    HttpResponse,
+};
+use futures_util::stream;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Acquire, PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::auth::Librarian;
+use crate::errors::AppError;
+use crate::routes::{
+    insert_book_in, AuthorMatch, BookMetadataData, ContributorData, NewAuthorData, NewBookData,
+};
+use crate::validations::{
+    author::NewAuthor,
+    book::{NewBook, CONTRIBUTOR_ROLES},
+    ValidationErrors, Violation,
+};
+
+pub const MAX_CSV_BYTES: usize = 16 * 1024 * 1024;
+pub const MAX_IMPORT_ROWS: usize = 10_000;
+// Rows fetched per round trip while streaming an export
+const EXPORT_CHUNK: i64 = 500;
+
+const BOOK_COLUMNS: [&str; 11] = [
+    "id",
+    "title",
+    "author",
+    "genre",
+    "isbn",
+    "publisher",
+    "publication_year",
+    "language",
+    "page_count",
+    "edition",
+    "description",
+];
+const AUTHOR_COLUMNS: [&str; 3] = ["id", "name", "nationality"];
+
+type ExportError = Box<dyn std::error::Error>;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct CsvImportParams {
+    pub dry_run: Option<bool>,
+}
+
+// Why one row of an upload was turned down, `row` being its line in a CSV file (the header is
+// 1) or its position among MARC records
+#[derive(Serialize, Debug)]
+pub struct RowError {
+    pub row: u64,
+    pub code: &'static str,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<ValidationErrors>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<Vec<AuthorMatch>>,
+}
+
+impl RowError {
+    // A failing database is not the row's fault, so that aborts the import instead
+    pub fn new(row: u64, e: AppError) -> Result<Self, AppError> {
+        let (code, detail) = (e.code(), e.to_string());
+        let (errors, candidates) = match e {
+            AppError::Database(_) => return Err(e),
+            AppError::Validation(errors) => (Some(errors), None),
+            AppError::UnknownAuthor(_, candidates) | AppError::AmbiguousAuthor(_, candidates) => {
+                (None, Some(candidates))
+            }
+            _ => (None, None),
+        };
+
+        Ok(Self {
+            row,
+            code,
+            detail,
+            errors,
+            candidates,
+        })
+    }
+}
+
+// A data row keyed by column name, blank cells left out
+struct CsvRow {
+    line: u64,
+    cells: HashMap<String, String>,
+}
+
+impl CsvRow {
+    fn get(&self, column: &str) -> Option<String> {
+        self.cells.get(column).cloned()
+    }
+
+    fn number(&self, column: &str, errors: &mut ValidationErrors) -> Option<i32> {
+        let cell = self.cells.get(column)?;
+        errors.check(
+            column,
+            cell.parse()
+                .map_err(|_| Violation::bad_format("must be a whole number")),
+        )
+    }
+}
+
+fn read_csv(body: &[u8], required: &[&str]) -> Result<Vec<CsvRow>, AppError> {
+    let unreadable = |e: csv::Error| AppError::MalformedRequest(format!("Unreadable CSV: {}", e));
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(body);
+
+    let headers = reader.headers().map_err(unreadable)?.clone();
+    let missing: Vec<&str> = required
+        .iter()
+        .filter(|column| !headers.iter().any(|header| header == **column))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        return Err(AppError::MalformedRequest(format!(
+            "CSV is missing the columns: {}",
+            missing.join(", ")
+        )));
+    }
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(unreadable)?;
+        let cells = headers
+            .iter()
+            .zip(record.iter())
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|(header, cell)| (header.to_string(), cell.to_string()))
+            .collect();
+        rows.push(CsvRow {
+            line: record.position().map_or(0, |position| position.line()),
+            cells,
+        });
+    }
+
+    match rows.len() {
+        0 => Err(AppError::Validation(ValidationErrors::single(
+            "body",
+            Violation::empty(),
+        ))),
+        n if n > MAX_IMPORT_ROWS => Err(AppError::Validation(ValidationErrors::single(
+            "body",
+            Violation::out_of_range(1, MAX_IMPORT_ROWS as i64),
+        ))),
+        _ => Ok(rows),
+    }
+}
+
+// A name in the `author` cell, followed by its role in parentheses unless that is `author`:
+// `Haruki Murakami; Jay Rubin (translator)`
+fn contributor_from_cell(entry: &str) -> ContributorData {
+    let role = entry.strip_suffix(')').and_then(|rest| {
+        CONTRIBUTOR_ROLES.iter().find_map(|role| {
+            rest.strip_suffix(role)
+                .and_then(|rest| rest.strip_suffix('('))
+                .map(|name| (name.trim_end(), *role))
+        })
+    });
+
+    match role {
+        Some((name, role)) if !name.is_empty() => ContributorData {
+            author_id: None,
+            author: Some(name.into()),
+            role: Some(role.into()),
+        },
+        _ => ContributorData {
+            author_id: None,
+            author: Some(entry.into()),
+            role: None,
+        },
+    }
+}
+
+// Several contributors share the `author` cell, separated by semicolons
+fn book_from_row(row: &CsvRow) -> Result<NewBook, ValidationErrors> {
+    let mut errors = ValidationErrors::default();
+    let publication_year = row.number("publication_year", &mut errors);
+    let page_count = row.number("page_count", &mut errors);
+
+    let mut contributors: Vec<ContributorData> = row
+        .get("author")
+        .map(|cell| {
+            cell.split(';')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(contributor_from_cell)
+                .collect()
+        })
+        .unwrap_or_default();
+    let (author, contributors) = match contributors.as_slice() {
+        [] => (None, None),
+        [only] if only.role.is_none() => (contributors.pop().and_then(|c| c.author), None),
+        _ => (None, Some(contributors)),
+    };
+
+    let data = NewBookData {
+        title: row.get("title").unwrap_or_default(),
+        author,
+        author_id: row.get("author_id"),
+        contributors,
+        genre: row.get("genre").unwrap_or_default(),
+        metadata: BookMetadataData {
+            isbn: row.get("isbn"),
+            publisher: row.get("publisher"),
+            publication_year,
+            language: row.get("language"),
+            page_count,
+            edition: row.get("edition"),
+            description: row.get("description"),
+        },
+    };
+
+    match NewBook::try_from(data) {
+        Ok(book) if errors.is_empty() => Ok(book),
+        Ok(_) => Err(errors),
+        Err(more) => {
+            errors.merge(more);
+            Err(errors)
+        }
+    }
+}
+
+fn author_from_row(row: &CsvRow) -> Result<NewAuthor, ValidationErrors> {
+    NewAuthor::try_from(NewAuthorData {
+        name: row.get("name").unwrap_or_default(),
+        nationality: row.get("nationality").unwrap_or_default(),
+    })
+}
+
+// Rows go in under their own savepoints, so one bad row doesn't hide problems further down,
+// but the upload is only committed when every row made it and it isn't a dry run
+pub async fn finish_import(
+    transaction: Transaction<'_, Postgres>,
+    rejected: Vec<RowError>,
+    rows: usize,
+    dry_run: bool,
+    message: &str,
+) -> Result<HttpResponse, AppError> {
+    if !rejected.is_empty() {
+        transaction.rollback().await?;
+        return Err(AppError::ImportRejected(rejected));
+    }
+
+    if dry_run {
+        transaction.rollback().await?;
+        return Ok(HttpResponse::Ok().json(json!({
+            "message": "Dry run passed, nothing was imported",
+            "dry_run": true,
+            "rows": rows,
+            "imported": 0
+        })));
+    }
+
+    transaction.commit().await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "message": message,
+        "dry_run": false,
+        "rows": rows,
+        "imported": rows
+    })))
+}
+
+pub async fn import_books(
+    _staff: Librarian,
+    params: Query<CsvImportParams>,
+    body: Bytes,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let rows = read_csv(&body, &["title", "genre"])?;
+
+    let mut transaction = db_pool.begin().await?;
+    let mut rejected = Vec::new();
+    for row in &rows {
+        let outcome = async {
+            let book = book_from_row(row).map_err(AppError::Validation)?;
+            let mut savepoint = transaction.begin().await?;
+            insert_book_in(&mut savepoint, &book, None).await?;
+            savepoint.commit().await?;
+            Ok(())
+        }
+        .await;
+        if let Err(e) = outcome {
+            rejected.push(RowError::new(row.line, e)?);
+        }
+    }
+
+    finish_import(
+        transaction,
+        rejected,
+        rows.len(),
+        params.dry_run.unwrap_or_default(),
+        "Books imported successfully!",
+    )
+    .await
+}
+
+pub async fn import_authors(
+    _staff: Librarian,
+    params: Query<CsvImportParams>,
+    body: Bytes,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let rows = read_csv(&body, &["name", "nationality"])?;
+
+    let mut transaction = db_pool.begin().await?;
+    let mut rejected = Vec::new();
+    for row in &rows {
+        let outcome = async {
+            let author = author_from_row(row).map_err(AppError::Validation)?;
+            let mut savepoint = transaction.begin().await?;
+            sqlx::query!(
+                "INSERT INTO authors (name, nationality, created_at) VALUES ($1, $2, $3)",
+                author.name.as_ref(),
+                author.nationality.as_ref(),
+                chrono::Utc::now()
+            )
+            .execute(&mut *savepoint)
+            .await?;
+            savepoint.commit().await?;
+            Ok(())
+        }
+        .await;
+        if let Err(e) = outcome {
+            rejected.push(RowError::new(row.line, e)?);
+        }
+    }
+
+    finish_import(
+        transaction,
+        rejected,
+        rows.len(),
+        params.dry_run.unwrap_or_default(),
+        "Authors imported successfully!",
+    )
+    .await
+}
+
+fn csv_chunk(header: Option<&[&str]>, records: Vec<Vec<String>>) -> Result<Bytes, ExportError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    if let Some(header) = header {
+        writer.write_record(header)?;
+    }
+    for record in records {
+        writer.write_record(record)?;
+    }
+
+    Ok(Bytes::from(writer.into_inner()?))
+}
+
+fn csv_response(filename: &str) -> actix_web::HttpResponseBuilder {
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", filename),
+        ));
+    response
+}
+
+// Pages through the table by id, so the whole catalog never sits in memory at once
+pub async fn export_books(db_pool: Data<PgPool>) -> HttpResponse {
+    let start = (db_pool.get_ref().clone(), Some(Uuid::nil()), true);
+    let chunks = stream::try_unfold(start, |(db_pool, after, first)| async move {
+        let Some(after) = after else {
+            return Ok(None);
+        };
+
+        let books = sqlx::query!(
+            r#"
+            SELECT
+                books.id, books.title, books.genre, books.isbn_13, books.publisher,
+                books.publication_year, books.language, books.page_count, books.edition,
+                books.description,
+                (
+                    SELECT string_agg(
+                        CASE book_contributors.role
+                            WHEN 'author' THEN authors.name
+                            ELSE authors.name || ' (' || book_contributors.role || ')'
+                        END,
+                        '; ' ORDER BY book_contributors.position
+                    )
+                    FROM book_contributors
+                    JOIN authors ON authors.id = book_contributors.author_id
+                    WHERE book_contributors.book_id = books.id
+                ) AS author
+            FROM books
+            WHERE books.id > $1
+            ORDER BY books.id
+            LIMIT $2
+            "#,
+            after,
+            EXPORT_CHUNK
+        )
+        .fetch_all(&db_pool)
+        .await?;
+
+        let next = (books.len() as i64 == EXPORT_CHUNK).then(|| books[books.len() - 1].id);
+        let records = books
+            .into_iter()
+            .map(|book| {
+                vec![
+                    book.id.to_string(),
+                    book.title,
+                    book.author.unwrap_or_default(),
+                    book.genre,
+                    book.isbn_13.unwrap_or_default(),
+                    book.publisher.unwrap_or_default(),
+                    cell(book.publication_year),
+                    book.language.unwrap_or_default(),
+                    cell(book.page_count),
+                    book.edition.unwrap_or_default(),
+                    book.description.unwrap_or_default(),
+                ]
+            })
+            .collect();
+        let chunk = csv_chunk(first.then_some(&BOOK_COLUMNS[..]), records)?;
+
+        Ok::<_, ExportError>(Some((chunk, (db_pool, next, false))))
+    });
+
+    csv_response("books.csv").streaming(chunks)
+}
+
+pub async fn export_authors(db_pool: Data<PgPool>) -> HttpResponse {
+    let start = (db_pool.get_ref().clone(), Some(Uuid::nil()), true);
+    let chunks = stream::try_unfold(start, |(db_pool, after, first)| async move {
+        let Some(after) = after else {
+            return Ok(None);
+        };
+
+        let authors = sqlx::query!(
+            "SELECT id, name, nationality FROM authors WHERE id > $1 ORDER BY id LIMIT $2",
+            after,
+            EXPORT_CHUNK
+        )
+        .fetch_all(&db_pool)
+        .await?;
+
+        let next = (authors.len() as i64 == EXPORT_CHUNK).then(|| authors[authors.len() - 1].id);
+        let records = authors
+            .into_iter()
+            .map(|author| vec![author.id.to_string(), author.name, author.nationality])
+            .collect();
+        let chunk = csv_chunk(first.then_some(&AUTHOR_COLUMNS[..]), records)?;
+
+        Ok::<_, ExportError>(Some((chunk, (db_pool, next, false))))
+    });
+
+    csv_response("authors.csv").streaming(chunks)
+}
+
+fn cell(value: Option<i32>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
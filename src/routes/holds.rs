@@ -0,0 +1,300 @@
+use crate::auth::{CurrentUser, Librarian};
+use crate::errors::AppError;
+use crate::validations::hold::{pickup_expiry, NewHold};
+use actix_web::{
+    web::{Data, Json, Path},
+    HttpResponse,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+// Hands a copy that just came back to the next patron waiting in line for its book,
+// or puts it back on the shelf when nobody is waiting. Every copy that becomes available
+// goes through here.
+pub async fn release_item(
+    transaction: &mut Transaction<'_, Postgres>,
+    item_id: Uuid,
+    book_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    lock_book(transaction, book_id).await?;
+    let ready_at = Utc::now();
+
+    let promoted_hold = sqlx::query!(
+        "UPDATE holds SET status = 'ready', item_id = $2, ready_at = $3, expires_at = $4
+        WHERE id = (
+            SELECT id FROM holds
+            WHERE book_id = $1 AND status = 'waiting'
+            ORDER BY placed_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id",
+        book_id,
+        item_id,
+        ready_at,
+        pickup_expiry(ready_at)
+    )
+    .fetch_optional(&mut **transaction)
+    .await?;
+
+    let status = match promoted_hold {
+        Some(_) => "on_hold",
+        None => "available",
+    };
+
+    sqlx::query!(
+        "UPDATE items SET status = $2 WHERE id = $1",
+        item_id,
+        status
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+// Placing a hold and releasing a copy of the same book take turns, so a hold is never
+// placed while a copy is on its way back to the shelf
+async fn lock_book(
+    transaction: &mut Transaction<'_, Postgres>,
+    book_id: Uuid,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT id FROM books WHERE id = $1 FOR NO KEY UPDATE",
+        book_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+}
+
+// Holds that were not picked up in time give their copy to the next patron in line
+pub async fn expire_holds(transaction: &mut Transaction<'_, Postgres>) -> Result<(), sqlx::Error> {
+    let expired_holds = sqlx::query!(
+        "UPDATE holds SET status = 'expired', closed_at = $1
+        WHERE status = 'ready' AND expires_at < $1
+        RETURNING book_id, item_id",
+        Utc::now()
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+
+    for hold in expired_holds {
+        if let Some(item_id) = hold.item_id {
+            release_item(transaction, item_id, hold.book_id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn book_holds_index(
+    _staff: Librarian,
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let book_id = input.into_inner();
+
+    let mut transaction = db_pool.begin().await?;
+    expire_holds(&mut transaction).await?;
+
+    let rows = sqlx::query!(
+        "SELECT id, user_id, item_id, status, placed_at, ready_at, expires_at
+        FROM holds
+        WHERE book_id = $1 AND status IN ('waiting', 'ready')
+        ORDER BY status = 'waiting', placed_at",
+        Uuid::parse_str(&book_id).unwrap_or_default()
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    let mut queue_position = 0;
+    let holds: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|row| {
+            let position = match row.status.as_str() {
+                "waiting" => {
+                    queue_position += 1;
+                    Some(queue_position)
+                }
+                _ => None,
+            };
+
+            json!({
+                "id": row.id,
+                "user_id": row.user_id,
+                "item_id": row.item_id,
+                "status": row.status,
+                "queue_position": position,
+                "placed_at": row.placed_at,
+                "ready_at": row.ready_at,
+                "expires_at": row.expires_at
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(holds))
+}
+
+pub async fn user_holds_index(
+    user: CurrentUser,
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&input.into_inner()).unwrap_or_default();
+    user.require_self_or_staff(user_id)?;
+
+    let mut transaction = db_pool.begin().await?;
+    expire_holds(&mut transaction).await?;
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            holds.id,
+            holds.book_id,
+            books.title,
+            holds.status,
+            holds.placed_at,
+            holds.ready_at,
+            holds.expires_at,
+            (
+                SELECT COUNT(*) FROM holds AS queue
+                WHERE queue.book_id = holds.book_id
+                AND queue.status = 'waiting'
+                AND queue.placed_at <= holds.placed_at
+            ) AS "queue_position!"
+        FROM holds
+        JOIN books ON books.id = holds.book_id
+        WHERE holds.user_id = $1
+        ORDER BY holds.placed_at
+        "#,
+        user_id
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    let holds: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|row| {
+            json!({
+                "id": row.id,
+                "book_id": row.book_id,
+                "title": row.title,
+                "status": row.status,
+                "queue_position": (row.status == "waiting").then_some(row.queue_position),
+                "placed_at": row.placed_at,
+                "ready_at": row.ready_at,
+                "expires_at": row.expires_at
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(holds))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewHoldData {
+    pub user_id: String,
+}
+
+pub async fn create_hold(
+    user: CurrentUser,
+    path: Path<String>,
+    input: Json<NewHoldData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let new_hold: NewHold = input.0.try_into().map_err(AppError::Validation)?;
+    user.require_self_or_staff(*new_hold.user_id.as_ref())?;
+
+    sqlx::query!(
+        "SELECT id FROM users WHERE id = $1",
+        new_hold.user_id.as_ref()
+    )
+    .fetch_optional(db_pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+
+    let mut transaction = db_pool.begin().await?;
+    expire_holds(&mut transaction).await?;
+
+    let book_id = lock_book(
+        &mut transaction,
+        Uuid::parse_str(&path.into_inner()).unwrap_or_default(),
+    )
+    .await?
+    .ok_or_else(|| AppError::NotFound("Book not found".into()))?;
+    let available = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM items WHERE book_id = $1 AND status = 'available'"#,
+        book_id
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+
+    if available.count > 0 {
+        return Err(AppError::Conflict(
+            "Copies of this book are available for checkout".into(),
+        ));
+    }
+
+    let hold = sqlx::query!(
+        "INSERT INTO holds (book_id, user_id, placed_at)
+        VALUES ($1, $2, $3)
+        RETURNING id",
+        book_id,
+        new_hold.user_id.as_ref(),
+        Utc::now()
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .map_err(|e| match AppError::from(e) {
+        AppError::Conflict(_) => {
+            AppError::Conflict("User already has an active hold for this book".into())
+        }
+        e => e,
+    })?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Hold placed successfully!",
+        "hold_id": hold.id
+    })))
+}
+
+pub async fn cancel_hold(
+    user: CurrentUser,
+    input: Path<(String, String)>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let (user_id, hold_id) = input.into_inner();
+    let user_id = Uuid::parse_str(&user_id).unwrap_or_default();
+    user.require_self_or_staff(user_id)?;
+
+    let mut transaction = db_pool.begin().await?;
+
+    let hold = sqlx::query!(
+        "UPDATE holds SET status = 'cancelled', closed_at = $3
+        WHERE id = $1 AND user_id = $2 AND status IN ('waiting', 'ready')
+        RETURNING book_id, item_id",
+        Uuid::parse_str(&hold_id).unwrap_or_default(),
+        user_id,
+        Utc::now()
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Active hold to be cancelled not found".into()))?;
+
+    if let Some(item_id) = hold.item_id {
+        release_item(&mut transaction, item_id, hold.book_id).await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": "Hold cancelled successfully!"})))
+}
@@ -0,0 +1,214 @@
+use actix_web::{
+    web::{Data, Json},
+    HttpResponse,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::Librarian;
+use crate::configuration::JobsConfig;
+use crate::errors::AppError;
+use crate::gutendex::{Gutendex, GutendexBook};
+use crate::jobs::{self, GUTENDEX_IMPORT};
+use crate::metadata::MetadataProvider;
+use crate::routes::{
+    create_missing_author, insert_book_in, BookMetadataData, ContributorData, NewBookData,
+};
+use crate::validations::book::{IsbnImport, NewBook};
+
+// Books imported without a genre, and with no usable subject from the provider, land here
+const DEFAULT_GENRE: &str = "Uncategorized";
+
+#[derive(Serialize, Deserialize)]
+pub struct IsbnImportData {
+    pub isbn: String,
+    pub genre: Option<String>,
+}
+
+pub async fn import_isbn(
+    _staff: Librarian,
+    input: Json<IsbnImportData>,
+    metadata_provider: Data<dyn MetadataProvider>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let import: IsbnImport = input.0.try_into().map_err(AppError::Validation)?;
+
+    let record = metadata_provider
+        .lookup_isbn(&import.isbn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No metadata found for this ISBN".into()))?;
+
+    let genre = match &import.genre {
+        Some(genre) => genre.as_ref().to_string(),
+        None => genre_from_subjects(&record.subjects),
+    };
+    let data = NewBookData {
+        title: record.title.clone(),
+        author: None,
+        author_id: None,
+        contributors: Some(
+            record
+                .authors
+                .iter()
+                .map(|author| ContributorData {
+                    author_id: None,
+                    author: Some(author.clone()),
+                    role: None,
+                })
+                .collect(),
+        ),
+        genre,
+        metadata: BookMetadataData {
+            isbn: Some(import.isbn.as_ref().into()),
+            publisher: record.publisher.clone(),
+            publication_year: record.publication_year,
+            page_count: record.page_count,
+            ..Default::default()
+        },
+    };
+    // The record comes from outside, so if it doesn't make a valid book that's on the provider
+    let new_book: NewBook = data.try_into().map_err(|errors| {
+        AppError::Upstream(format!("Unusable metadata for this ISBN: {}", errors))
+    })?;
+
+    // New authors only stay if the book goes in with them
+    let mut transaction = db_pool.begin().await?;
+    for author in &record.authors {
+        create_missing_author(author, &mut transaction).await?;
+    }
+    let book_id = insert_book_in(&mut transaction, &new_book, None).await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Book imported successfully!",
+        "book_id": book_id,
+        "metadata": record
+    })))
+}
+
+// Subjects come as "Whales -- Fiction", the broad part before the dashes makes the genre
+pub fn genre_from_subjects(subjects: &[String]) -> String {
+    subjects
+        .iter()
+        .filter_map(|subject| subject.split(" -- ").next())
+        .map(str::trim)
+        .find(|subject| !subject.is_empty() && subject.chars().count() <= 80)
+        .map(String::from)
+        .unwrap_or_else(|| DEFAULT_GENRE.into())
+}
+
+#[derive(Serialize, Default)]
+pub struct ImportSummary {
+    pub pages: u32,
+    pub created: u32,
+    pub skipped: u32,
+    pub failed: u32,
+    pub failures: Vec<ImportFailure>,
+}
+
+#[derive(Serialize)]
+pub struct ImportFailure {
+    pub gutenberg_id: i32,
+    pub reason: String,
+}
+
+// Walking the whole catalog takes a while, so the request only queues it for the job workers
+pub async fn import_gutendex(
+    _staff: Librarian,
+    jobs_config: Data<JobsConfig>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let job_id = jobs::enqueue(GUTENDEX_IMPORT, &jobs_config, &db_pool).await?;
+
+    Ok(HttpResponse::Accepted()
+        .insert_header(("Location", format!("/api/v1/jobs/{}", job_id)))
+        .json(json!({"message": "Import queued", "job_id": job_id})))
+}
+
+// Walks the Gutendex catalog page by page. Books already imported are skipped, so a failed
+// import can simply be retried, and cancelling the job stops it before the next page.
+pub async fn run_gutendex_import(
+    gutendex: &Gutendex,
+    job_id: Uuid,
+    db_pool: &PgPool,
+) -> Result<ImportSummary, AppError> {
+    let mut summary = ImportSummary::default();
+    let mut next = Some(gutendex.first_page());
+
+    while let Some(url) = next.take() {
+        if summary.pages == gutendex.max_pages || jobs::is_cancelled(job_id, db_pool).await? {
+            break;
+        }
+        let page = gutendex.page(&url).await?;
+        summary.pages += 1;
+
+        for book in &page.results {
+            match import_gutendex_book(book, db_pool).await {
+                Ok(true) => summary.created += 1,
+                Ok(false) => summary.skipped += 1,
+                Err(AppError::Database(e)) => return Err(AppError::Database(e)),
+                Err(e) => {
+                    summary.failed += 1;
+                    summary.failures.push(ImportFailure {
+                        gutenberg_id: book.id,
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+        jobs::record_progress(job_id, json!(summary), db_pool).await?;
+        next = page.next;
+    }
+
+    Ok(summary)
+}
+
+// Ok(false) when the book was imported before
+async fn import_gutendex_book(book: &GutendexBook, db_pool: &PgPool) -> Result<bool, AppError> {
+    let imported = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM books WHERE gutenberg_id = $1) AS "exists!""#,
+        book.id
+    )
+    .fetch_one(db_pool)
+    .await?;
+    if imported {
+        return Ok(false);
+    }
+
+    let people = book
+        .authors
+        .iter()
+        .map(|person| (person, "author"))
+        .chain(book.translators.iter().map(|person| (person, "translator")));
+    let contributors: Vec<ContributorData> = people
+        .map(|(person, role)| ContributorData {
+            author_id: None,
+            author: Some(person.display_name()),
+            role: Some(role.into()),
+        })
+        .collect();
+
+    let data = NewBookData {
+        title: book.title.clone(),
+        author: None,
+        author_id: None,
+        contributors: (!contributors.is_empty()).then_some(contributors),
+        genre: genre_from_subjects(&book.subjects),
+        metadata: BookMetadataData {
+            language: book.languages.first().cloned(),
+            ..Default::default()
+        },
+    };
+    let new_book: NewBook = data.try_into().map_err(AppError::Validation)?;
+
+    let mut transaction = db_pool.begin().await?;
+    for person in book.authors.iter().chain(&book.translators) {
+        create_missing_author(&person.display_name(), &mut transaction).await?;
+    }
+    insert_book_in(&mut transaction, &new_book, Some(book.id)).await?;
+    transaction.commit().await?;
+
+    Ok(true)
+}
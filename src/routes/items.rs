@@ -0,0 +1,203 @@
+use crate::auth::Librarian;
+use crate::errors::AppError;
+use crate::routes::release_item;
+use crate::validations::item::NewItem;
+use actix_web::{
+    web::{Data, Json, Path},
+    HttpResponse,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn items_index(
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let book_id = input.into_inner();
+
+    let rows = sqlx::query!(
+        "SELECT id, barcode, condition, shelf_location, status, created_at
+        FROM items
+        WHERE book_id = $1
+        ORDER BY created_at",
+        Uuid::parse_str(&book_id).unwrap_or_default()
+    )
+    .fetch_all(db_pool.get_ref())
+    .await?;
+
+    let items: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|row| {
+            json!({
+                "id": row.id,
+                "barcode": row.barcode,
+                "condition": row.condition,
+                "shelf_location": row.shelf_location,
+                "status": row.status,
+                "created_at": row.created_at
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(items))
+}
+
+pub async fn show_item(
+    input: Path<(String, String)>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let (book_id, item_id) = input.into_inner();
+
+    let item = sqlx::query!(
+        "SELECT id, book_id, barcode, condition, shelf_location, status, created_at
+        FROM items
+        WHERE id = $1 AND book_id = $2",
+        Uuid::parse_str(&item_id).unwrap_or_default(),
+        Uuid::parse_str(&book_id).unwrap_or_default()
+    )
+    .fetch_optional(db_pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Item not found".into()))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "id": item.id,
+        "book_id": item.book_id,
+        "barcode": item.barcode,
+        "condition": item.condition,
+        "shelf_location": item.shelf_location,
+        "status": item.status,
+        "created_at": item.created_at
+    })))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewItemData {
+    pub barcode: String,
+    pub condition: String,
+    pub shelf_location: String,
+    pub status: Option<String>,
+}
+
+pub async fn create_item(
+    _staff: Librarian,
+    path: Path<String>,
+    input: Json<NewItemData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let new_item: NewItem = input.0.try_into().map_err(AppError::Validation)?;
+
+    let mut transaction = db_pool.begin().await?;
+    let book = sqlx::query!(
+        "SELECT id FROM books WHERE id = $1",
+        Uuid::parse_str(&path.into_inner()).unwrap_or_default()
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Book not found".into()))?;
+
+    let record = sqlx::query!(
+        "INSERT INTO items (book_id, barcode, condition, shelf_location, status, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id",
+        book.id,
+        new_item.barcode.as_ref(),
+        new_item.condition.as_ref(),
+        new_item.shelf_location.as_ref(),
+        new_item.status.as_ref(),
+        Utc::now()
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .map_err(barcode_in_use)?;
+    // A new copy goes to the first patron waiting for its book before reaching the shelf
+    if new_item.status.as_ref() == "available" {
+        release_item(&mut transaction, record.id, book.id).await?;
+    }
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Item created successfully!",
+        "item_id": record.id
+    })))
+}
+
+pub async fn update_item(
+    _staff: Librarian,
+    path: Path<(String, String)>,
+    input: Json<NewItemData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let (book_id, item_id) = path.into_inner();
+    let item: NewItem = input.0.try_into().map_err(AppError::Validation)?;
+
+    // Items on loan or set aside for a hold are managed by circulation
+    let mut transaction = db_pool.begin().await?;
+    let record = sqlx::query!(
+        "UPDATE items
+        SET barcode = $3, condition = $4, shelf_location = $5, status = $6
+        WHERE id = $1 AND book_id = $2 AND status NOT IN ('on_loan', 'on_hold')
+        RETURNING id, book_id",
+        Uuid::parse_str(&item_id).unwrap_or_default(),
+        Uuid::parse_str(&book_id).unwrap_or_default(),
+        item.barcode.as_ref(),
+        item.condition.as_ref(),
+        item.shelf_location.as_ref(),
+        item.status.as_ref()
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(barcode_in_use)?
+    .ok_or_else(|| {
+        AppError::NotFound("Item to be updated not found or currently in circulation".into())
+    })?;
+    // A copy back from repair, or found again, goes to the first patron waiting for its book
+    if item.status.as_ref() == "available" {
+        release_item(&mut transaction, record.id, record.book_id).await?;
+    }
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": "Item updated successfully!"})))
+}
+
+pub async fn delete_item(
+    _staff: Librarian,
+    input: Path<(String, String)>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let (book_id, item_id) = input.into_inner();
+
+    let result = sqlx::query!(
+        "DELETE FROM items WHERE id = $1 AND book_id = $2",
+        Uuid::parse_str(&item_id).unwrap_or_default(),
+        Uuid::parse_str(&book_id).unwrap_or_default()
+    )
+    .execute(db_pool.get_ref())
+    .await
+    .map_err(|e| still_circulating(e, "Item"))?;
+
+    match result.rows_affected() == 1 {
+        true => Ok(HttpResponse::Ok().json(json!({"message": "Item deleted successfully!"}))),
+        false => Err(AppError::NotFound("Item to be deleted not found".into())),
+    }
+}
+
+fn barcode_in_use(e: sqlx::Error) -> AppError {
+    match AppError::from(e) {
+        AppError::Conflict(_) => AppError::Conflict("Item barcode already in use".into()),
+        e => e,
+    }
+}
+
+// Copies that were ever lent or held, and books with such copies, keep their history
+pub fn still_circulating(e: sqlx::Error, record: &str) -> AppError {
+    match AppError::from(e) {
+        AppError::Conflict(_) => AppError::Conflict(format!(
+            "{} has loans or holds and can't be deleted",
+            record
+        )),
+        e => e,
+    }
+}
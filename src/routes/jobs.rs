@@ -0,0 +1,65 @@
+use actix_web::{
+    web::{Data, Path},
+    HttpResponse,
+};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::Librarian;
+use crate::errors::AppError;
+
+pub async fn show_job(
+    _staff: Librarian,
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let job_id = input.into_inner();
+
+    let job = sqlx::query!(
+        "SELECT id, kind, status, attempts, max_attempts, run_at, result, last_error,
+            created_at, started_at, finished_at
+        FROM jobs WHERE id = $1",
+        Uuid::parse_str(&job_id).unwrap_or_default()
+    )
+    .fetch_optional(db_pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Job not found".into()))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "id": job.id,
+        "kind": job.kind,
+        "status": job.status,
+        "attempts": job.attempts,
+        "max_attempts": job.max_attempts,
+        "run_at": job.run_at,
+        "result": job.result,
+        "last_error": job.last_error,
+        "created_at": job.created_at,
+        "started_at": job.started_at,
+        "finished_at": job.finished_at
+    })))
+}
+
+// A running job sees the cancellation before its next step and stops there
+pub async fn cancel_job(
+    _staff: Librarian,
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let job_id = input.into_inner();
+
+    sqlx::query!(
+        "UPDATE jobs SET status = 'cancelled', finished_at = $2
+        WHERE id = $1 AND status IN ('queued', 'running')
+        RETURNING id",
+        Uuid::parse_str(&job_id).unwrap_or_default(),
+        Utc::now()
+    )
+    .fetch_optional(db_pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Active job to be cancelled not found".into()))?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": "Job cancelled successfully!"})))
+}
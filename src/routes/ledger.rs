@@ -0,0 +1,217 @@
+use crate::{
+    auth::{CurrentUser, Librarian},
+    configuration::CirculationConfig,
+    errors::AppError,
+    validations::ledger::{overdue_fine, NewLedgerEntry},
+};
+use actix_web::{
+    web::{Data, Json, Path},
+    HttpResponse,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+// Charges whatever fines the user's overdue loans have accrued since they were last checked
+pub async fn accrue_fines(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    daily_fine_cents: i64,
+) -> Result<(), sqlx::Error> {
+    // Serializes accruals for the same user so fines are never charged twice
+    sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+        .fetch_optional(&mut **transaction)
+        .await?;
+
+    let now = Utc::now();
+
+    let overdue_loans = sqlx::query!(
+        r#"
+        SELECT
+            loans.id,
+            loans.due_at,
+            loans.returned_at,
+            (
+                SELECT COALESCE(SUM(amount_cents), 0)::BIGINT FROM ledger_entries
+                WHERE ledger_entries.loan_id = loans.id AND ledger_entries.kind = 'fine'
+            ) AS "charged_cents!"
+        FROM loans
+        WHERE loans.user_id = $1 AND COALESCE(loans.returned_at, $2) > loans.due_at
+        "#,
+        user_id,
+        now
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+
+    for loan in overdue_loans {
+        let until = loan.returned_at.unwrap_or(now);
+        let fine = overdue_fine(loan.due_at, until, daily_fine_cents);
+
+        if fine > loan.charged_cents {
+            sqlx::query!(
+                "INSERT INTO ledger_entries (user_id, loan_id, kind, amount_cents, note, created_at)
+                VALUES ($1, $2, 'fine', $3, $4, $5)",
+                user_id,
+                loan.id,
+                fine - loan.charged_cents,
+                format!("{} day(s) overdue", (until - loan.due_at).num_days()),
+                now
+            )
+            .execute(&mut **transaction)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn user_balance(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<i64, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"SELECT COALESCE(SUM(amount_cents), 0)::BIGINT AS "balance_cents!"
+        FROM ledger_entries
+        WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+
+    Ok(record.balance_cents)
+}
+
+pub async fn show_balance(
+    user: CurrentUser,
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+    circulation: Data<CirculationConfig>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&input.into_inner()).unwrap_or_default();
+    user.require_self_or_staff(user_id)?;
+
+    let mut transaction = db_pool.begin().await?;
+
+    sqlx::query!("SELECT id FROM users WHERE id = $1", user_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+
+    accrue_fines(&mut transaction, user_id, circulation.daily_fine_cents).await?;
+    let balance_cents = user_balance(&mut transaction, user_id).await?;
+
+    let rows = sqlx::query!(
+        "SELECT id, loan_id, kind, amount_cents, note, created_at
+        FROM ledger_entries
+        WHERE user_id = $1
+        ORDER BY created_at",
+        user_id
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    let entries: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|row| {
+            json!({
+                "id": row.id,
+                "loan_id": row.loan_id,
+                "kind": row.kind,
+                "amount_cents": row.amount_cents,
+                "note": row.note,
+                "created_at": row.created_at
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "user_id": user_id,
+        "balance_cents": balance_cents,
+        "entries": entries
+    })))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewLedgerEntryData {
+    pub amount_cents: i64,
+    pub note: Option<String>,
+}
+
+// Patrons can settle their own fines, waiving them is up to staff
+pub async fn create_payment(
+    user: CurrentUser,
+    path: Path<String>,
+    input: Json<NewLedgerEntryData>,
+    db_pool: Data<PgPool>,
+    circulation: Data<CirculationConfig>,
+) -> Result<HttpResponse, AppError> {
+    user.require_self_or_staff(Uuid::parse_str(&path).unwrap_or_default())?;
+    credit_user(path, input, db_pool, circulation, "payment").await
+}
+
+pub async fn create_waiver(
+    _staff: Librarian,
+    path: Path<String>,
+    input: Json<NewLedgerEntryData>,
+    db_pool: Data<PgPool>,
+    circulation: Data<CirculationConfig>,
+) -> Result<HttpResponse, AppError> {
+    credit_user(path, input, db_pool, circulation, "waiver").await
+}
+
+async fn credit_user(
+    path: Path<String>,
+    input: Json<NewLedgerEntryData>,
+    db_pool: Data<PgPool>,
+    circulation: Data<CirculationConfig>,
+    kind: &str,
+) -> Result<HttpResponse, AppError> {
+    let new_entry: NewLedgerEntry = input.0.try_into().map_err(AppError::Validation)?;
+    let user_id = Uuid::parse_str(&path.into_inner()).unwrap_or_default();
+
+    let mut transaction = db_pool.begin().await?;
+
+    sqlx::query!("SELECT id FROM users WHERE id = $1", user_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+
+    accrue_fines(&mut transaction, user_id, circulation.daily_fine_cents).await?;
+    let balance_cents = user_balance(&mut transaction, user_id).await?;
+
+    if *new_entry.amount_cents.as_ref() > balance_cents {
+        return Err(AppError::Conflict(format!(
+            "The {} exceeds the outstanding balance",
+            kind
+        )));
+    }
+
+    let entry = sqlx::query!(
+        "INSERT INTO ledger_entries (user_id, kind, amount_cents, note, created_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id",
+        user_id,
+        kind,
+        -new_entry.amount_cents.as_ref(),
+        new_entry.note.as_ref(),
+        Utc::now()
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": match kind {
+            "payment" => "Payment recorded successfully!",
+            _ => "Waiver recorded successfully!",
+        },
+        "entry_id": entry.id,
+        "balance_cents": balance_cents - new_entry.amount_cents.as_ref()
+    })))
+}
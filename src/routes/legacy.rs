@@ -0,0 +1,45 @@
+use crate::auth::Librarian;
+use crate::errors::AppError;
+use crate::routes::{delete_author, delete_book, delete_item};
+use actix_web::{
+    web::{Data, Json, Path},
+    HttpResponse,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+// The pre-versioning API took the id of the record to delete in a POST body
+#[derive(Deserialize)]
+pub struct LegacyId {
+    id: String,
+}
+
+pub async fn legacy_delete_book(
+    staff: Librarian,
+    input: Json<LegacyId>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    delete_book(staff, Path::from(input.into_inner().id), db_pool).await
+}
+
+pub async fn legacy_delete_author(
+    staff: Librarian,
+    input: Json<LegacyId>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    delete_author(staff, Path::from(input.into_inner().id), db_pool).await
+}
+
+pub async fn legacy_delete_item(
+    staff: Librarian,
+    path: Path<String>,
+    input: Json<LegacyId>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    delete_item(
+        staff,
+        Path::from((path.into_inner(), input.into_inner().id)),
+        db_pool,
+    )
+    .await
+}
@@ -0,0 +1,209 @@
+use crate::{
+    auth::CurrentUser,
+    configuration::CirculationConfig,
+    errors::AppError,
+    routes::{accrue_fines, expire_holds, release_item, user_balance},
+    validations::loan::{due_date, renewed_due_date, NewLoan, MAX_ACTIVE_LOANS},
+};
+use actix_web::{
+    web::{Data, Json, Path},
+    HttpResponse,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize)]
+pub struct NewLoanData {
+    pub user_id: String,
+    pub book_id: String,
+}
+
+pub async fn checkout_loan(
+    user: CurrentUser,
+    input: Json<NewLoanData>,
+    db_pool: Data<PgPool>,
+    circulation: Data<CirculationConfig>,
+) -> Result<HttpResponse, AppError> {
+    let new_loan: NewLoan = input.0.try_into().map_err(AppError::Validation)?;
+    user.require_self_or_staff(*new_loan.user_id.as_ref())?;
+
+    let mut transaction = db_pool.begin().await?;
+
+    // Locking the user serializes their checkouts, so two at once can't both pass the limit
+    sqlx::query!(
+        "SELECT id FROM users WHERE id = $1 FOR UPDATE",
+        new_loan.user_id.as_ref()
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+
+    sqlx::query!(
+        "SELECT id FROM books WHERE id = $1",
+        new_loan.book_id.as_ref()
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Book not found".into()))?;
+
+    let active_loans = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM loans WHERE user_id = $1 AND returned_at IS NULL"#,
+        new_loan.user_id.as_ref()
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+
+    if active_loans.count >= MAX_ACTIVE_LOANS {
+        return Err(AppError::Conflict(format!(
+            "User already has the maximum of {} active loans",
+            MAX_ACTIVE_LOANS
+        )));
+    }
+
+    let user_id = *new_loan.user_id.as_ref();
+    accrue_fines(&mut transaction, user_id, circulation.daily_fine_cents).await?;
+
+    let balance_cents = user_balance(&mut transaction, user_id).await?;
+    if balance_cents > circulation.max_balance_cents {
+        return Err(AppError::Conflict(format!(
+            "User has outstanding fines of {} cents, over the checkout limit",
+            balance_cents
+        )));
+    }
+
+    expire_holds(&mut transaction).await?;
+
+    // A patron picking up their hold takes the copy that was set aside for them
+    let ready_hold = sqlx::query!(
+        "UPDATE holds SET status = 'fulfilled', closed_at = $3
+        WHERE book_id = $1 AND user_id = $2 AND status = 'ready'
+        RETURNING item_id",
+        new_loan.book_id.as_ref(),
+        new_loan.user_id.as_ref(),
+        Utc::now()
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .and_then(|hold| hold.item_id);
+
+    let item = sqlx::query!(
+        "SELECT id, barcode FROM items
+        WHERE book_id = $1 AND (id = $2 OR ($2 IS NULL AND status = 'available'))
+        ORDER BY created_at
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED",
+        new_loan.book_id.as_ref(),
+        ready_hold
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| AppError::Conflict("No copies of this book are available for loan".into()))?;
+
+    let checked_out_at = Utc::now();
+
+    let loan = sqlx::query!(
+        "INSERT INTO loans (book_id, item_id, user_id, checked_out_at, due_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, due_at",
+        new_loan.book_id.as_ref(),
+        item.id,
+        new_loan.user_id.as_ref(),
+        checked_out_at,
+        due_date(checked_out_at)
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+
+    sqlx::query!("UPDATE items SET status = 'on_loan' WHERE id = $1", item.id)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Book checked out successfully!",
+        "loan_id": loan.id,
+        "item_id": item.id,
+        "barcode": item.barcode,
+        "due_at": loan.due_at
+    })))
+}
+
+pub async fn return_loan(
+    user: CurrentUser,
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+    circulation: Data<CirculationConfig>,
+) -> Result<HttpResponse, AppError> {
+    let loan_id = input.into_inner();
+
+    let mut transaction = db_pool.begin().await?;
+
+    // The loan is checked against the caller before anything about it changes
+    let loan = sqlx::query!(
+        "SELECT id, item_id, book_id, user_id FROM loans
+        WHERE id = $1 AND returned_at IS NULL
+        FOR UPDATE",
+        Uuid::parse_str(&loan_id).unwrap_or_default()
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Active loan to be returned not found".into()))?;
+    user.require_self_or_staff(loan.user_id)?;
+
+    sqlx::query!(
+        "UPDATE loans SET returned_at = $2 WHERE id = $1",
+        loan.id,
+        Utc::now()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    release_item(&mut transaction, loan.item_id, loan.book_id).await?;
+    accrue_fines(&mut transaction, loan.user_id, circulation.daily_fine_cents).await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": "Book returned successfully!"})))
+}
+
+pub async fn renew_loan(
+    user: CurrentUser,
+    input: Path<String>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let loan_id = Uuid::parse_str(&input.into_inner()).unwrap_or_default();
+
+    let loan = sqlx::query!(
+        "SELECT id, user_id, due_at, renewals FROM loans WHERE id = $1 AND returned_at IS NULL",
+        loan_id
+    )
+    .fetch_optional(db_pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Active loan to be renewed not found".into()))?;
+    user.require_self_or_staff(loan.user_id)?;
+
+    let new_due_at =
+        renewed_due_date(loan.due_at, loan.renewals, Utc::now()).map_err(AppError::Conflict)?;
+
+    // Matching on the renewal count keeps two concurrent renewals from both going through
+    let record = sqlx::query!(
+        "UPDATE loans SET due_at = $2, renewals = renewals + 1
+        WHERE id = $1 AND renewals = $3
+        RETURNING due_at, renewals",
+        loan.id,
+        new_due_at,
+        loan.renewals
+    )
+    .fetch_optional(db_pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::Conflict("Loan was renewed concurrently".into()))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Loan renewed successfully!",
+        "due_at": record.due_at,
+        "renewals": record.renewals
+    })))
+}
@@ -0,0 +1,319 @@
+use actix_web::{
+    http::header::ContentDisposition,
+    web::{Bytes, Data, Path, Query},
+    HttpResponse,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Acquire, PgPool};
+use uuid::Uuid;
+
+use crate::auth::Librarian;
+use crate::errors::AppError;
+use crate::marc::{
+    direct_order, inverted_order, read_iso2709, read_marcxml, write_iso2709, write_marcxml, Field,
+    Record,
+};
+use crate::metadata::publication_year;
+use crate::routes::{
+    book_contributors, create_missing_author, finish_import, genre_from_subjects, insert_book_in,
+    BookMetadataData, Contributor, ContributorData, CsvImportParams, NewBookData, RowError,
+    MAX_IMPORT_ROWS,
+};
+use crate::validations::{
+    book::{AuthorRef, NewBook},
+    ValidationErrors, Violation,
+};
+
+pub const MAX_MARC_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct MarcExportParams {
+    pub format: Option<String>,
+}
+
+struct MarcBook {
+    id: Uuid,
+    title: String,
+    genre: String,
+    isbn_13: Option<String>,
+    publisher: Option<String>,
+    publication_year: Option<i32>,
+    edition: Option<String>,
+}
+
+// Personal names are entered surname first (first indicator 1) whenever there is a surname
+fn name_heading(name: &str) -> (char, String) {
+    match inverted_order(name) {
+        Some(inverted) => ('1', inverted),
+        None => ('0', name.into()),
+    }
+}
+
+// The first author is the main entry (100), everyone else an added entry (700) with their role
+fn book_record(book: &MarcBook, contributors: &[Contributor]) -> Record {
+    let mut record = Record::default();
+    record.control("001", &book.id.to_string());
+    if let Some(isbn) = &book.isbn_13 {
+        record.data("020", [' ', ' '], &[('a', isbn)]);
+    }
+
+    let main_entry = contributors
+        .iter()
+        .position(|contributor| contributor.role == "author");
+    if let Some(index) = main_entry {
+        let (indicator, heading) = name_heading(&contributors[index].name);
+        record.data("100", [indicator, ' '], &[('a', &heading)]);
+    }
+
+    let title_indicator = if main_entry.is_some() { '1' } else { '0' };
+    record.data("245", [title_indicator, '0'], &[('a', &book.title)]);
+    if let Some(edition) = &book.edition {
+        record.data("250", [' ', ' '], &[('a', edition)]);
+    }
+
+    let year = book.publication_year.map(|year| year.to_string());
+    let mut imprint = Vec::new();
+    if let Some(publisher) = &book.publisher {
+        imprint.push(('b', publisher.as_str()));
+    }
+    if let Some(year) = &year {
+        imprint.push(('c', year.as_str()));
+    }
+    if !imprint.is_empty() {
+        record.data("260", [' ', ' '], &imprint);
+    }
+
+    // Our genres aren't Library of Congress headings, so the source is left unspecified
+    record.data("650", [' ', '4'], &[('a', &book.genre)]);
+
+    for (index, contributor) in contributors.iter().enumerate() {
+        if Some(index) != main_entry {
+            let (indicator, heading) = name_heading(&contributor.name);
+            record.data(
+                "700",
+                [indicator, ' '],
+                &[('a', &heading), ('e', &contributor.role)],
+            );
+        }
+    }
+
+    record
+}
+
+// Catalogers end subfields with ISBD punctuation ("Moby Dick /", "Penguin Books,"), which is
+// dropped. A final period stays when it closes an initial, as in "Tolkien, J. R. R."
+fn strip_punctuation(value: &str) -> String {
+    let value = value
+        .trim()
+        .trim_end_matches([' ', '/', ':', ';', ',', '=']);
+    match value.strip_suffix('.') {
+        Some(rest)
+            if rest
+                .rsplit(' ')
+                .next()
+                .map_or(0, |word| word.chars().count())
+                > 1 =>
+        {
+            rest.trim_end().into()
+        }
+        _ => value.into(),
+    }
+}
+
+fn heading_name(field: &Field) -> Option<String> {
+    let name = strip_punctuation(field.subfield('a')?);
+    match field.indicator(0) {
+        '1' => Some(direct_order(&name)),
+        _ => Some(name),
+    }
+}
+
+// Relators come as terms in $e or as codes in $4. Roles the catalog doesn't know (say "writer
+// of introduction") have no place on the book and those entries are left out.
+fn contributor_role(field: &Field) -> Option<String> {
+    let relator = field
+        .subfield('e')
+        .or_else(|| field.subfield('4'))
+        .map(|relator| strip_punctuation(relator).to_lowercase());
+    let role = match relator.as_deref() {
+        None | Some("aut") | Some("author") => "author",
+        Some("edt") | Some("editor") => "editor",
+        Some("trl") | Some("translator") => "translator",
+        Some("ill") | Some("illustrator") => "illustrator",
+        Some(_) => return None,
+    };
+    Some(role.into())
+}
+
+fn book_from_record(record: &Record) -> Result<NewBook, ValidationErrors> {
+    let title = match (record.first("245", 'a'), record.first("245", 'b')) {
+        (Some(title), Some(subtitle)) => format!(
+            "{}: {}",
+            strip_punctuation(title),
+            strip_punctuation(subtitle)
+        ),
+        (title, _) => title.map(strip_punctuation).unwrap_or_default(),
+    };
+
+    let mut contributors: Vec<ContributorData> = record
+        .fields("100")
+        .filter_map(heading_name)
+        .map(|name| ContributorData {
+            author_id: None,
+            author: Some(name),
+            role: None,
+        })
+        .collect();
+    contributors.extend(record.fields("700").filter_map(|field| {
+        Some(ContributorData {
+            author_id: None,
+            author: Some(heading_name(field)?),
+            role: Some(contributor_role(field)?),
+        })
+    }));
+
+    // 264 replaced 260 under RDA, only its publication statement (second indicator 1) counts
+    let imprint = record
+        .fields("260")
+        .next()
+        .or_else(|| record.fields("264").find(|field| field.indicator(1) == '1'));
+    let publication_year = imprint
+        .and_then(|field| field.subfield('c'))
+        .and_then(publication_year);
+
+    let subjects: Vec<String> = record
+        .fields("650")
+        .filter_map(|field| field.subfield('a'))
+        .map(strip_punctuation)
+        .collect();
+
+    NewBook::try_from(NewBookData {
+        title,
+        author: None,
+        author_id: None,
+        contributors: (!contributors.is_empty()).then_some(contributors),
+        genre: genre_from_subjects(&subjects),
+        metadata: BookMetadataData {
+            isbn: record
+                .first("020", 'a')
+                .and_then(|isbn| isbn.split_whitespace().next())
+                .map(String::from),
+            publisher: imprint
+                .and_then(|field| field.subfield('b'))
+                .map(strip_punctuation),
+            publication_year,
+            edition: record.first("250", 'a').map(strip_punctuation),
+            ..Default::default()
+        },
+    })
+}
+
+pub async fn export_book_marc(
+    info: Path<String>,
+    params: Query<MarcExportParams>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let xml = match params.format.as_deref() {
+        None | Some("marc21") => false,
+        Some("marcxml") => true,
+        Some(_) => {
+            return Err(AppError::Validation(ValidationErrors::single(
+                "format",
+                Violation::not_allowed("must be marc21 or marcxml"),
+            )))
+        }
+    };
+
+    let book = sqlx::query_as!(
+        MarcBook,
+        "SELECT id, title, genre, isbn_13, publisher, publication_year, edition
+        FROM books WHERE id = $1",
+        Uuid::parse_str(&info.into_inner()).unwrap_or_default(),
+    )
+    .fetch_optional(db_pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Book not found".into()))?;
+
+    let contributors = book_contributors(&[book.id], &db_pool)
+        .await?
+        .remove(&book.id)
+        .unwrap_or_default();
+    let record = book_record(&book, &contributors);
+
+    if xml {
+        return Ok(HttpResponse::Ok()
+            .content_type("application/marcxml+xml")
+            .insert_header(ContentDisposition::attachment(format!("{}.xml", book.id)))
+            .body(write_marcxml(&[record])));
+    }
+
+    // Nothing upstream is involved, a book ISO 2709 can't hold is our own failure
+    let bytes = write_iso2709(&record).map_err(|e| AppError::Internal(e.to_string()))?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/marc")
+        .insert_header(ContentDisposition::attachment(format!("{}.mrc", book.id)))
+        .body(bytes))
+}
+
+// The upload is MARCXML when it opens with markup, ISO 2709 otherwise
+fn read_records(body: &[u8]) -> Result<Vec<Record>, AppError> {
+    let unreadable = |e| AppError::MalformedRequest(format!("Unreadable MARC: {}", e));
+    let records = match body.iter().find(|byte| !byte.is_ascii_whitespace()) {
+        Some(b'<') => {
+            let xml = std::str::from_utf8(body).map_err(|e| unreadable(e.to_string()))?;
+            read_marcxml(xml).map_err(|e| unreadable(e.to_string()))?
+        }
+        _ => read_iso2709(body).map_err(|e| unreadable(e.to_string()))?,
+    };
+
+    match records.len() {
+        0 => Err(AppError::Validation(ValidationErrors::single(
+            "body",
+            Violation::empty(),
+        ))),
+        n if n > MAX_IMPORT_ROWS => Err(AppError::Validation(ValidationErrors::single(
+            "body",
+            Violation::out_of_range(1, MAX_IMPORT_ROWS as i64),
+        ))),
+        _ => Ok(records),
+    }
+}
+
+pub async fn import_marc(
+    _staff: Librarian,
+    params: Query<CsvImportParams>,
+    body: Bytes,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let records = read_records(&body)?;
+
+    let mut transaction = db_pool.begin().await?;
+    let mut rejected = Vec::new();
+    for (index, record) in records.iter().enumerate() {
+        let outcome = async {
+            let book = book_from_record(record).map_err(AppError::Validation)?;
+            let mut savepoint = transaction.begin().await?;
+            for contributor in &book.contributors {
+                if let AuthorRef::Name(name) = &contributor.author {
+                    create_missing_author(name.as_ref(), &mut savepoint).await?;
+                }
+            }
+            insert_book_in(&mut savepoint, &book, None).await?;
+            savepoint.commit().await?;
+            Ok(())
+        }
+        .await;
+        if let Err(e) = outcome {
+            rejected.push(RowError::new(index as u64 + 1, e)?);
+        }
+    }
+
+    finish_import(
+        transaction,
+        rejected,
+        records.len(),
+        params.dry_run.unwrap_or_default(),
+        "Books imported successfully!",
+    )
+    .await
+}
@@ -1,9 +1,39 @@
+pub mod accounts;
+pub mod api_keys;
 pub mod authors;
 pub mod books;
+pub mod bulk;
 pub mod health_check;
+pub mod holds;
+pub mod imports;
+pub mod items;
+pub mod jobs;
+pub mod ledger;
+pub mod legacy;
+pub mod loans;
+pub mod marc;
+pub mod opds;
+pub mod pagination;
+pub mod search;
+pub mod sessions;
 pub mod users;
 
+pub use accounts::*;
+pub use api_keys::*;
 pub use authors::*;
 pub use books::*;
+pub use bulk::*;
 pub use health_check::*;
+pub use holds::*;
+pub use imports::*;
+pub use items::*;
+pub use jobs::*;
+pub use ledger::*;
+pub use legacy::*;
+pub use loans::*;
+pub use marc::*;
+pub use opds::*;
+pub use pagination::*;
+pub use search::*;
+pub use sessions::*;
 pub use users::*;
@@ -0,0 +1,436 @@
+use actix_web::{
+    web::{Data, Query},
+    HttpRequest, HttpResponse,
+};
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::opds::{
+    opensearch_description, Entry, Feed, Link, Person, ACQUISITION_FEED, NAVIGATION_FEED,
+    OPENSEARCH_DESCRIPTION, REL_BORROW, REL_SUBSECTION,
+};
+use crate::routes::{
+    author_cursor, book_contributors, book_cursor, list_authors, list_books, next_page,
+    search_books, AuthorListParams, BookListParams, BookRow, Contributor, SearchParams,
+};
+use crate::validations::{author::AuthorListQuery, book::BookListQuery, search::SearchQuery};
+
+struct GenreCount {
+    genre: String,
+    books: i64,
+}
+
+fn feed_href<P: Serialize>(path: &str, params: &P) -> String {
+    match serde_urlencoded::to_string(params).unwrap_or_default() {
+        query if query.is_empty() => path.into(),
+        query => format!("{}?{}", path, query),
+    }
+}
+
+// Where the catalog is served from, as the client reached it
+fn base_url(request: &HttpRequest) -> String {
+    let connection = request.connection_info();
+    format!("{}://{}", connection.scheme(), connection.host())
+}
+
+// Atom wants a permanent IRI for every feed, its own address does
+fn feed_id(request: &HttpRequest) -> String {
+    format!("{}{}", base_url(request), request.uri())
+}
+
+fn feed_response(kind: &str, feed: Feed) -> HttpResponse {
+    HttpResponse::Ok().content_type(kind).body(feed.to_xml())
+}
+
+// Links every feed carries back to the start of the catalog and to its search
+fn catalog_links(request: &HttpRequest, kind: &str) -> Vec<Link> {
+    vec![
+        Link::new("self", &request.uri().to_string(), kind),
+        Link::new("start", "/opds", NAVIGATION_FEED).titled("Midnight Library"),
+        Link::new("search", "/opds/opensearch.xml", OPENSEARCH_DESCRIPTION),
+    ]
+}
+
+fn author_books_href(author_id: Uuid) -> String {
+    feed_href(
+        "/opds/books",
+        &BookListParams {
+            sort: Some("title".into()),
+            author_id: Some(author_id.to_string()),
+            ..Default::default()
+        },
+    )
+}
+
+// Authors are credited as such, everyone else as a contributor. Copies are physical, so
+// borrowing a book through a reader app means placing a hold on it.
+fn book_entry(id: Uuid, title: String, genre: String, contributors: &[Contributor]) -> Entry {
+    let (authors, others): (Vec<&Contributor>, Vec<&Contributor>) = contributors
+        .iter()
+        .partition(|contributor| contributor.role == "author");
+    let person = |contributor: &&Contributor| Person {
+        name: contributor.name.clone(),
+        uri: Some(author_books_href(contributor.author_id)),
+    };
+
+    Entry {
+        id: format!("urn:uuid:{}", id),
+        title,
+        authors: authors.iter().map(person).collect(),
+        contributors: others.iter().map(person).collect(),
+        categories: vec![genre],
+        links: vec![
+            Link::new(
+                REL_BORROW,
+                &format!("/api/v1/books/{}/holds", id),
+                "application/json",
+            ),
+            Link::new(
+                "alternate",
+                &format!("/api/v1/books/{}", id),
+                "application/json",
+            ),
+            Link::new(
+                "alternate",
+                &format!("/api/v1/books/{}/marc?format=marcxml", id),
+                "application/marcxml+xml",
+            ),
+        ],
+        ..Default::default()
+    }
+}
+
+fn book_row_entry(row: BookRow, contributors: &[Contributor]) -> Entry {
+    Entry {
+        updated: row.created_at,
+        identifier: row.isbn_13.map(|isbn| format!("urn:isbn:{}", isbn)),
+        publisher: row.publisher,
+        issued: row.publication_year,
+        language: row.language,
+        summary: row.description,
+        ..book_entry(row.id, row.title, row.genre, contributors)
+    }
+}
+
+async fn genre_counts(
+    author_id: Option<Uuid>,
+    db_pool: &PgPool,
+) -> Result<Vec<GenreCount>, AppError> {
+    let genres = sqlx::query_as!(
+        GenreCount,
+        r#"
+        SELECT books.genre, COUNT(*) AS "books!"
+        FROM books
+        WHERE $1::UUID IS NULL OR EXISTS (
+            SELECT 1 FROM book_contributors
+            WHERE book_contributors.book_id = books.id AND book_contributors.author_id = $1
+        )
+        GROUP BY books.genre
+        ORDER BY books.genre
+        "#,
+        author_id
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    Ok(genres)
+}
+
+pub async fn opds_root(request: HttpRequest) -> HttpResponse {
+    let section = |title: &str, content: &str, href: &str, kind: &str| Entry {
+        id: format!("urn:midnight-library:opds:{}", title.to_lowercase()),
+        title: title.into(),
+        updated: Utc::now(),
+        content: Some(content.into()),
+        links: vec![Link::new(REL_SUBSECTION, href, kind)],
+        ..Default::default()
+    };
+
+    feed_response(
+        NAVIGATION_FEED,
+        Feed {
+            id: feed_id(&request),
+            title: "Midnight Library".into(),
+            updated: Utc::now(),
+            links: catalog_links(&request, NAVIGATION_FEED),
+            entries: vec![
+                section(
+                    "Books",
+                    "The whole catalog by title",
+                    "/opds/books?sort=title",
+                    ACQUISITION_FEED,
+                ),
+                section(
+                    "Authors",
+                    "Browse the catalog by author",
+                    "/opds/authors",
+                    NAVIGATION_FEED,
+                ),
+                section(
+                    "Genres",
+                    "Browse the catalog by genre",
+                    "/opds/genres",
+                    NAVIGATION_FEED,
+                ),
+            ],
+        },
+    )
+}
+
+// The `books_index` listing as an acquisition feed, sorted by title unless asked otherwise,
+// with facets to narrow it down by genre or by one of the authors on the page
+pub async fn opds_books(
+    request: HttpRequest,
+    params: Query<BookListParams>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let mut params = params.into_inner();
+    params.sort.get_or_insert_with(|| "title".into());
+    let query: BookListQuery = params.clone().try_into().map_err(AppError::Validation)?;
+    let author_id = query.author_id.as_ref().map(|id| *id.as_ref());
+
+    let author_name = match author_id {
+        Some(author_id) => Some(
+            sqlx::query_scalar!("SELECT name FROM authors WHERE id = $1", author_id)
+                .fetch_optional(db_pool.get_ref())
+                .await?
+                .ok_or_else(|| AppError::NotFound("Author not found".into()))?,
+        ),
+        None => None,
+    };
+    let title = match (&author_name, &query.genre) {
+        (Some(name), Some(genre)) => format!("{} by {}", genre, name),
+        (Some(name), None) => format!("Books by {}", name),
+        (None, Some(genre)) => genre.clone(),
+        (None, None) => "Books".into(),
+    };
+
+    let mut rows = list_books(&query, &db_pool).await?;
+    let sort = query.sort.as_ref();
+    let next = next_page(&params, &mut rows, *query.limit.as_ref(), |row| {
+        book_cursor(row, sort)
+    });
+
+    let book_ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+    let mut contributors = book_contributors(&book_ids, &db_pool).await?;
+
+    let mut links = catalog_links(&request, ACQUISITION_FEED);
+    if let Some(next) = next {
+        links.push(Link::new(
+            "next",
+            &format!("/opds/books?{}", next),
+            ACQUISITION_FEED,
+        ));
+    }
+    if params.cursor.is_some() {
+        let first = BookListParams {
+            cursor: None,
+            ..params.clone()
+        };
+        links.push(Link::new(
+            "first",
+            &feed_href("/opds/books", &first),
+            ACQUISITION_FEED,
+        ));
+    }
+
+    // Picking a facet starts over from the first page but keeps the other group's choice
+    for genre in genre_counts(author_id, &db_pool).await? {
+        let facet = BookListParams {
+            cursor: None,
+            genre: Some(genre.genre.clone()),
+            ..params.clone()
+        };
+        links.push(Link::facet(
+            "Genre",
+            &genre.genre,
+            &feed_href("/opds/books", &facet),
+            Some(genre.books),
+            query.genre.as_ref() == Some(&genre.genre),
+        ));
+    }
+    let mut page_authors: Vec<(Uuid, String)> = contributors
+        .values()
+        .flatten()
+        .map(|contributor| (contributor.author_id, contributor.name.clone()))
+        .collect::<HashMap<_, _>>()
+        .into_iter()
+        .collect();
+    page_authors.sort_by(|a, b| a.1.cmp(&b.1));
+    for (id, name) in page_authors {
+        let facet = BookListParams {
+            cursor: None,
+            author_id: Some(id.to_string()),
+            ..params.clone()
+        };
+        links.push(Link::facet(
+            "Author",
+            &name,
+            &feed_href("/opds/books", &facet),
+            None,
+            author_id == Some(id),
+        ));
+    }
+
+    let entries = rows
+        .into_iter()
+        .map(|row| {
+            let book_contributors = contributors.remove(&row.id).unwrap_or_default();
+            book_row_entry(row, &book_contributors)
+        })
+        .collect();
+
+    Ok(feed_response(
+        ACQUISITION_FEED,
+        Feed {
+            id: feed_id(&request),
+            title,
+            updated: Utc::now(),
+            links,
+            entries,
+        },
+    ))
+}
+
+// The `authors_index` listing as a navigation feed, each author leading to their books
+pub async fn opds_authors(
+    request: HttpRequest,
+    params: Query<AuthorListParams>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let mut params = params.into_inner();
+    params.sort.get_or_insert_with(|| "name".into());
+    let query: AuthorListQuery = params.clone().try_into().map_err(AppError::Validation)?;
+
+    let mut rows = list_authors(&query, &db_pool).await?;
+    let sort = query.sort.as_ref();
+    let next = next_page(&params, &mut rows, *query.limit.as_ref(), |row| {
+        author_cursor(row, sort)
+    });
+
+    let mut links = catalog_links(&request, NAVIGATION_FEED);
+    if let Some(next) = next {
+        links.push(Link::new(
+            "next",
+            &format!("/opds/authors?{}", next),
+            NAVIGATION_FEED,
+        ));
+    }
+
+    let entries = rows
+        .into_iter()
+        .map(|row| Entry {
+            id: format!("urn:uuid:{}", row.id),
+            title: row.name,
+            updated: row.created_at,
+            content: Some(row.nationality),
+            links: vec![Link::new(
+                REL_SUBSECTION,
+                &author_books_href(row.id),
+                ACQUISITION_FEED,
+            )],
+            ..Default::default()
+        })
+        .collect();
+
+    Ok(feed_response(
+        NAVIGATION_FEED,
+        Feed {
+            id: feed_id(&request),
+            title: "Authors".into(),
+            updated: Utc::now(),
+            links,
+            entries,
+        },
+    ))
+}
+
+pub async fn opds_genres(
+    request: HttpRequest,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let entries = genre_counts(None, &db_pool)
+        .await?
+        .into_iter()
+        .map(|genre| {
+            let href = feed_href(
+                "/opds/books",
+                &BookListParams {
+                    sort: Some("title".into()),
+                    genre: Some(genre.genre.clone()),
+                    ..Default::default()
+                },
+            );
+            let mut link = Link::new(REL_SUBSECTION, &href, ACQUISITION_FEED);
+            link.count = Some(genre.books);
+            Entry {
+                id: format!("urn:midnight-library:opds:genre:{}", genre.genre),
+                content: Some(format!("{} books", genre.books)),
+                title: genre.genre,
+                updated: Utc::now(),
+                links: vec![link],
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    Ok(feed_response(
+        NAVIGATION_FEED,
+        Feed {
+            id: feed_id(&request),
+            title: "Genres".into(),
+            updated: Utc::now(),
+            links: catalog_links(&request, NAVIGATION_FEED),
+            entries,
+        },
+    ))
+}
+
+// The catalog search as an acquisition feed, best matches first
+pub async fn opds_search(
+    request: HttpRequest,
+    params: Query<SearchParams>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let terms = params.q.clone().unwrap_or_default();
+    let query: SearchQuery = params
+        .into_inner()
+        .try_into()
+        .map_err(AppError::Validation)?;
+    let results = search_books(&query, &db_pool).await?;
+
+    let book_ids: Vec<Uuid> = results.iter().map(|result| result.id).collect();
+    let mut contributors = book_contributors(&book_ids, &db_pool).await?;
+
+    let entries = results
+        .into_iter()
+        .map(|result| {
+            let book_contributors = contributors.remove(&result.id).unwrap_or_default();
+            Entry {
+                updated: result.created_at,
+                ..book_entry(result.id, result.title, result.genre, &book_contributors)
+            }
+        })
+        .collect();
+
+    Ok(feed_response(
+        ACQUISITION_FEED,
+        Feed {
+            id: feed_id(&request),
+            title: format!("Search results for \"{}\"", terms),
+            updated: Utc::now(),
+            links: catalog_links(&request, ACQUISITION_FEED),
+            entries,
+        },
+    ))
+}
+
+pub async fn opds_search_description(request: HttpRequest) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(OPENSEARCH_DESCRIPTION)
+        .body(opensearch_description(&base_url(&request)))
+}
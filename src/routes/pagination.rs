@@ -0,0 +1,56 @@
+use crate::validations::page::Cursor;
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder};
+use serde::Serialize;
+use serde_json::Value;
+
+// List queries fetch one row past the limit to learn whether another page follows. This
+// drops that row and gives the query string of the next page, keeping the filters and sort
+// of the current request.
+pub fn next_page<T, P, F>(params: &P, rows: &mut Vec<T>, limit: i64, cursor: F) -> Option<String>
+where
+    P: Serialize,
+    F: Fn(&T) -> Cursor,
+{
+    if rows.len() as i64 <= limit {
+        return None;
+    }
+    rows.truncate(limit as usize);
+
+    let last = rows.last()?;
+    let mut query: Vec<(String, String)> = match serde_json::to_value(params) {
+        Ok(Value::Object(fields)) => fields
+            .into_iter()
+            .filter(|(name, value)| name != "cursor" && !value.is_null())
+            .map(|(name, value)| match value {
+                Value::String(value) => (name, value),
+                value => (name, value.to_string()),
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    query.push(("cursor".into(), cursor(last).encode()));
+
+    Some(serde_urlencoded::to_string(query).unwrap_or_default())
+}
+
+// Answers with a `Link` header pointing at the next page, if there is one
+pub fn paginated_response<T, P, F>(
+    request: &HttpRequest,
+    params: &P,
+    mut rows: Vec<T>,
+    limit: i64,
+    cursor: F,
+) -> (HttpResponseBuilder, Vec<T>)
+where
+    P: Serialize,
+    F: Fn(&T) -> Cursor,
+{
+    let mut response = HttpResponse::Ok();
+
+    if let Some(query) = next_page(params, &mut rows, limit, cursor) {
+        let link = format!(r#"<{}?{}>; rel="next""#, request.path(), query);
+        response.insert_header(("Link", link));
+    }
+
+    (response, rows)
+}
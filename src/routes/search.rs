@@ -0,0 +1,140 @@
+use actix_web::{
+    web::{Data, Query},
+    HttpResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::routes::{book_contributors, book_json};
+use crate::validations::search::SearchQuery;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SearchParams {
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+}
+
+pub struct SearchRow {
+    pub id: Uuid,
+    pub title: String,
+    pub genre: String,
+    pub created_at: DateTime<Utc>,
+    pub rank: f32,
+    pub snippet: String,
+}
+
+pub async fn search_books(
+    query: &SearchQuery,
+    db_pool: &PgPool,
+) -> Result<Vec<SearchRow>, AppError> {
+    // Titles weigh more than author names, and those more than genres, so ts_rank_cd puts
+    // title matches first. Snippets are HTML, so the text is escaped before it's highlighted
+    let mut results = sqlx::query_as!(
+        SearchRow,
+        r#"
+        SELECT
+            books.id,
+            books.title,
+            books.genre,
+            books.created_at,
+            ts_rank_cd(books.search_vector, query) AS "rank!",
+            ts_headline(
+                'english',
+                html_escape(
+                    concat_ws(' · ', books.title, books_contributor_names(books.id), books.genre)
+                ),
+                query,
+                'StartSel=<mark>, StopSel=</mark>, HighlightAll=true'
+            ) AS "snippet!"
+        FROM books, to_tsquery('english', $1) AS query
+        WHERE books.search_vector @@ query
+        ORDER BY "rank!" DESC, books.id
+        LIMIT $2
+        "#,
+        query.terms.as_ref(),
+        query.limit.as_ref()
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    // A misspelt word finds nothing in the full-text index, so only then fall back to
+    // trigram similarity on titles and author names, ranked by how close they are. Each
+    // match is its own query so both can use their trigram index
+    if let (true, Some(fuzzy)) = (results.is_empty(), query.terms.fuzzy()) {
+        results = sqlx::query_as!(
+            SearchRow,
+            r#"
+            WITH matches AS (
+                SELECT books.id AS book_id FROM books WHERE $1 <% books.title
+                UNION
+                SELECT book_contributors.book_id
+                FROM authors
+                JOIN book_contributors ON book_contributors.author_id = authors.id
+                WHERE $1 <% authors.name
+            )
+            SELECT
+                books.id,
+                books.title,
+                books.genre,
+                books.created_at,
+                GREATEST(word_similarity($1, books.title), contributors.similarity) AS "rank!",
+                html_escape(
+                    concat_ws(' · ', books.title, contributors.names, books.genre)
+                ) AS "snippet!"
+            FROM matches
+            JOIN books ON books.id = matches.book_id,
+            LATERAL (
+                SELECT
+                    string_agg(authors.name, ', ' ORDER BY book_contributors.position) AS names,
+                    MAX(word_similarity($1, authors.name)) AS similarity
+                FROM book_contributors
+                JOIN authors ON authors.id = book_contributors.author_id
+                WHERE book_contributors.book_id = books.id
+            ) AS contributors
+            ORDER BY "rank!" DESC, books.id
+            LIMIT $2
+            "#,
+            fuzzy,
+            query.limit.as_ref()
+        )
+        .fetch_all(db_pool)
+        .await?;
+    }
+
+    Ok(results)
+}
+
+pub async fn search(
+    params: Query<SearchParams>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let query: SearchQuery = params
+        .into_inner()
+        .try_into()
+        .map_err(AppError::Validation)?;
+    let results = search_books(&query, &db_pool).await?;
+
+    let book_ids: Vec<Uuid> = results.iter().map(|result| result.id).collect();
+    let mut contributors = book_contributors(&book_ids, &db_pool).await?;
+
+    let results: Vec<serde_json::Value> = results
+        .into_iter()
+        .map(|result| {
+            let mut book = book_json(
+                result.id,
+                result.title,
+                result.genre,
+                contributors.remove(&result.id).unwrap_or_default(),
+            );
+            book["rank"] = json!(result.rank);
+            book["snippet"] = json!(result.snippet);
+            book
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(results))
+}
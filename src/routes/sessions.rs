@@ -0,0 +1,112 @@
+use crate::auth::{
+    expired_session_cookie, session_cookie, session_token, verify_password, Credential, CurrentUser,
+};
+use crate::configuration::AuthConfig;
+use crate::errors::AppError;
+use actix_web::{
+    web::{Data, Json},
+    HttpResponse,
+};
+use chrono::{Duration, Utc};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[derive(Serialize, Deserialize)]
+pub struct LoginData {
+    pub email: String,
+    pub password: String,
+}
+
+// The token comes back both as a cookie for browsers and in the body for API clients, which
+// send it as `Authorization: Bearer <token>`
+pub async fn login(
+    input: Json<LoginData>,
+    auth_config: Data<AuthConfig>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let LoginData { email, password } = input.into_inner();
+
+    let user = sqlx::query!(
+        "SELECT id, password_hash FROM users WHERE email = $1",
+        email
+    )
+    .fetch_optional(db_pool.get_ref())
+    .await?;
+    let (user_id, password_hash) = match user {
+        Some(user) => (Some(user.id), user.password_hash),
+        None => (None, None),
+    };
+    // Which of the two was wrong stays unsaid, so logins can't be used to probe for accounts
+    let user_id = match verify_password(password, password_hash).await? {
+        true => user_id,
+        false => None,
+    }
+    .ok_or_else(|| AppError::Unauthorized("Invalid email or password".into()))?;
+
+    let now = Utc::now();
+    let expires_at = now + Duration::hours(auth_config.session_ttl_hours);
+    let mut transaction = db_pool.begin().await?;
+    sqlx::query!(
+        "DELETE FROM sessions WHERE user_id = $1 AND expires_at <= $2",
+        user_id,
+        now
+    )
+    .execute(&mut *transaction)
+    .await?;
+    let session_id = sqlx::query_scalar!(
+        "INSERT INTO sessions (user_id, created_at, expires_at)
+        VALUES ($1, $2, $3)
+        RETURNING id",
+        user_id,
+        now,
+        expires_at
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    let token = session_token(session_id, auth_config.session_secret.expose_secret());
+    Ok(HttpResponse::Ok()
+        .cookie(session_cookie(token.clone(), &auth_config))
+        .json(json!({
+            "message": "Logged in successfully!",
+            "user_id": user_id,
+            "token": token,
+            "expires_at": expires_at
+        })))
+}
+
+pub async fn logout(user: CurrentUser, db_pool: Data<PgPool>) -> Result<HttpResponse, AppError> {
+    let Credential::Session(session_id) = user.credential else {
+        return Err(AppError::Forbidden(
+            "API keys are revoked, not logged out".into(),
+        ));
+    };
+    sqlx::query!("DELETE FROM sessions WHERE id = $1", session_id)
+        .execute(db_pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .cookie(expired_session_cookie())
+        .json(json!({"message": "Logged out successfully!"})))
+}
+
+pub async fn show_current_user(
+    user: CurrentUser,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let verified_at =
+        sqlx::query_scalar!("SELECT email_verified_at FROM users WHERE id = $1", user.id)
+            .fetch_one(db_pool.get_ref())
+            .await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "id": user.id,
+        "name": user.name,
+        "email": user.email,
+        "email_verified": verified_at.is_some(),
+        "role": user.role.as_str()
+    })))
+}
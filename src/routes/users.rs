@@ -1,40 +1,238 @@
-use crate::validations::user::NewUser;
+use crate::auth::{hash_password, parse_role, Admin, CurrentUser, Role};
+use crate::configuration::AuthConfig;
+use crate::errors::AppError;
+use crate::mailer::{Email, Mailer};
+use crate::routes::send_verification;
+use crate::validations::user::{NewUser, UserPatch, ValidatedRole};
+use crate::validations::{ValidationErrors, Violation};
 use actix_web::{
-    web::{Data, Json},
+    web::{Data, Json, Path},
     HttpResponse,
 };
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::PgPool;
+use uuid::Uuid;
 
 #[derive(Serialize, Deserialize)]
 pub struct NewUserData {
     pub name: String,
     pub email: String,
+    pub password: Option<String>,
 }
 
-pub async fn create_user(input: Json<NewUserData>, db_pool: Data<PgPool>) -> HttpResponse {
-    let new_user: NewUser = match input.0.try_into() {
-        Ok(value) => value,
-        Err(error) => return HttpResponse::BadRequest().body(error),
+// New users are sent a token to confirm their email with
+pub async fn create_user(
+    input: Json<NewUserData>,
+    db_pool: Data<PgPool>,
+    mailer: Data<dyn Mailer>,
+    auth_config: Data<AuthConfig>,
+) -> Result<HttpResponse, AppError> {
+    let new_user: NewUser = input.0.try_into().map_err(AppError::Validation)?;
+    let password_hash = match &new_user.password {
+        Some(password) => Some(hash_password(password.as_ref().into()).await?),
+        None => None,
     };
 
-    match sqlx::query!(
-        "INSERT INTO users (name, email, created_at)
-        VALUES ($1, $2, $3)
+    let mut transaction = db_pool.begin().await?;
+    let record = sqlx::query!(
+        "INSERT INTO users (name, email, password_hash, created_at)
+        VALUES ($1, $2, $3, $4)
         RETURNING id",
         new_user.name.as_ref(),
         new_user.email.as_ref(),
+        password_hash,
         Utc::now()
     )
-    .fetch_one(db_pool.get_ref())
+    .fetch_one(&mut *transaction)
     .await
-    {
-        Ok(record) => HttpResponse::Ok().json(json!({
-            "message": "User created successfully!",
-            "user_id": record.id
-        })),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    .map_err(email_in_use)?;
+    send_verification(
+        &mut transaction,
+        mailer.get_ref(),
+        &auth_config,
+        record.id,
+        new_user.name.as_ref(),
+        new_user.email.as_ref(),
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "User created successfully!",
+        "user_id": record.id
+    })))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserPatchData {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+pub async fn update_user(
+    current_user: CurrentUser,
+    path: Path<String>,
+    input: Json<NewUserData>,
+    db_pool: Data<PgPool>,
+    mailer: Data<dyn Mailer>,
+    auth_config: Data<AuthConfig>,
+) -> Result<HttpResponse, AppError> {
+    let user: NewUser = input.0.try_into().map_err(AppError::Validation)?;
+    // Passwords are set when registering, an update mustn't let anyone take over an account
+    if user.password.is_some() {
+        return Err(AppError::Validation(ValidationErrors::single(
+            "password",
+            Violation::not_allowed("can only be set when registering"),
+        )));
+    }
+
+    apply_user_patch(
+        current_user,
+        path.into_inner(),
+        user.into(),
+        db_pool,
+        mailer,
+        auth_config,
+    )
+    .await
+}
+
+pub async fn patch_user(
+    current_user: CurrentUser,
+    path: Path<String>,
+    input: Json<UserPatchData>,
+    db_pool: Data<PgPool>,
+    mailer: Data<dyn Mailer>,
+    auth_config: Data<AuthConfig>,
+) -> Result<HttpResponse, AppError> {
+    let patch: UserPatch = input.0.try_into().map_err(AppError::Validation)?;
+
+    apply_user_patch(
+        current_user,
+        path.into_inner(),
+        patch,
+        db_pool,
+        mailer,
+        auth_config,
+    )
+    .await
+}
+
+async fn apply_user_patch(
+    current_user: CurrentUser,
+    user_id: String,
+    patch: UserPatch,
+    db_pool: Data<PgPool>,
+    mailer: Data<dyn Mailer>,
+    auth_config: Data<AuthConfig>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&user_id).unwrap_or_default();
+    let new_email = patch.email.as_ref().map(|email| email.as_ref());
+
+    let mut transaction = db_pool.begin().await?;
+    let user = sqlx::query!(
+        "SELECT name, role, email FROM users WHERE id = $1 FOR UPDATE",
+        user_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User to be updated not found".into()))?;
+    let role = parse_role(user.role)?;
+    let name = patch
+        .name
+        .as_ref()
+        .map_or(user.name.as_str(), |name| name.as_ref());
+    let changes_email = new_email.is_some_and(|email| email != user.email);
+    current_user.require_account_editor(user_id, role, changes_email)?;
+
+    // A new email address has to be confirmed again, and the old one hears about the change.
+    // Sending the new confirmation revokes any token still out for the old address
+    sqlx::query!(
+        "UPDATE users
+        SET name = COALESCE($2, name), email = COALESCE($3, email),
+            email_verified_at = CASE
+                WHEN $3 IS NULL OR $3 = email THEN email_verified_at
+            END,
+            email_changed_at = CASE
+                WHEN $3 IS NULL OR $3 = email THEN email_changed_at
+                ELSE $4
+            END
+        WHERE id = $1",
+        user_id,
+        patch.name.as_ref().map(|name| name.as_ref()),
+        new_email,
+        Utc::now()
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(email_in_use)?;
+    if let Some(new_email) = new_email.filter(|_| changes_email) {
+        mailer
+            .send(Email {
+                to: user.email,
+                subject: "Your Midnight Library email was changed".into(),
+                body: format!(
+                    "Hello {},\n\n\
+                    The email address on your Midnight Library account was changed to {}.\n\n\
+                    If you didn't make this change, contact the library.\n",
+                    name, new_email
+                ),
+            })
+            .await?;
+        send_verification(
+            &mut transaction,
+            mailer.get_ref(),
+            &auth_config,
+            user_id,
+            name,
+            new_email,
+        )
+        .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": "User updated successfully!"})))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserRoleData {
+    pub role: String,
+}
+
+pub async fn update_user_role(
+    admin: Admin,
+    path: Path<String>,
+    input: Json<UserRoleData>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let role = ValidatedRole::new(input.into_inner().role)
+        .map_err(|violation| AppError::Validation(ValidationErrors::single("role", violation)))?
+        .role();
+    let user_id = Uuid::parse_str(&path.into_inner()).unwrap_or_default();
+    // Otherwise the last admin could lock everyone out of handing out roles
+    if user_id == admin.0.id && role != Role::Admin {
+        return Err(AppError::Conflict("Admins can't demote themselves".into()));
+    }
+
+    let result = sqlx::query!(
+        "UPDATE users SET role = $2 WHERE id = $1",
+        user_id,
+        role.as_str()
+    )
+    .execute(db_pool.get_ref())
+    .await?;
+
+    match result.rows_affected() == 1 {
+        true => Ok(HttpResponse::Ok().json(json!({"message": "User role updated successfully!"}))),
+        false => Err(AppError::NotFound("User to be updated not found".into())),
+    }
+}
+
+fn email_in_use(e: sqlx::Error) -> AppError {
+    match AppError::from(e) {
+        AppError::Conflict(_) => AppError::Conflict("User email already in use".into()),
+        e => e,
     }
 }
@@ -1,28 +1,258 @@
+use crate::configuration::ApplicationConfigs;
+use crate::errors::AppError;
+use crate::gutendex::Gutendex;
+use crate::jobs::start_workers;
+use crate::mailer::mailer;
+use crate::metadata::metadata_provider;
 use crate::routes;
 use actix_web::dev::Server;
+use actix_web::middleware::DefaultHeaders;
 use actix_web::{web, App, HttpServer};
 use sqlx::PgPool;
 use std::net::TcpListener;
 
-pub fn run(address: TcpListener, db_pool: PgPool) -> Result<Server, std::io::Error> {
+pub fn run(
+    address: TcpListener,
+    db_pool: PgPool,
+    config: ApplicationConfigs,
+) -> Result<Server, std::io::Error> {
     let db_pool = web::Data::new(db_pool);
+    let circulation = web::Data::new(config.circulation);
+    // One client for every outgoing request, so connections are pooled across handlers
+    let http_client = reqwest::Client::new();
+    let metadata_provider =
+        web::Data::from(metadata_provider(&config.metadata, http_client.clone())?);
+    let gutendex = web::Data::new(Gutendex::new(&config.gutendex, http_client.clone()));
+    start_workers(
+        db_pool.get_ref().clone(),
+        gutendex.clone().into_inner(),
+        config.jobs.clone(),
+    );
+    let jobs = web::Data::new(config.jobs);
+    let auth = web::Data::new(config.auth);
+    let mailer = web::Data::from(mailer(&config.mailer)?);
     let server = HttpServer::new(move || {
         App::new()
             .route("/health_check", web::get().to(routes::health_check))
-            .route("/books", web::get().to(routes::books_index))
-            .route("/books/{book_id}", web::get().to(routes::show_book))
-            .route("/books/create", web::post().to(routes::create_book))
-            .route("/books/delete", web::post().to(routes::delete_book))
-            .route("/authors", web::get().to(routes::authors_index))
-            .route("/authors/{author_id}", web::get().to(routes::show_author))
-            .route("/authors/create", web::post().to(routes::create_author))
-            .route("/authors/delete", web::post().to(routes::delete_author))
-            .route("/users/create", web::post().to(routes::create_user))
-            .route("/seed_authors", web::get().to(routes::seed_authors))
+            .service(web::scope("/api/v1").configure(api_v1))
+            .service(web::scope("/opds").configure(opds))
+            .service(
+                web::scope("")
+                    .wrap(
+                        DefaultHeaders::new()
+                            .add(("Deprecation", "true"))
+                            .add(("Link", r#"</api/v1>; rel="successor-version""#)),
+                    )
+                    .configure(legacy_api),
+            )
             .app_data(db_pool.clone())
+            .app_data(circulation.clone())
+            .app_data(jobs.clone())
+            .app_data(auth.clone())
+            .app_data(mailer.clone())
+            .app_data(metadata_provider.clone())
+            .app_data(
+                web::JsonConfig::default()
+                    .error_handler(|e, _| AppError::MalformedRequest(e.to_string()).into()),
+            )
     })
     .listen(address)?
     .run();
 
     Ok(server)
 }
+
+// OPDS catalog for e-reader apps, unversioned since clients follow its links
+fn opds(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(routes::opds_root))
+        .route("/books", web::get().to(routes::opds_books))
+        .route("/authors", web::get().to(routes::opds_authors))
+        .route("/genres", web::get().to(routes::opds_genres))
+        .route("/search", web::get().to(routes::opds_search))
+        .route(
+            "/opensearch.xml",
+            web::get().to(routes::opds_search_description),
+        );
+}
+
+fn api_v1(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/books")
+            .route(web::get().to(routes::books_index))
+            .route(web::post().to(routes::create_book)),
+    )
+    .service(
+        web::resource("/books/import")
+            .app_data(web::PayloadConfig::new(routes::MAX_CSV_BYTES))
+            .route(web::post().to(routes::import_books)),
+    )
+    .service(web::resource("/books/export.csv").route(web::get().to(routes::export_books)))
+    .service(web::resource("/books/import_isbn").route(web::post().to(routes::import_isbn)))
+    .service(
+        web::resource("/books/import_marc")
+            .app_data(web::PayloadConfig::new(routes::MAX_MARC_BYTES))
+            .route(web::post().to(routes::import_marc)),
+    )
+    .service(web::resource("/imports/gutendex").route(web::post().to(routes::import_gutendex)))
+    .service(
+        web::resource("/books/{book_id}")
+            .route(web::get().to(routes::show_book))
+            .route(web::put().to(routes::update_book))
+            .route(web::patch().to(routes::patch_book))
+            .route(web::delete().to(routes::delete_book)),
+    )
+    .service(web::resource("/books/{book_id}/marc").route(web::get().to(routes::export_book_marc)))
+    .service(
+        web::resource("/books/{book_id}/items")
+            .route(web::get().to(routes::items_index))
+            .route(web::post().to(routes::create_item)),
+    )
+    .service(
+        web::resource("/books/{book_id}/items/{item_id}")
+            .route(web::get().to(routes::show_item))
+            .route(web::put().to(routes::update_item))
+            .route(web::delete().to(routes::delete_item)),
+    )
+    .service(
+        web::resource("/books/{book_id}/holds")
+            .route(web::get().to(routes::book_holds_index))
+            .route(web::post().to(routes::create_hold)),
+    )
+    .service(
+        web::resource("/authors")
+            .route(web::get().to(routes::authors_index))
+            .route(web::post().to(routes::create_author)),
+    )
+    .service(web::resource("/search").route(web::get().to(routes::search)))
+    .service(web::resource("/authors/seed").route(web::post().to(routes::import_gutendex)))
+    .service(
+        web::resource("/authors/import")
+            .app_data(web::PayloadConfig::new(routes::MAX_CSV_BYTES))
+            .route(web::post().to(routes::import_authors)),
+    )
+    .service(web::resource("/authors/export.csv").route(web::get().to(routes::export_authors)))
+    .service(web::resource("/authors/suggest").route(web::get().to(routes::suggest_authors)))
+    .service(
+        web::resource("/authors/{author_id}")
+            .route(web::get().to(routes::show_author))
+            .route(web::put().to(routes::update_author))
+            .route(web::patch().to(routes::patch_author))
+            .route(web::delete().to(routes::delete_author)),
+    )
+    .service(web::resource("/users").route(web::post().to(routes::create_user)))
+    .service(web::resource("/login").route(web::post().to(routes::login)))
+    .service(web::resource("/logout").route(web::post().to(routes::logout)))
+    .service(web::resource("/me").route(web::get().to(routes::show_current_user)))
+    .service(web::resource("/verify_email").route(web::post().to(routes::verify_email)))
+    .service(
+        web::resource("/verify_email/resend").route(web::post().to(routes::resend_verification)),
+    )
+    .service(web::resource("/password_reset").route(web::post().to(routes::request_password_reset)))
+    .service(web::resource("/password_reset/confirm").route(web::post().to(routes::reset_password)))
+    .service(
+        web::resource("/api_keys")
+            .route(web::get().to(routes::api_keys_index))
+            .route(web::post().to(routes::create_api_key)),
+    )
+    .service(
+        web::resource("/api_keys/{api_key_id}").route(web::delete().to(routes::revoke_api_key)),
+    )
+    .service(
+        web::resource("/users/{user_id}")
+            .route(web::put().to(routes::update_user))
+            .route(web::patch().to(routes::patch_user)),
+    )
+    .service(web::resource("/users/{user_id}/role").route(web::put().to(routes::update_user_role)))
+    .service(web::resource("/users/{user_id}/balance").route(web::get().to(routes::show_balance)))
+    .service(
+        web::resource("/users/{user_id}/payments").route(web::post().to(routes::create_payment)),
+    )
+    .service(web::resource("/users/{user_id}/waivers").route(web::post().to(routes::create_waiver)))
+    .service(web::resource("/users/{user_id}/holds").route(web::get().to(routes::user_holds_index)))
+    .service(
+        web::resource("/users/{user_id}/holds/{hold_id}/cancel")
+            .route(web::post().to(routes::cancel_hold)),
+    )
+    .service(web::resource("/jobs/{job_id}").route(web::get().to(routes::show_job)))
+    .service(web::resource("/jobs/{job_id}/cancel").route(web::post().to(routes::cancel_job)))
+    .service(web::resource("/loans").route(web::post().to(routes::checkout_loan)))
+    .service(web::resource("/loans/{loan_id}/return").route(web::post().to(routes::return_loan)))
+    .service(web::resource("/loans/{loan_id}/renew").route(web::post().to(routes::renew_loan)));
+}
+
+// Routes from before the API was versioned, kept for existing clients while they migrate
+fn legacy_api(cfg: &mut web::ServiceConfig) {
+    cfg.route("/books", web::get().to(routes::books_index))
+        .route("/books/{book_id}", web::get().to(routes::show_book))
+        .route("/books/{book_id}", web::put().to(routes::update_book))
+        .route("/books/{book_id}", web::patch().to(routes::patch_book))
+        .route("/books/create", web::post().to(routes::create_book))
+        .route("/books/delete", web::post().to(routes::legacy_delete_book))
+        .route("/books/{book_id}/items", web::get().to(routes::items_index))
+        .route(
+            "/books/{book_id}/items/{item_id}",
+            web::get().to(routes::show_item),
+        )
+        .route(
+            "/books/{book_id}/items/create",
+            web::post().to(routes::create_item),
+        )
+        .route(
+            "/books/{book_id}/items/delete",
+            web::post().to(routes::legacy_delete_item),
+        )
+        .route(
+            "/books/{book_id}/items/{item_id}/update",
+            web::post().to(routes::update_item),
+        )
+        .route(
+            "/books/{book_id}/holds",
+            web::get().to(routes::book_holds_index),
+        )
+        .route(
+            "/books/{book_id}/holds",
+            web::post().to(routes::create_hold),
+        )
+        .route("/authors", web::get().to(routes::authors_index))
+        .route("/authors/{author_id}", web::get().to(routes::show_author))
+        .route("/authors/{author_id}", web::put().to(routes::update_author))
+        .route(
+            "/authors/{author_id}",
+            web::patch().to(routes::patch_author),
+        )
+        .route("/authors/create", web::post().to(routes::create_author))
+        .route(
+            "/authors/delete",
+            web::post().to(routes::legacy_delete_author),
+        )
+        .route("/users/create", web::post().to(routes::create_user))
+        .route("/users/{user_id}", web::put().to(routes::update_user))
+        .route("/users/{user_id}", web::patch().to(routes::patch_user))
+        .route(
+            "/users/{user_id}/balance",
+            web::get().to(routes::show_balance),
+        )
+        .route(
+            "/users/{user_id}/payments",
+            web::post().to(routes::create_payment),
+        )
+        .route(
+            "/users/{user_id}/waivers",
+            web::post().to(routes::create_waiver),
+        )
+        .route(
+            "/users/{user_id}/holds",
+            web::get().to(routes::user_holds_index),
+        )
+        .route(
+            "/users/{user_id}/holds/{hold_id}/cancel",
+            web::post().to(routes::cancel_hold),
+        )
+        .route("/loans/checkout", web::post().to(routes::checkout_loan))
+        .route(
+            "/loans/{loan_id}/return",
+            web::post().to(routes::return_loan),
+        )
+        .route("/loans/{loan_id}/renew", web::post().to(routes::renew_loan))
+        .route("/seed_authors", web::get().to(routes::import_gutendex));
+}
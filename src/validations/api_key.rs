@@ -0,0 +1,100 @@
+use crate::{
+    auth::ApiKeyScope,
+    routes::NewApiKeyData,
+    validations::{required_text, ValidationErrors, Violation},
+};
+
+pub const MAX_API_KEY_DAYS: i64 = 3650;
+
+pub struct NewApiKey {
+    pub name: ValidatedApiKeyName,
+    pub scope: ApiKeyScope,
+    pub expires_in_days: Option<i64>,
+}
+
+impl TryFrom<NewApiKeyData> for NewApiKey {
+    type Error = ValidationErrors;
+
+    fn try_from(value: NewApiKeyData) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let name = errors.check("name", ValidatedApiKeyName::new(value.name));
+        let scope = errors.check("scope", api_key_scope(&value.scope));
+        let expires_in_days = value
+            .expires_in_days
+            .and_then(|days| errors.check("expires_in_days", api_key_lifetime(days)));
+
+        match (name, scope) {
+            (Some(name), Some(scope)) if errors.is_empty() => Ok(Self {
+                name,
+                scope,
+                expires_in_days,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+// Says what the key is for, such as which kiosk holds it
+pub struct ValidatedApiKeyName(String);
+
+impl ValidatedApiKeyName {
+    pub fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 100)?;
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for ValidatedApiKeyName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+fn api_key_scope(value: &str) -> Result<ApiKeyScope, Violation> {
+    value.parse().map_err(|_| {
+        let scopes: Vec<&str> = ApiKeyScope::ALL.iter().map(ApiKeyScope::as_str).collect();
+        Violation::not_allowed(&format!("must be one of {}", scopes.join(", ")))
+    })
+}
+
+fn api_key_lifetime(days: i64) -> Result<i64, Violation> {
+    match (1..=MAX_API_KEY_DAYS).contains(&days) {
+        true => Ok(days),
+        false => Err(Violation::out_of_range(1, MAX_API_KEY_DAYS)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validations::Rule;
+
+    fn data(scope: &str, expires_in_days: Option<i64>) -> NewApiKeyData {
+        NewApiKeyData {
+            name: "Front desk kiosk".into(),
+            scope: scope.into(),
+            expires_in_days,
+        }
+    }
+
+    #[test]
+    fn new_api_key_success() {
+        let key = NewApiKey::try_from(data("circulation", Some(90))).unwrap();
+        assert_eq!(key.scope, ApiKeyScope::Circulation);
+        assert_eq!(key.expires_in_days, Some(90));
+
+        let key = NewApiKey::try_from(data("catalog_read", None)).unwrap();
+        assert_eq!(key.expires_in_days, None);
+    }
+
+    #[test]
+    fn new_api_key_failure() {
+        let errors = NewApiKey::try_from(data("everything", Some(0)))
+            .err()
+            .unwrap();
+        assert_eq!(errors.errors()[0].field, "scope");
+        assert_eq!(errors.errors()[0].violation.rule, Rule::NotAllowed);
+        assert_eq!(errors.errors()[1].field, "expires_in_days");
+        assert_eq!(errors.errors()[1].violation.rule, Rule::OutOfRange);
+    }
+}
@@ -1,4 +1,12 @@
-use crate::routes::NewAuthorData;
+use crate::{
+    routes::{AuthorListParams, AuthorPatchData, AuthorSuggestParams, NewAuthorData},
+    validations::{
+        page::{Cursor, ValidatedLimit, ValidatedSort},
+        required_text, ValidationErrors, Violation,
+    },
+};
+
+pub const AUTHOR_SORTS: [&str; 2] = ["created_at", "name"];
 
 pub struct NewAuthor {
     pub name: ValidatedAuthorName,
@@ -6,27 +14,87 @@ pub struct NewAuthor {
 }
 
 impl TryFrom<NewAuthorData> for NewAuthor {
-    type Error = String;
+    type Error = ValidationErrors;
 
     fn try_from(value: NewAuthorData) -> Result<Self, Self::Error> {
-        let name = ValidatedAuthorName::new(value.name)?;
-        let nationality = ValidatedAuthorNationality::new(value.nationality)?;
+        let mut errors = ValidationErrors::default();
+        let name = errors.check("name", ValidatedAuthorName::new(value.name));
+        let nationality = errors.check(
+            "nationality",
+            ValidatedAuthorNationality::new(value.nationality),
+        );
+
+        match (name, nationality) {
+            (Some(name), Some(nationality)) => Ok(Self { name, nationality }),
+            _ => Err(errors),
+        }
+    }
+}
+
+pub struct AuthorPatch {
+    pub name: Option<ValidatedAuthorName>,
+    pub nationality: Option<ValidatedAuthorNationality>,
+}
+
+impl TryFrom<AuthorPatchData> for AuthorPatch {
+    type Error = ValidationErrors;
+
+    fn try_from(value: AuthorPatchData) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let name = value
+            .name
+            .and_then(|name| errors.check("name", ValidatedAuthorName::new(name)));
+        let nationality = value.nationality.and_then(|nationality| {
+            errors.check("nationality", ValidatedAuthorNationality::new(nationality))
+        });
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        if name.is_none() && nationality.is_none() {
+            return Err(ValidationErrors::single("body", Violation::empty()));
+        }
+
         Ok(Self { name, nationality })
     }
 }
 
+impl From<NewAuthor> for AuthorPatch {
+    fn from(value: NewAuthor) -> Self {
+        Self {
+            name: Some(value.name),
+            nationality: Some(value.nationality),
+        }
+    }
+}
+
+pub struct AuthorSuggestQuery {
+    pub q: ValidatedAuthorName,
+    pub limit: ValidatedLimit,
+}
+
+impl TryFrom<AuthorSuggestParams> for AuthorSuggestQuery {
+    type Error = ValidationErrors;
+
+    fn try_from(value: AuthorSuggestParams) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let q = errors.check("q", ValidatedAuthorName::new(value.q.unwrap_or_default()));
+        let limit = errors.check("limit", ValidatedLimit::new(value.limit));
+
+        match (q, limit) {
+            (Some(q), Some(limit)) => Ok(Self { q, limit }),
+            _ => Err(errors),
+        }
+    }
+}
+
 pub struct ValidatedAuthorName(String);
 
 impl ValidatedAuthorName {
-    pub fn new(value: String) -> Result<Self, String> {
-        let is_empty_or_whitespace = value.trim().is_empty();
-        let size_too_big = value.chars().count() > 256;
-
-        if is_empty_or_whitespace || size_too_big {
-            Err(format!("'{}' is not a valid author name.", value))
-        } else {
-            Ok(Self(value))
-        }
+    pub fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 256)?;
+        Ok(Self(value))
     }
 }
 
@@ -39,15 +107,9 @@ impl AsRef<str> for ValidatedAuthorName {
 pub struct ValidatedAuthorNationality(String);
 
 impl ValidatedAuthorNationality {
-    fn new(value: String) -> Result<Self, String> {
-        let is_empty_or_whitespace = value.trim().is_empty();
-        let size_too_big = value.chars().count() > 80;
-
-        if is_empty_or_whitespace || size_too_big {
-            Err(format!("'{}' is not a valid author nationality.", value))
-        } else {
-            Ok(Self(value))
-        }
+    fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 80)?;
+        Ok(Self(value))
     }
 }
 
@@ -57,6 +119,39 @@ impl AsRef<str> for ValidatedAuthorNationality {
     }
 }
 
+pub struct AuthorListQuery {
+    pub limit: ValidatedLimit,
+    pub sort: ValidatedSort,
+    pub cursor: Option<Cursor>,
+    pub nationality: Option<String>,
+}
+
+impl TryFrom<AuthorListParams> for AuthorListQuery {
+    type Error = ValidationErrors;
+
+    fn try_from(value: AuthorListParams) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let limit = errors.check("limit", ValidatedLimit::new(value.limit));
+        let sort = errors.check("sort", ValidatedSort::new(value.sort, &AUTHOR_SORTS));
+        let cursor = match (&sort, value.cursor) {
+            (Some(sort), Some(cursor)) => {
+                errors.check("cursor", Cursor::decode(&cursor, sort.as_ref()))
+            }
+            _ => None,
+        };
+
+        match (limit, sort) {
+            (Some(limit), Some(sort)) if errors.is_empty() => Ok(Self {
+                limit,
+                sort,
+                cursor,
+                nationality: value.nationality,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,4 +233,61 @@ mod tests {
         };
         assert!(NewAuthor::try_from(data).is_err());
     }
+
+    #[test]
+    fn author_list_sorted_by_name() {
+        let data = AuthorListParams {
+            sort: Some(String::from("name")),
+            ..Default::default()
+        };
+        let query = AuthorListQuery::try_from(data).unwrap();
+        assert_eq!(query.sort.as_ref(), "name");
+    }
+
+    #[test]
+    fn author_list_with_cursor_for_other_sort() {
+        let data = AuthorListParams {
+            sort: Some(String::from("name")),
+            cursor: Some(Cursor::new("created_at", String::new(), Default::default()).encode()),
+            ..Default::default()
+        };
+        assert!(AuthorListQuery::try_from(data).is_err());
+    }
+
+    #[test]
+    fn author_suggest_needs_query() {
+        let errors = AuthorSuggestQuery::try_from(AuthorSuggestParams::default())
+            .err()
+            .unwrap();
+        assert_eq!(errors.errors()[0].field, "q");
+    }
+
+    #[test]
+    fn author_patch_success() {
+        let data = AuthorPatchData {
+            name: None,
+            nationality: Some(String::from("English")),
+        };
+        let patch = AuthorPatch::try_from(data).unwrap();
+        assert!(patch.name.is_none());
+        assert_eq!(patch.nationality.unwrap().as_ref(), "English");
+    }
+
+    #[test]
+    fn author_patch_invalid_field() {
+        let data = AuthorPatchData {
+            name: Some("a".repeat(257)),
+            nationality: None,
+        };
+        assert!(AuthorPatch::try_from(data).is_err());
+    }
+
+    #[test]
+    fn empty_author_patch() {
+        let data = AuthorPatchData {
+            name: None,
+            nationality: None,
+        };
+        assert!(AuthorPatch::try_from(data).is_err());
+    }
 }
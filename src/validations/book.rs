@@ -1,42 +1,248 @@
-use crate::{routes::NewBookData, validations::author::ValidatedAuthorName};
+use crate::{
+    routes::{
+        BookListParams, BookMetadataData, BookPatchData, ContributorData, IsbnImportData,
+        NewBookData,
+    },
+    validations::{
+        author::ValidatedAuthorName,
+        loan::ValidatedId,
+        page::{Cursor, ValidatedLimit, ValidatedSort},
+        required_text, ValidationErrors, Violation,
+    },
+};
+use chrono::{Datelike, Utc};
+
+pub const BOOK_SORTS: [&str; 2] = ["created_at", "title"];
+pub const CONTRIBUTOR_ROLES: [&str; 4] = ["author", "editor", "translator", "illustrator"];
+pub const MAX_CONTRIBUTORS: usize = 50;
+pub const MAX_PAGE_COUNT: i32 = 100_000;
 
 pub struct NewBook {
     pub title: ValidatedBookTitle,
-    pub author: ValidatedAuthorName,
+    pub contributors: Vec<NewContributor>,
     pub genre: ValidatedBookGenre,
+    pub metadata: BookMetadata,
 }
 
 impl TryFrom<NewBookData> for NewBook {
-    type Error = String;
+    type Error = ValidationErrors;
 
     fn try_from(value: NewBookData) -> Result<Self, Self::Error> {
-        let title = ValidatedBookTitle::new(value.title)?;
-        let author = ValidatedAuthorName::new(value.author)?;
-        let genre = ValidatedBookGenre::new(value.genre)?;
+        let mut errors = ValidationErrors::default();
+        let title = errors.check("title", ValidatedBookTitle::new(value.title));
+        let contributors = match NewContributor::from_payload(
+            value.author_id,
+            value.author,
+            value.contributors,
+            &mut errors,
+        ) {
+            Some(contributors) => contributors,
+            None => {
+                errors.add("author_id", Violation::empty());
+                None
+            }
+        };
+        let genre = errors.check("genre", ValidatedBookGenre::new(value.genre));
+        let metadata = BookMetadata::new(value.metadata, &mut errors);
+
+        match (title, contributors, genre) {
+            (Some(title), Some(contributors), Some(genre)) if errors.is_empty() => Ok(Self {
+                title,
+                contributors,
+                genre,
+                metadata,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+pub struct BookPatch {
+    pub title: Option<ValidatedBookTitle>,
+    pub contributors: Option<Vec<NewContributor>>,
+    pub genre: Option<ValidatedBookGenre>,
+    pub metadata: BookMetadata,
+}
+
+impl TryFrom<BookPatchData> for BookPatch {
+    type Error = ValidationErrors;
+
+    fn try_from(value: BookPatchData) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let title = value
+            .title
+            .and_then(|title| errors.check("title", ValidatedBookTitle::new(title)));
+        let contributors = NewContributor::from_payload(
+            value.author_id,
+            value.author,
+            value.contributors,
+            &mut errors,
+        )
+        .flatten();
+        let genre = value
+            .genre
+            .and_then(|genre| errors.check("genre", ValidatedBookGenre::new(genre)));
+        let metadata = BookMetadata::new(value.metadata, &mut errors);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        if title.is_none() && contributors.is_none() && genre.is_none() && metadata.is_empty() {
+            return Err(ValidationErrors::single("body", Violation::empty()));
+        }
 
         Ok(Self {
             title,
-            author,
+            contributors,
             genre,
+            metadata,
         })
     }
 }
 
-pub struct ValidatedBookTitle(String);
+// Credited in list order, which is also the order books show them in
+pub struct NewContributor {
+    pub author: AuthorRef,
+    pub role: ValidatedContributorRole,
+}
 
-impl ValidatedBookTitle {
-    pub fn new(title: String) -> Result<Self, String> {
-        let is_empty_or_whitespace = title.trim().is_empty();
-        let size_too_big = title.chars().count() > 256;
+impl NewContributor {
+    // Single author books can skip the list and name the author at the top level instead.
+    // None when neither was given, Some(None) when what was given failed validation.
+    fn from_payload(
+        author_id: Option<String>,
+        author: Option<String>,
+        contributors: Option<Vec<ContributorData>>,
+        errors: &mut ValidationErrors,
+    ) -> Option<Option<Vec<Self>>> {
+        let author = AuthorRef::new(author_id, author, "", errors);
+
+        match (contributors, author) {
+            (None, author) => author.map(|author| {
+                author.map(|author| {
+                    vec![Self {
+                        author,
+                        role: ValidatedContributorRole::default(),
+                    }]
+                })
+            }),
+            (Some(_), Some(_)) => {
+                errors.add(
+                    "contributors",
+                    Violation::not_allowed("must not be combined with author or author_id"),
+                );
+                Some(None)
+            }
+            (Some(contributors), None) => Some(Self::list(contributors, errors)),
+        }
+    }
+
+    fn list(
+        contributors: Vec<ContributorData>,
+        errors: &mut ValidationErrors,
+    ) -> Option<Vec<Self>> {
+        if contributors.is_empty() || contributors.len() > MAX_CONTRIBUTORS {
+            errors.add(
+                "contributors",
+                Violation::out_of_range(1, MAX_CONTRIBUTORS as i64),
+            );
+            return None;
+        }
+
+        let error_count = errors.errors().len();
+        let mut valid = Vec::new();
+        for (i, contributor) in contributors.into_iter().enumerate() {
+            let prefix = format!("contributors[{}].", i);
+            let author =
+                match AuthorRef::new(contributor.author_id, contributor.author, &prefix, errors) {
+                    Some(author) => author,
+                    None => {
+                        errors.add(format!("{}author_id", prefix), Violation::empty());
+                        None
+                    }
+                };
+            let role = errors.check(
+                format!("{}role", prefix),
+                ValidatedContributorRole::new(contributor.role.unwrap_or("author".into())),
+            );
+
+            if let (Some(author), Some(role)) = (author, role) {
+                valid.push(Self { author, role });
+            }
+        }
+
+        (errors.errors().len() == error_count).then_some(valid)
+    }
+}
+
+// Books point at their authors by id. The name is only a fallback for clients that don't have
+// the id, and is ignored when both are given.
+pub enum AuthorRef {
+    Id(ValidatedId),
+    Name(ValidatedAuthorName),
+}
+
+impl AuthorRef {
+    // None when neither was given, Some(None) when the given one failed validation
+    fn new(
+        id: Option<String>,
+        name: Option<String>,
+        prefix: &str,
+        errors: &mut ValidationErrors,
+    ) -> Option<Option<Self>> {
+        match (id, name) {
+            (Some(id), _) => Some(
+                errors
+                    .check(format!("{}author_id", prefix), ValidatedId::new(id))
+                    .map(Self::Id),
+            ),
+            (None, Some(name)) => Some(
+                errors
+                    .check(format!("{}author", prefix), ValidatedAuthorName::new(name))
+                    .map(Self::Name),
+            ),
+            (None, None) => None,
+        }
+    }
+}
+
+pub struct ValidatedContributorRole(String);
 
-        if is_empty_or_whitespace || size_too_big {
-            Err(format!("'{}' is not a valid author title.", title))
+impl ValidatedContributorRole {
+    pub fn new(value: String) -> Result<Self, Violation> {
+        if CONTRIBUTOR_ROLES.contains(&value.as_str()) {
+            Ok(Self(value))
         } else {
-            Ok(Self(title))
+            Err(Violation::not_allowed(&format!(
+                "must be one of {}",
+                CONTRIBUTOR_ROLES.join(", ")
+            )))
         }
     }
 }
 
+impl Default for ValidatedContributorRole {
+    fn default() -> Self {
+        Self(String::from("author"))
+    }
+}
+
+impl AsRef<str> for ValidatedContributorRole {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct ValidatedBookTitle(String);
+
+impl ValidatedBookTitle {
+    pub fn new(title: String) -> Result<Self, Violation> {
+        required_text(&title, 256)?;
+        Ok(Self(title))
+    }
+}
+
 impl AsRef<str> for ValidatedBookTitle {
     fn as_ref(&self) -> &str {
         &self.0
@@ -46,27 +252,351 @@ impl AsRef<str> for ValidatedBookTitle {
 pub struct ValidatedBookGenre(String);
 
 impl ValidatedBookGenre {
-    fn new(genre: String) -> Result<Self, String> {
-        let is_empty_or_whitespace = genre.trim().is_empty();
-        let size_too_big = genre.chars().count() > 80;
+    fn new(genre: String) -> Result<Self, Violation> {
+        required_text(&genre, 80)?;
+        Ok(Self(genre))
+    }
+}
+
+impl AsRef<str> for ValidatedBookGenre {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct IsbnImport {
+    pub isbn: ValidatedIsbn13,
+    pub genre: Option<ValidatedBookGenre>,
+}
+
+impl TryFrom<IsbnImportData> for IsbnImport {
+    type Error = ValidationErrors;
+
+    fn try_from(value: IsbnImportData) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let isbn = errors.check("isbn", ValidatedIsbn13::new(value.isbn));
+        let genre = value
+            .genre
+            .and_then(|genre| errors.check("genre", ValidatedBookGenre::new(genre)));
+
+        match isbn {
+            Some(isbn) if errors.is_empty() => Ok(Self { isbn, genre }),
+            _ => Err(errors),
+        }
+    }
+}
+
+// Cataloging details, all optional. Fields left out are kept as they are on updates.
+#[derive(Default)]
+pub struct BookMetadata {
+    pub isbn: Option<ValidatedIsbn13>,
+    pub publisher: Option<ValidatedPublisher>,
+    pub publication_year: Option<ValidatedPublicationYear>,
+    pub language: Option<ValidatedLanguage>,
+    pub page_count: Option<ValidatedPageCount>,
+    pub edition: Option<ValidatedEdition>,
+    pub description: Option<ValidatedDescription>,
+}
+
+impl BookMetadata {
+    fn new(value: BookMetadataData, errors: &mut ValidationErrors) -> Self {
+        Self {
+            isbn: value
+                .isbn
+                .and_then(|isbn| errors.check("isbn", ValidatedIsbn13::new(isbn))),
+            publisher: value.publisher.and_then(|publisher| {
+                errors.check("publisher", ValidatedPublisher::new(publisher))
+            }),
+            publication_year: value.publication_year.and_then(|year| {
+                errors.check("publication_year", ValidatedPublicationYear::new(year))
+            }),
+            language: value
+                .language
+                .and_then(|language| errors.check("language", ValidatedLanguage::new(language))),
+            page_count: value.page_count.and_then(|page_count| {
+                errors.check("page_count", ValidatedPageCount::new(page_count))
+            }),
+            edition: value
+                .edition
+                .and_then(|edition| errors.check("edition", ValidatedEdition::new(edition))),
+            description: value.description.and_then(|description| {
+                errors.check("description", ValidatedDescription::new(description))
+            }),
+        }
+    }
 
-        if is_empty_or_whitespace || size_too_big {
-            Err(format!("'{}' is not a valid author genre.", genre))
+    pub fn is_empty(&self) -> bool {
+        self.isbn.is_none()
+            && self.publisher.is_none()
+            && self.publication_year.is_none()
+            && self.language.is_none()
+            && self.page_count.is_none()
+            && self.edition.is_none()
+            && self.description.is_none()
+    }
+}
+
+// Hyphens and spaces are only grouping, so they are dropped before checking
+fn isbn_characters(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn digit_values(value: &str) -> Vec<u32> {
+    value.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+fn isbn_10_check_digit(first_nine: &str) -> char {
+    let sum: u32 = digit_values(first_nine)
+        .iter()
+        .enumerate()
+        .map(|(i, digit)| digit * (10 - i as u32))
+        .sum();
+
+    match (11 - sum % 11) % 11 {
+        10 => 'X',
+        check => char::from_digit(check, 10).unwrap_or('0'),
+    }
+}
+
+fn isbn_13_check_digit(first_twelve: &str) -> char {
+    let sum: u32 = digit_values(first_twelve)
+        .iter()
+        .enumerate()
+        .map(|(i, digit)| if i % 2 == 0 { *digit } else { digit * 3 })
+        .sum();
+
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap_or('0')
+}
+
+pub struct ValidatedIsbn10(String);
+
+impl ValidatedIsbn10 {
+    pub fn new(value: String) -> Result<Self, Violation> {
+        let isbn = isbn_characters(&value);
+        // Checked as ASCII before slicing, so multibyte characters can't split a byte index
+        let is_isbn_10 = isbn.len() == 10
+            && isbn.is_ascii()
+            && isbn[..9].chars().all(|c| c.is_ascii_digit())
+            && isbn[9..].chars().all(|c| c.is_ascii_digit() || c == 'X');
+
+        if !is_isbn_10 {
+            Err(Violation::bad_format("must be a valid ISBN-10"))
+        } else if isbn[9..].starts_with(isbn_10_check_digit(&isbn[..9])) {
+            Ok(Self(isbn))
         } else {
-            Ok(Self(genre))
+            Err(Violation::bad_format("has an invalid check digit"))
         }
     }
 }
 
-impl AsRef<str> for ValidatedBookGenre {
+impl AsRef<str> for ValidatedIsbn10 {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Books are stored under their ISBN-13 so the same edition can't be cataloged twice
+pub struct ValidatedIsbn13(String);
+
+impl ValidatedIsbn13 {
+    // Takes either form, ISBN-10s are converted
+    pub fn new(value: String) -> Result<Self, Violation> {
+        let isbn = isbn_characters(&value);
+        if isbn.len() == 10 {
+            return ValidatedIsbn10::new(isbn).map(Self::from);
+        }
+
+        let is_isbn_13 = isbn.len() == 13
+            && isbn.chars().all(|c| c.is_ascii_digit())
+            && (isbn.starts_with("978") || isbn.starts_with("979"));
+
+        if !is_isbn_13 {
+            Err(Violation::bad_format("must be a valid ISBN-10 or ISBN-13"))
+        } else if isbn[12..].starts_with(isbn_13_check_digit(&isbn[..12])) {
+            Ok(Self(isbn))
+        } else {
+            Err(Violation::bad_format("has an invalid check digit"))
+        }
+    }
+
+    // Only the 978 range existed before ISBN-13, so 979 numbers have no ISBN-10
+    pub fn to_isbn_10(&self) -> Option<ValidatedIsbn10> {
+        if !self.0.starts_with("978") {
+            return None;
+        }
+
+        let first_nine = &self.0[3..12];
+        Some(ValidatedIsbn10(format!(
+            "{}{}",
+            first_nine,
+            isbn_10_check_digit(first_nine)
+        )))
+    }
+}
+
+impl From<ValidatedIsbn10> for ValidatedIsbn13 {
+    fn from(value: ValidatedIsbn10) -> Self {
+        let first_twelve = format!("978{}", &value.0[..9]);
+        let check = isbn_13_check_digit(&first_twelve);
+        Self(format!("{}{}", first_twelve, check))
+    }
+}
+
+impl AsRef<str> for ValidatedIsbn13 {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct ValidatedPublisher(String);
+
+impl ValidatedPublisher {
+    fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 256)?;
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for ValidatedPublisher {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct ValidatedPublicationYear(i32);
+
+impl ValidatedPublicationYear {
+    // Announced books can carry next year's date
+    fn new(value: i32) -> Result<Self, Violation> {
+        let latest = Utc::now().year() + 1;
+        if (1..=latest).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(Violation::out_of_range(1, latest as i64))
+        }
+    }
+}
+
+impl AsRef<i32> for ValidatedPublicationYear {
+    fn as_ref(&self) -> &i32 {
+        &self.0
+    }
+}
+
+// ISO 639-1 or 639-2/3 code, stored in lower case
+pub struct ValidatedLanguage(String);
+
+impl ValidatedLanguage {
+    fn new(value: String) -> Result<Self, Violation> {
+        let code = value.trim().to_ascii_lowercase();
+        if (2..=3).contains(&code.len()) && code.chars().all(|c| c.is_ascii_lowercase()) {
+            Ok(Self(code))
+        } else {
+            Err(Violation::bad_format("must be an ISO 639 language code"))
+        }
+    }
+}
+
+impl AsRef<str> for ValidatedLanguage {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct ValidatedPageCount(i32);
+
+impl ValidatedPageCount {
+    fn new(value: i32) -> Result<Self, Violation> {
+        if (1..=MAX_PAGE_COUNT).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(Violation::out_of_range(1, MAX_PAGE_COUNT as i64))
+        }
+    }
+}
+
+impl AsRef<i32> for ValidatedPageCount {
+    fn as_ref(&self) -> &i32 {
+        &self.0
+    }
+}
+
+pub struct ValidatedEdition(String);
+
+impl ValidatedEdition {
+    fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 80)?;
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for ValidatedEdition {
     fn as_ref(&self) -> &str {
         &self.0
     }
 }
 
+pub struct ValidatedDescription(String);
+
+impl ValidatedDescription {
+    fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 10_000)?;
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for ValidatedDescription {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct BookListQuery {
+    pub limit: ValidatedLimit,
+    pub sort: ValidatedSort,
+    pub cursor: Option<Cursor>,
+    pub genre: Option<String>,
+    pub author_id: Option<ValidatedId>,
+}
+
+impl TryFrom<BookListParams> for BookListQuery {
+    type Error = ValidationErrors;
+
+    fn try_from(value: BookListParams) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let limit = errors.check("limit", ValidatedLimit::new(value.limit));
+        let sort = errors.check("sort", ValidatedSort::new(value.sort, &BOOK_SORTS));
+        let cursor = match (&sort, value.cursor) {
+            (Some(sort), Some(cursor)) => {
+                errors.check("cursor", Cursor::decode(&cursor, sort.as_ref()))
+            }
+            _ => None,
+        };
+        let author_id = value
+            .author_id
+            .and_then(|author_id| errors.check("author_id", ValidatedId::new(author_id)));
+
+        match (limit, sort) {
+            (Some(limit), Some(sort)) if errors.is_empty() => Ok(Self {
+                limit,
+                sort,
+                cursor,
+                genre: value.genre,
+                author_id,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::validations::Rule;
 
     #[test]
     fn valid_title() {
@@ -132,8 +662,11 @@ mod tests {
     fn new_book_success() {
         let data = NewBookData {
             title: String::from("Pride and Prejudice"),
-            author: String::from("Jane Austen"),
+            author: Some(String::from("Jane Austen")),
+            author_id: None,
+            contributors: None,
             genre: String::from("British"),
+            metadata: Default::default(),
         };
         assert!(NewBook::try_from(data).is_ok());
     }
@@ -142,9 +675,345 @@ mod tests {
     fn new_book_failure() {
         let data = NewBookData {
             title: String::from("Pride \\(and) Prejudice"),
-            author: String::from(""),
+            author: Some(String::from("")),
+            author_id: None,
+            contributors: None,
             genre: String::from("Britisn"),
+            metadata: Default::default(),
         };
         assert!(NewBook::try_from(data).is_err());
     }
+
+    #[test]
+    fn new_book_reports_every_failing_field() {
+        let data = NewBookData {
+            title: String::from(""),
+            author: Some(String::from("Jane Austen")),
+            author_id: None,
+            contributors: None,
+            genre: "a".repeat(81),
+            metadata: Default::default(),
+        };
+        let errors = NewBook::try_from(data).err().unwrap();
+        let errors = errors.errors();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field, "title");
+        assert_eq!(errors[0].violation.rule, Rule::Empty);
+        assert_eq!(errors[1].field, "genre");
+        assert_eq!(errors[1].violation.rule, Rule::TooLong);
+        assert_eq!(errors[1].violation.max, Some(80));
+    }
+
+    #[test]
+    fn new_book_prefers_author_id() {
+        let data = NewBookData {
+            title: String::from("Emma"),
+            author: Some(String::from("Jane Austen")),
+            author_id: Some(String::from("a56de2a8-61d3-43f4-b66b-b454c2b54589")),
+            contributors: None,
+            genre: String::from("Romance"),
+            metadata: Default::default(),
+        };
+        let book = NewBook::try_from(data).unwrap();
+        assert!(matches!(book.contributors[0].author, AuthorRef::Id(_)));
+        assert_eq!(book.contributors[0].role.as_ref(), "author");
+    }
+
+    #[test]
+    fn new_book_without_author() {
+        let data = NewBookData {
+            title: String::from("Emma"),
+            author: None,
+            author_id: None,
+            contributors: None,
+            genre: String::from("Romance"),
+            metadata: Default::default(),
+        };
+        let errors = NewBook::try_from(data).err().unwrap();
+        assert_eq!(errors.errors()[0].field, "author_id");
+        assert_eq!(errors.errors()[0].violation.rule, Rule::Empty);
+    }
+
+    #[test]
+    fn new_book_with_invalid_author_id() {
+        let data = NewBookData {
+            title: String::from("Emma"),
+            author: None,
+            author_id: Some(String::from("Jane Austen")),
+            contributors: None,
+            genre: String::from("Romance"),
+            metadata: Default::default(),
+        };
+        let errors = NewBook::try_from(data).err().unwrap();
+        assert_eq!(errors.errors()[0].violation.rule, Rule::BadFormat);
+    }
+
+    fn contributor(author: &str, role: Option<&str>) -> ContributorData {
+        ContributorData {
+            author_id: None,
+            author: Some(String::from(author)),
+            role: role.map(String::from),
+        }
+    }
+
+    #[test]
+    fn new_book_with_contributors() {
+        let data = NewBookData {
+            title: String::from("The Odyssey"),
+            author: None,
+            author_id: None,
+            contributors: Some(vec![
+                contributor("Homer", None),
+                contributor("Emily Wilson", Some("translator")),
+            ]),
+            genre: String::from("Epic"),
+            metadata: Default::default(),
+        };
+        let book = NewBook::try_from(data).unwrap();
+        assert_eq!(book.contributors.len(), 2);
+        assert_eq!(book.contributors[0].role.as_ref(), "author");
+        assert_eq!(book.contributors[1].role.as_ref(), "translator");
+    }
+
+    #[test]
+    fn new_book_with_invalid_contributors() {
+        let data = NewBookData {
+            title: String::from("The Odyssey"),
+            author: None,
+            author_id: None,
+            contributors: Some(vec![
+                contributor("Homer", Some("narrator")),
+                ContributorData {
+                    author_id: None,
+                    author: None,
+                    role: None,
+                },
+            ]),
+            genre: String::from("Epic"),
+            metadata: Default::default(),
+        };
+        let errors = NewBook::try_from(data).err().unwrap();
+        let errors = errors.errors();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field, "contributors[0].role");
+        assert_eq!(errors[0].violation.rule, Rule::NotAllowed);
+        assert_eq!(errors[1].field, "contributors[1].author_id");
+        assert_eq!(errors[1].violation.rule, Rule::Empty);
+    }
+
+    #[test]
+    fn new_book_with_empty_contributors() {
+        let data = NewBookData {
+            title: String::from("The Odyssey"),
+            author: None,
+            author_id: None,
+            contributors: Some(Vec::new()),
+            genre: String::from("Epic"),
+            metadata: Default::default(),
+        };
+        let errors = NewBook::try_from(data).err().unwrap();
+        assert_eq!(errors.errors()[0].field, "contributors");
+        assert_eq!(errors.errors()[0].violation.rule, Rule::OutOfRange);
+    }
+
+    #[test]
+    fn new_book_with_author_and_contributors() {
+        let data = NewBookData {
+            title: String::from("The Odyssey"),
+            author: Some(String::from("Homer")),
+            author_id: None,
+            contributors: Some(vec![contributor("Homer", None)]),
+            genre: String::from("Epic"),
+            metadata: Default::default(),
+        };
+        let errors = NewBook::try_from(data).err().unwrap();
+        assert_eq!(errors.errors()[0].violation.rule, Rule::NotAllowed);
+    }
+
+    #[test]
+    fn valid_isbn_13() {
+        let isbn = ValidatedIsbn13::new(String::from("978-0-306-40615-7")).unwrap();
+        assert_eq!(isbn.as_ref(), "9780306406157");
+    }
+
+    #[test]
+    fn isbn_10_becomes_isbn_13() {
+        let isbn = ValidatedIsbn13::new(String::from("0-306-40615-2")).unwrap();
+        assert_eq!(isbn.as_ref(), "9780306406157");
+        assert_eq!(isbn.to_isbn_10().unwrap().as_ref(), "0306406152");
+    }
+
+    #[test]
+    fn isbn_10_with_x_check_digit() {
+        let isbn = ValidatedIsbn10::new(String::from("0-8044-2957-x")).unwrap();
+        assert_eq!(isbn.as_ref(), "080442957X");
+        assert_eq!(ValidatedIsbn13::from(isbn).as_ref(), "9780804429573");
+    }
+
+    #[test]
+    fn isbn_979_has_no_isbn_10() {
+        let isbn = ValidatedIsbn13::new(String::from("979-10-90636-07-1")).unwrap();
+        assert!(isbn.to_isbn_10().is_none());
+    }
+
+    #[test]
+    fn isbn_with_bad_check_digit() {
+        let violation = ValidatedIsbn13::new(String::from("9780306406158"))
+            .err()
+            .unwrap();
+        assert_eq!(violation.message, "has an invalid check digit");
+        assert!(ValidatedIsbn10::new(String::from("0306406153")).is_err());
+    }
+
+    #[test]
+    fn malformed_isbn() {
+        for isbn in ["12345", "978030640615X", "1234567890123", "03064O6152"] {
+            assert_eq!(
+                ValidatedIsbn13::new(String::from(isbn)).err().unwrap().rule,
+                Rule::BadFormat
+            );
+        }
+    }
+
+    #[test]
+    fn multibyte_isbn_is_malformed() {
+        // 10 bytes once cleaned, with the é straddling the check digit
+        for isbn in ["12345678é", "1234567é9", "€1234567"] {
+            assert_eq!(
+                ValidatedIsbn10::new(String::from(isbn)).err().unwrap().rule,
+                Rule::BadFormat
+            );
+            assert_eq!(
+                ValidatedIsbn13::new(String::from(isbn)).err().unwrap().rule,
+                Rule::BadFormat
+            );
+        }
+    }
+
+    #[test]
+    fn language_codes() {
+        assert_eq!(
+            ValidatedLanguage::new(String::from("EN")).unwrap().as_ref(),
+            "en"
+        );
+        assert!(ValidatedLanguage::new(String::from("grc")).is_ok());
+        assert!(ValidatedLanguage::new(String::from("english")).is_err());
+        assert!(ValidatedLanguage::new(String::from("e1")).is_err());
+    }
+
+    #[test]
+    fn publication_year_range() {
+        assert!(ValidatedPublicationYear::new(1851).is_ok());
+        assert!(ValidatedPublicationYear::new(0).is_err());
+        assert!(ValidatedPublicationYear::new(Utc::now().year() + 2).is_err());
+    }
+
+    #[test]
+    fn page_count_range() {
+        assert!(ValidatedPageCount::new(635).is_ok());
+        assert!(ValidatedPageCount::new(0).is_err());
+        assert!(ValidatedPageCount::new(MAX_PAGE_COUNT + 1).is_err());
+    }
+
+    #[test]
+    fn new_book_reports_invalid_metadata() {
+        let data = NewBookData {
+            title: String::from("Moby Dick"),
+            author: Some(String::from("Herman Melville")),
+            author_id: None,
+            contributors: None,
+            genre: String::from("Adventure"),
+            metadata: BookMetadataData {
+                isbn: Some(String::from("9780142437248")),
+                page_count: Some(-1),
+                ..Default::default()
+            },
+        };
+        let errors = NewBook::try_from(data).err().unwrap();
+        let fields: Vec<&str> = errors.errors().iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["isbn", "page_count"]);
+    }
+
+    #[test]
+    fn metadata_only_patch() {
+        let data = BookPatchData {
+            title: None,
+            author: None,
+            author_id: None,
+            contributors: None,
+            genre: None,
+            metadata: BookMetadataData {
+                publisher: Some(String::from("Penguin Classics")),
+                ..Default::default()
+            },
+        };
+        let patch = BookPatch::try_from(data).unwrap();
+        assert_eq!(
+            patch.metadata.publisher.unwrap().as_ref(),
+            "Penguin Classics"
+        );
+    }
+
+    #[test]
+    fn book_list_defaults() {
+        let query = BookListQuery::try_from(BookListParams::default()).unwrap();
+        assert_eq!(query.sort.as_ref(), "created_at");
+        assert!(query.cursor.is_none());
+    }
+
+    #[test]
+    fn book_list_failure() {
+        let data = BookListParams {
+            limit: Some(0),
+            sort: Some(String::from("genre")),
+            author_id: Some(String::from("Tolkien")),
+            ..Default::default()
+        };
+        let errors = BookListQuery::try_from(data).err().unwrap();
+        assert_eq!(errors.errors().len(), 3);
+    }
+
+    #[test]
+    fn book_patch_success() {
+        let data = BookPatchData {
+            title: Some(String::from("Persuasion")),
+            author: None,
+            author_id: None,
+            contributors: None,
+            genre: None,
+            metadata: Default::default(),
+        };
+        let patch = BookPatch::try_from(data).unwrap();
+        assert_eq!(patch.title.unwrap().as_ref(), "Persuasion");
+        assert!(patch.contributors.is_none());
+        assert!(patch.genre.is_none());
+    }
+
+    #[test]
+    fn book_patch_invalid_field() {
+        let data = BookPatchData {
+            title: None,
+            author: None,
+            author_id: None,
+            contributors: None,
+            genre: Some(String::from(" ")),
+            metadata: Default::default(),
+        };
+        assert!(BookPatch::try_from(data).is_err());
+    }
+
+    #[test]
+    fn empty_book_patch() {
+        let data = BookPatchData {
+            title: None,
+            author: None,
+            author_id: None,
+            contributors: None,
+            genre: None,
+            metadata: Default::default(),
+        };
+        assert!(BookPatch::try_from(data).is_err());
+    }
 }
@@ -0,0 +1,55 @@
+use crate::{
+    routes::NewHoldData,
+    validations::{loan::ValidatedId, ValidationErrors},
+};
+use chrono::{DateTime, Duration, Utc};
+
+pub const HOLD_PICKUP_DAYS: i64 = 7;
+
+pub struct NewHold {
+    pub user_id: ValidatedId,
+}
+
+impl TryFrom<NewHoldData> for NewHold {
+    type Error = ValidationErrors;
+
+    fn try_from(value: NewHoldData) -> Result<Self, Self::Error> {
+        let user_id = ValidatedId::new(value.user_id)
+            .map_err(|violation| ValidationErrors::single("user_id", violation))?;
+        Ok(Self { user_id })
+    }
+}
+
+pub fn pickup_expiry(ready_at: DateTime<Utc>) -> DateTime<Utc> {
+    ready_at + Duration::days(HOLD_PICKUP_DAYS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    #[test]
+    fn pickup_expiry_is_one_pickup_period_later() {
+        let ready_at = Utc.with_ymd_and_hms(2024, 4, 20, 9, 30, 0).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 4, 27, 9, 30, 0).unwrap();
+        assert_eq!(pickup_expiry(ready_at), expected);
+    }
+
+    #[test]
+    fn new_hold_success() {
+        let data = NewHoldData {
+            user_id: Uuid::new_v4().to_string(),
+        };
+        assert!(NewHold::try_from(data).is_ok());
+    }
+
+    #[test]
+    fn new_hold_failure() {
+        let data = NewHoldData {
+            user_id: String::from("Richard"),
+        };
+        assert!(NewHold::try_from(data).is_err());
+    }
+}
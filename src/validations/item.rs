@@ -0,0 +1,229 @@
+use crate::{
+    routes::NewItemData,
+    validations::{required_text, ValidationErrors, Violation},
+};
+
+pub const ITEM_STATUSES: [&str; 5] = ["available", "on_loan", "on_hold", "lost", "in_repair"];
+
+pub struct NewItem {
+    pub barcode: ValidatedItemBarcode,
+    pub condition: ValidatedItemCondition,
+    pub shelf_location: ValidatedItemShelfLocation,
+    pub status: ValidatedItemStatus,
+}
+
+impl TryFrom<NewItemData> for NewItem {
+    type Error = ValidationErrors;
+
+    fn try_from(value: NewItemData) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let barcode = errors.check("barcode", ValidatedItemBarcode::new(value.barcode));
+        let condition = errors.check("condition", ValidatedItemCondition::new(value.condition));
+        let shelf_location = errors.check(
+            "shelf_location",
+            ValidatedItemShelfLocation::new(value.shelf_location),
+        );
+        let status = errors.check(
+            "status",
+            ValidatedItemStatus::new(value.status.unwrap_or("available".into())),
+        );
+
+        match (barcode, condition, shelf_location, status) {
+            (Some(barcode), Some(condition), Some(shelf_location), Some(status)) => Ok(Self {
+                barcode,
+                condition,
+                shelf_location,
+                status,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+pub struct ValidatedItemBarcode(String);
+
+impl ValidatedItemBarcode {
+    pub fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 64)?;
+
+        if !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            Err(Violation::bad_format(
+                "must only contain letters, digits and dashes",
+            ))
+        } else {
+            Ok(Self(value))
+        }
+    }
+}
+
+impl AsRef<str> for ValidatedItemBarcode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct ValidatedItemCondition(String);
+
+impl ValidatedItemCondition {
+    fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 80)?;
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for ValidatedItemCondition {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct ValidatedItemShelfLocation(String);
+
+impl ValidatedItemShelfLocation {
+    fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 80)?;
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for ValidatedItemShelfLocation {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Items are only put "on_loan" or "on_hold" by circulation, never through this input
+pub struct ValidatedItemStatus(String);
+
+impl ValidatedItemStatus {
+    fn new(value: String) -> Result<Self, Violation> {
+        let is_circulation_status = value == "on_loan" || value == "on_hold";
+
+        if is_circulation_status || !ITEM_STATUSES.contains(&value.as_str()) {
+            Err(Violation::not_allowed(
+                "must be one of available, lost or in_repair",
+            ))
+        } else {
+            Ok(Self(value))
+        }
+    }
+}
+
+impl AsRef<str> for ValidatedItemStatus {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_barcode() {
+        let barcode = String::from("ML-000123");
+        assert!(ValidatedItemBarcode::new(barcode).is_ok());
+    }
+
+    #[test]
+    fn empty_barcode() {
+        let barcode = String::from("");
+        assert!(ValidatedItemBarcode::new(barcode).is_err());
+    }
+
+    #[test]
+    fn barcode_with_spaces() {
+        let barcode = String::from("ML 000123");
+        assert!(ValidatedItemBarcode::new(barcode).is_err());
+    }
+
+    #[test]
+    fn max_length_barcode() {
+        let barcode = "1".repeat(64);
+        assert!(ValidatedItemBarcode::new(barcode).is_ok());
+    }
+
+    #[test]
+    fn too_long_barcode() {
+        let barcode = "1".repeat(65);
+        assert!(ValidatedItemBarcode::new(barcode).is_err());
+    }
+
+    #[test]
+    fn valid_condition() {
+        let condition = String::from("Worn cover");
+        assert!(ValidatedItemCondition::new(condition).is_ok());
+    }
+
+    #[test]
+    fn whitespace_only_condition() {
+        let condition = String::from(" ");
+        assert!(ValidatedItemCondition::new(condition).is_err());
+    }
+
+    #[test]
+    fn too_long_condition() {
+        let condition = "a".repeat(81);
+        assert!(ValidatedItemCondition::new(condition).is_err());
+    }
+
+    #[test]
+    fn valid_shelf_location() {
+        let shelf_location = String::from("Fiction A-3");
+        assert!(ValidatedItemShelfLocation::new(shelf_location).is_ok());
+    }
+
+    #[test]
+    fn empty_shelf_location() {
+        let shelf_location = String::from("");
+        assert!(ValidatedItemShelfLocation::new(shelf_location).is_err());
+    }
+
+    #[test]
+    fn valid_statuses() {
+        for status in ["available", "lost", "in_repair"] {
+            assert!(ValidatedItemStatus::new(status.into()).is_ok());
+        }
+    }
+
+    #[test]
+    fn on_loan_status() {
+        let status = String::from("on_loan");
+        assert!(ValidatedItemStatus::new(status).is_err());
+    }
+
+    #[test]
+    fn on_hold_status() {
+        let status = String::from("on_hold");
+        assert!(ValidatedItemStatus::new(status).is_err());
+    }
+
+    #[test]
+    fn unknown_status() {
+        let status = String::from("borrowed");
+        assert!(ValidatedItemStatus::new(status).is_err());
+    }
+
+    #[test]
+    fn new_item_defaults_to_available() {
+        let data = NewItemData {
+            barcode: String::from("ML-000123"),
+            condition: String::from("New"),
+            shelf_location: String::from("Fiction A-3"),
+            status: None,
+        };
+        let new_item = NewItem::try_from(data).unwrap();
+        assert_eq!(new_item.status.as_ref(), "available");
+    }
+
+    #[test]
+    fn new_item_failure() {
+        let data = NewItemData {
+            barcode: String::from("ML-000123"),
+            condition: String::from(""),
+            shelf_location: String::from("Fiction A-3"),
+            status: Some(String::from("lost")),
+        };
+        assert!(NewItem::try_from(data).is_err());
+    }
+}
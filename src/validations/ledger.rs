@@ -0,0 +1,152 @@
+use crate::{
+    routes::NewLedgerEntryData,
+    validations::{ValidationErrors, Violation},
+};
+use chrono::{DateTime, Utc};
+
+pub struct NewLedgerEntry {
+    pub amount_cents: ValidatedAmount,
+    pub note: ValidatedLedgerNote,
+}
+
+impl TryFrom<NewLedgerEntryData> for NewLedgerEntry {
+    type Error = ValidationErrors;
+
+    fn try_from(value: NewLedgerEntryData) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let amount_cents = errors.check("amount_cents", ValidatedAmount::new(value.amount_cents));
+        let note = errors.check(
+            "note",
+            ValidatedLedgerNote::new(value.note.unwrap_or_default()),
+        );
+
+        match (amount_cents, note) {
+            (Some(amount_cents), Some(note)) => Ok(Self { amount_cents, note }),
+            _ => Err(errors),
+        }
+    }
+}
+
+pub struct ValidatedAmount(i64);
+
+impl ValidatedAmount {
+    pub fn new(value: i64) -> Result<Self, Violation> {
+        if value <= 0 || value > 1_000_000 {
+            Err(Violation::out_of_range(1, 1_000_000))
+        } else {
+            Ok(Self(value))
+        }
+    }
+}
+
+impl AsRef<i64> for ValidatedAmount {
+    fn as_ref(&self) -> &i64 {
+        &self.0
+    }
+}
+
+pub struct ValidatedLedgerNote(String);
+
+impl ValidatedLedgerNote {
+    fn new(value: String) -> Result<Self, Violation> {
+        if value.chars().count() > 256 {
+            Err(Violation::too_long(256))
+        } else {
+            Ok(Self(value))
+        }
+    }
+}
+
+impl AsRef<str> for ValidatedLedgerNote {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Only whole days past the due date are charged
+pub fn overdue_fine(due_at: DateTime<Utc>, until: DateTime<Utc>, daily_fine_cents: i64) -> i64 {
+    let days_overdue = (until - due_at).num_days();
+
+    if days_overdue > 0 {
+        days_overdue * daily_fine_cents
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn valid_amount() {
+        assert!(ValidatedAmount::new(250).is_ok());
+    }
+
+    #[test]
+    fn zero_amount() {
+        assert!(ValidatedAmount::new(0).is_err());
+    }
+
+    #[test]
+    fn negative_amount() {
+        assert!(ValidatedAmount::new(-250).is_err());
+    }
+
+    #[test]
+    fn too_big_amount() {
+        assert!(ValidatedAmount::new(1_000_001).is_err());
+    }
+
+    #[test]
+    fn empty_note() {
+        let note = String::from("");
+        assert!(ValidatedLedgerNote::new(note).is_ok());
+    }
+
+    #[test]
+    fn too_long_note() {
+        let note = "a".repeat(257);
+        assert!(ValidatedLedgerNote::new(note).is_err());
+    }
+
+    #[test]
+    fn no_fine_before_due_date() {
+        let due_at = Utc.with_ymd_and_hms(2024, 4, 15, 10, 0, 0).unwrap();
+        let until = Utc.with_ymd_and_hms(2024, 4, 14, 10, 0, 0).unwrap();
+        assert_eq!(overdue_fine(due_at, until, 25), 0);
+    }
+
+    #[test]
+    fn no_fine_for_partial_day() {
+        let due_at = Utc.with_ymd_and_hms(2024, 4, 15, 10, 0, 0).unwrap();
+        let until = Utc.with_ymd_and_hms(2024, 4, 16, 9, 59, 0).unwrap();
+        assert_eq!(overdue_fine(due_at, until, 25), 0);
+    }
+
+    #[test]
+    fn fine_per_full_day_overdue() {
+        let due_at = Utc.with_ymd_and_hms(2024, 4, 15, 10, 0, 0).unwrap();
+        let until = Utc.with_ymd_and_hms(2024, 4, 18, 12, 0, 0).unwrap();
+        assert_eq!(overdue_fine(due_at, until, 25), 75);
+    }
+
+    #[test]
+    fn new_ledger_entry_success() {
+        let data = NewLedgerEntryData {
+            amount_cents: 500,
+            note: Some(String::from("Paid at the front desk")),
+        };
+        assert!(NewLedgerEntry::try_from(data).is_ok());
+    }
+
+    #[test]
+    fn new_ledger_entry_failure() {
+        let data = NewLedgerEntryData {
+            amount_cents: 0,
+            note: None,
+        };
+        assert!(NewLedgerEntry::try_from(data).is_err());
+    }
+}
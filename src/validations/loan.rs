@@ -0,0 +1,146 @@
+use crate::{
+    routes::NewLoanData,
+    validations::{ValidationErrors, Violation},
+};
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+pub const LOAN_PERIOD_DAYS: i64 = 14;
+pub const MAX_ACTIVE_LOANS: i64 = 5;
+pub const MAX_RENEWALS: i32 = 2;
+
+pub struct NewLoan {
+    pub user_id: ValidatedId,
+    pub book_id: ValidatedId,
+}
+
+impl TryFrom<NewLoanData> for NewLoan {
+    type Error = ValidationErrors;
+
+    fn try_from(value: NewLoanData) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let user_id = errors.check("user_id", ValidatedId::new(value.user_id));
+        let book_id = errors.check("book_id", ValidatedId::new(value.book_id));
+
+        match (user_id, book_id) {
+            (Some(user_id), Some(book_id)) => Ok(Self { user_id, book_id }),
+            _ => Err(errors),
+        }
+    }
+}
+
+pub struct ValidatedId(Uuid);
+
+impl ValidatedId {
+    pub fn new(value: String) -> Result<Self, Violation> {
+        if value.trim().is_empty() {
+            return Err(Violation::empty());
+        }
+
+        match Uuid::parse_str(value.trim()) {
+            Ok(id) => Ok(Self(id)),
+            Err(_) => Err(Violation::bad_format("must be a valid UUID")),
+        }
+    }
+}
+
+impl AsRef<Uuid> for ValidatedId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+pub fn due_date(from: DateTime<Utc>) -> DateTime<Utc> {
+    from + Duration::days(LOAN_PERIOD_DAYS)
+}
+
+pub fn renewed_due_date(
+    current_due_at: DateTime<Utc>,
+    renewals: i32,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>, String> {
+    // Fines run from the due date, so moving it on an overdue loan would write them off
+    if current_due_at <= now {
+        Err("Overdue loans can't be renewed, return the book instead.".into())
+    } else if renewals >= MAX_RENEWALS {
+        Err(format!(
+            "Loan has already been renewed the maximum of {} times.",
+            MAX_RENEWALS
+        ))
+    } else {
+        Ok(due_date(current_due_at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validations::Rule;
+    use chrono::TimeZone;
+
+    #[test]
+    fn valid_id() {
+        let id = Uuid::new_v4();
+        let validated = ValidatedId::new(id.to_string()).unwrap();
+        assert_eq!(validated.as_ref(), &id);
+    }
+
+    #[test]
+    fn empty_id() {
+        let id = String::from("");
+        assert!(ValidatedId::new(id).is_err());
+    }
+
+    #[test]
+    fn malformed_id() {
+        let id = String::from("not-a-uuid");
+        assert_eq!(ValidatedId::new(id).err().unwrap().rule, Rule::BadFormat);
+    }
+
+    #[test]
+    fn due_date_is_one_loan_period_later() {
+        let checked_out_at = Utc.with_ymd_and_hms(2024, 4, 1, 10, 0, 0).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 4, 15, 10, 0, 0).unwrap();
+        assert_eq!(due_date(checked_out_at), expected);
+    }
+
+    #[test]
+    fn renewal_extends_from_current_due_date() {
+        let due_at = Utc.with_ymd_and_hms(2024, 4, 15, 10, 0, 0).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 4, 29, 10, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 4, 10, 10, 0, 0).unwrap();
+        assert_eq!(renewed_due_date(due_at, 0, now), Ok(expected));
+    }
+
+    #[test]
+    fn renewal_over_limit() {
+        let due_at = Utc.with_ymd_and_hms(2024, 4, 15, 10, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 4, 10, 10, 0, 0).unwrap();
+        assert!(renewed_due_date(due_at, MAX_RENEWALS, now).is_err());
+    }
+
+    #[test]
+    fn overdue_loan_is_not_renewed() {
+        let due_at = Utc.with_ymd_and_hms(2024, 4, 15, 10, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 4, 16, 10, 0, 0).unwrap();
+        assert!(renewed_due_date(due_at, 0, now).is_err());
+    }
+
+    #[test]
+    fn new_loan_success() {
+        let data = NewLoanData {
+            user_id: Uuid::new_v4().to_string(),
+            book_id: Uuid::new_v4().to_string(),
+        };
+        assert!(NewLoan::try_from(data).is_ok());
+    }
+
+    #[test]
+    fn new_loan_failure() {
+        let data = NewLoanData {
+            user_id: Uuid::new_v4().to_string(),
+            book_id: String::from("Moby Dick"),
+        };
+        assert!(NewLoan::try_from(data).is_err());
+    }
+}
@@ -1,3 +1,223 @@
+pub mod api_key;
 pub mod author;
 pub mod book;
+pub mod hold;
+pub mod item;
+pub mod ledger;
+pub mod loan;
+pub mod page;
+pub mod search;
 pub mod user;
+
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    Empty,
+    TooShort,
+    TooLong,
+    BadFormat,
+    OutOfRange,
+    NotAllowed,
+}
+
+// What was wrong with a single value, without knowing which field it came from
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub rule: Rule,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<i64>,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn empty() -> Self {
+        Self {
+            rule: Rule::Empty,
+            min: None,
+            max: None,
+            message: String::from("must not be empty"),
+        }
+    }
+
+    pub fn too_short(min: usize) -> Self {
+        Self {
+            rule: Rule::TooShort,
+            min: Some(min as i64),
+            max: None,
+            message: format!("must be at least {} characters long", min),
+        }
+    }
+
+    pub fn too_long(max: usize) -> Self {
+        Self {
+            rule: Rule::TooLong,
+            min: None,
+            max: Some(max as i64),
+            message: format!("must be at most {} characters long", max),
+        }
+    }
+
+    pub fn bad_format(message: &str) -> Self {
+        Self {
+            rule: Rule::BadFormat,
+            min: None,
+            max: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn out_of_range(min: i64, max: i64) -> Self {
+        Self {
+            rule: Rule::OutOfRange,
+            min: Some(min),
+            max: Some(max),
+            message: format!("must be between {} and {}", min, max),
+        }
+    }
+
+    pub fn not_allowed(message: &str) -> Self {
+        Self {
+            rule: Rule::NotAllowed,
+            min: None,
+            max: None,
+            message: message.into(),
+        }
+    }
+}
+
+// Shared check for free text fields that are required and capped in length
+pub fn required_text(value: &str, max: usize) -> Result<(), Violation> {
+    if value.trim().is_empty() {
+        Err(Violation::empty())
+    } else if value.chars().count() > max {
+        Err(Violation::too_long(max))
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    #[serde(flatten)]
+    pub violation: Violation,
+}
+
+// Every failing field of a payload, so clients can show them all at once
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct ValidationErrors(Vec<FieldError>);
+
+impl ValidationErrors {
+    pub fn add(&mut self, field: impl Into<String>, violation: Violation) {
+        self.0.push(FieldError {
+            field: field.into(),
+            violation,
+        });
+    }
+
+    // Keeps the valid value, or records why the field failed
+    pub fn check<T>(
+        &mut self,
+        field: impl Into<String>,
+        result: Result<T, Violation>,
+    ) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(violation) => {
+                self.add(field, violation);
+                None
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: ValidationErrors) {
+        self.0.extend(other.0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn errors(&self) -> &[FieldError] {
+        &self.0
+    }
+
+    pub fn single(field: impl Into<String>, violation: Violation) -> Self {
+        let mut errors = Self::default();
+        errors.add(field, violation);
+        errors
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self
+            .0
+            .iter()
+            .map(|error| format!("{} {}", error.field, error.violation.message))
+            .collect();
+
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_text_rules() {
+        assert_eq!(required_text(" ", 10).unwrap_err().rule, Rule::Empty);
+        assert_eq!(
+            required_text("abcdefghijk", 10).unwrap_err().rule,
+            Rule::TooLong
+        );
+        assert!(required_text("abcdefghij", 10).is_ok());
+    }
+
+    #[test]
+    fn check_keeps_valid_values() {
+        let mut errors = ValidationErrors::default();
+        assert_eq!(errors.check("title", Ok::<_, Violation>(1)), Some(1));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_both_sides() {
+        let mut errors = ValidationErrors::single("page_count", Violation::bad_format("nope"));
+        errors.merge(ValidationErrors::single("title", Violation::empty()));
+
+        assert_eq!(errors.errors()[0].field, "page_count");
+        assert_eq!(errors.errors()[1].field, "title");
+    }
+
+    #[test]
+    fn check_records_failing_fields() {
+        let mut errors = ValidationErrors::default();
+        errors.check::<()>("title", Err(Violation::empty()));
+        errors.check::<()>("genre", Err(Violation::too_long(80)));
+
+        assert_eq!(errors.errors().len(), 2);
+        assert_eq!(
+            errors.to_string(),
+            "title must not be empty; genre must be at most 80 characters long"
+        );
+    }
+
+    #[test]
+    fn serialized_report() {
+        let errors = ValidationErrors::single("genre", Violation::too_long(80));
+        let json = serde_json::to_value(&errors).unwrap();
+
+        assert_eq!(json[0]["field"], "genre");
+        assert_eq!(json[0]["rule"], "too_long");
+        assert_eq!(json[0]["max"], 80);
+        assert!(json[0].get("min").is_none());
+    }
+}
@@ -0,0 +1,179 @@
+use crate::validations::Violation;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+pub struct ValidatedLimit(i64);
+
+impl ValidatedLimit {
+    pub fn new(value: Option<i64>) -> Result<Self, Violation> {
+        match value.unwrap_or(DEFAULT_PAGE_SIZE) {
+            limit if (1..=MAX_PAGE_SIZE).contains(&limit) => Ok(Self(limit)),
+            _ => Err(Violation::out_of_range(1, MAX_PAGE_SIZE)),
+        }
+    }
+}
+
+impl AsRef<i64> for ValidatedLimit {
+    fn as_ref(&self) -> &i64 {
+        &self.0
+    }
+}
+
+pub struct ValidatedSort(&'static str);
+
+impl ValidatedSort {
+    // The first allowed column is the default
+    pub fn new(value: Option<String>, allowed: &[&'static str]) -> Result<Self, Violation> {
+        match value {
+            None => Ok(Self(allowed[0])),
+            Some(value) => allowed
+                .iter()
+                .find(|column| **column == value)
+                .map(|column| Self(column))
+                .ok_or_else(|| {
+                    Violation::not_allowed(&format!("must be one of {}", allowed.join(", ")))
+                }),
+        }
+    }
+}
+
+impl AsRef<str> for ValidatedSort {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+// Sort key and id of the last record on a page. Clients get it hex encoded and pass it
+// back untouched to fetch the following page.
+pub struct Cursor {
+    pub sort: String,
+    pub key: String,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn new(sort: &str, key: String, id: Uuid) -> Self {
+        Self {
+            sort: sort.into(),
+            key,
+            id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}\n{}\n{}", self.sort, self.id, self.key)
+            .bytes()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    pub fn decode(value: &str, sort: &str) -> Result<Self, Violation> {
+        let invalid = || Violation::bad_format("must be a cursor returned by this endpoint");
+
+        let bytes = (0..value.len())
+            .step_by(2)
+            .map(|i| {
+                value
+                    .get(i..i + 2)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+        let decoded = String::from_utf8(bytes).map_err(|_| invalid())?;
+
+        let mut parts = decoded.splitn(3, '\n');
+        let (Some(cursor_sort), Some(id), Some(key)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+
+        if cursor_sort != sort {
+            return Err(Violation::not_allowed(
+                "was issued for a different sort order",
+            ));
+        }
+
+        let cursor = Self {
+            sort: cursor_sort.into(),
+            key: key.into(),
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        };
+        if sort == "created_at" && cursor.timestamp().is_none() {
+            return Err(invalid());
+        }
+
+        Ok(cursor)
+    }
+
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.key)
+            .ok()
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validations::Rule;
+
+    #[test]
+    fn default_limit() {
+        assert_eq!(
+            *ValidatedLimit::new(None).unwrap().as_ref(),
+            DEFAULT_PAGE_SIZE
+        );
+    }
+
+    #[test]
+    fn limit_out_of_range() {
+        assert!(ValidatedLimit::new(Some(0)).is_err());
+        assert!(ValidatedLimit::new(Some(MAX_PAGE_SIZE + 1)).is_err());
+        assert!(ValidatedLimit::new(Some(MAX_PAGE_SIZE)).is_ok());
+    }
+
+    #[test]
+    fn default_sort() {
+        let sort = ValidatedSort::new(None, &["created_at", "title"]).unwrap();
+        assert_eq!(sort.as_ref(), "created_at");
+    }
+
+    #[test]
+    fn unknown_sort() {
+        let violation = ValidatedSort::new(Some("genre".into()), &["created_at", "title"])
+            .err()
+            .unwrap();
+        assert_eq!(violation.rule, Rule::NotAllowed);
+    }
+
+    #[test]
+    fn cursor_round_trip() {
+        let id = Uuid::new_v4();
+        let encoded = Cursor::new("title", "Moby Dick\nor, The Whale".into(), id).encode();
+        let cursor = Cursor::decode(&encoded, "title").unwrap();
+
+        assert_eq!(cursor.key, "Moby Dick\nor, The Whale");
+        assert_eq!(cursor.id, id);
+    }
+
+    #[test]
+    fn cursor_for_other_sort() {
+        let encoded = Cursor::new("title", "Moby Dick".into(), Uuid::new_v4()).encode();
+        assert!(Cursor::decode(&encoded, "created_at").is_err());
+    }
+
+    #[test]
+    fn garbage_cursor() {
+        assert!(Cursor::decode("zz", "title").is_err());
+        assert!(Cursor::decode("abc", "title").is_err());
+    }
+
+    #[test]
+    fn created_at_cursor_needs_timestamp() {
+        let encoded = Cursor::new("created_at", "yesterday".into(), Uuid::new_v4()).encode();
+        assert!(Cursor::decode(&encoded, "created_at").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    routes::SearchParams,
+    validations::{page::ValidatedLimit, required_text, ValidationErrors, Violation},
+};
+
+pub struct SearchQuery {
+    pub terms: ValidatedSearchTerms,
+    pub limit: ValidatedLimit,
+}
+
+impl TryFrom<SearchParams> for SearchQuery {
+    type Error = ValidationErrors;
+
+    fn try_from(value: SearchParams) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let terms = errors.check("q", ValidatedSearchTerms::new(value.q.unwrap_or_default()));
+        let limit = errors.check("limit", ValidatedLimit::new(value.limit));
+
+        match (terms, limit) {
+            (Some(terms), Some(limit)) => Ok(Self { terms, limit }),
+            _ => Err(errors),
+        }
+    }
+}
+
+// The patron's search as a `to_tsquery` expression. Quoted text becomes a phrase and every
+// other word matches as a prefix, so partial titles and names are found while typing. The
+// words outside quotes are also kept as plain text for typo tolerant trigram matching.
+pub struct ValidatedSearchTerms {
+    tsquery: String,
+    fuzzy: Option<String>,
+}
+
+impl ValidatedSearchTerms {
+    pub fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 256)?;
+
+        let mut parts = Vec::new();
+        let mut loose_words = Vec::new();
+        for (i, chunk) in value.split('"').enumerate() {
+            let words = search_words(chunk);
+            let is_phrase = i % 2 == 1;
+
+            if is_phrase && !words.is_empty() {
+                parts.push(format!("({})", words.join(" <-> ")));
+            } else {
+                parts.extend(words.iter().map(|word| format!("{}:*", word)));
+                loose_words.extend(words);
+            }
+        }
+
+        if parts.is_empty() {
+            Err(Violation::bad_format("must contain at least one word"))
+        } else {
+            Ok(Self {
+                tsquery: parts.join(" & "),
+                fuzzy: (!loose_words.is_empty()).then(|| loose_words.join(" ")),
+            })
+        }
+    }
+
+    // Phrases ask for exact wording, so a search made only of phrases skips trigram matching
+    pub fn fuzzy(&self) -> Option<&str> {
+        self.fuzzy.as_deref()
+    }
+}
+
+impl AsRef<str> for ValidatedSearchTerms {
+    fn as_ref(&self) -> &str {
+        &self.tsquery
+    }
+}
+
+// Anything but letters and digits would be read as tsquery syntax, so it only splits words
+fn search_words(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_match_as_prefixes() {
+        let terms = ValidatedSearchTerms::new("Hobb tolk".into()).unwrap();
+        assert_eq!(terms.as_ref(), "hobb:* & tolk:*");
+        assert_eq!(terms.fuzzy(), Some("hobb tolk"));
+    }
+
+    #[test]
+    fn quoted_text_is_a_phrase() {
+        let terms = ValidatedSearchTerms::new(r#""lord of the rings" tolk"#.into()).unwrap();
+        assert_eq!(terms.as_ref(), "(lord <-> of <-> the <-> rings) & tolk:*");
+        assert_eq!(terms.fuzzy(), Some("tolk"));
+    }
+
+    #[test]
+    fn phrase_only_search_is_not_fuzzy() {
+        let terms = ValidatedSearchTerms::new(r#""the hobbit""#.into()).unwrap();
+        assert!(terms.fuzzy().is_none());
+    }
+
+    #[test]
+    fn unclosed_quote_is_still_a_phrase() {
+        let terms = ValidatedSearchTerms::new(r#"tolkien "the hob"#.into()).unwrap();
+        assert_eq!(terms.as_ref(), "tolkien:* & (the <-> hob)");
+    }
+
+    #[test]
+    fn tsquery_syntax_is_stripped() {
+        let terms = ValidatedSearchTerms::new("moby & !dick:* | (whale)".into()).unwrap();
+        assert_eq!(terms.as_ref(), "moby:* & dick:* & whale:*");
+    }
+
+    #[test]
+    fn empty_search() {
+        assert!(ValidatedSearchTerms::new(" ".into()).is_err());
+    }
+
+    #[test]
+    fn search_without_words() {
+        assert!(ValidatedSearchTerms::new("&& !!".into()).is_err());
+    }
+
+    #[test]
+    fn too_long_search() {
+        assert!(ValidatedSearchTerms::new("a".repeat(257)).is_err());
+    }
+
+    #[test]
+    fn search_query_failure() {
+        let data = SearchParams {
+            q: None,
+            limit: Some(0),
+        };
+        let errors = SearchQuery::try_from(data).err().unwrap();
+        assert_eq!(errors.errors().len(), 2);
+    }
+}
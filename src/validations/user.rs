@@ -1,33 +1,82 @@
-use crate::routes::NewUserData;
+use crate::{
+    auth::Role,
+    routes::{NewUserData, UserPatchData},
+    validations::{required_text, ValidationErrors, Violation},
+};
 use regex::Regex;
 
 pub struct NewUser {
     pub name: ValidatedUserName,
     pub email: ValidatedUserEmail,
+    pub password: Option<ValidatedPassword>,
 }
 
 impl TryFrom<NewUserData> for NewUser {
-    type Error = String;
+    type Error = ValidationErrors;
 
     fn try_from(value: NewUserData) -> Result<Self, Self::Error> {
-        let name = ValidatedUserName::new(value.name)?;
-        let email = ValidatedUserEmail::new(value.email)?;
+        let mut errors = ValidationErrors::default();
+        let name = errors.check("name", ValidatedUserName::new(value.name));
+        let email = errors.check("email", ValidatedUserEmail::new(value.email));
+        let password = value
+            .password
+            .and_then(|password| errors.check("password", ValidatedPassword::new(password)));
+
+        match (name, email) {
+            (Some(name), Some(email)) if errors.is_empty() => Ok(Self {
+                name,
+                email,
+                password,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+pub struct UserPatch {
+    pub name: Option<ValidatedUserName>,
+    pub email: Option<ValidatedUserEmail>,
+}
+
+impl TryFrom<UserPatchData> for UserPatch {
+    type Error = ValidationErrors;
+
+    fn try_from(value: UserPatchData) -> Result<Self, Self::Error> {
+        let mut errors = ValidationErrors::default();
+        let name = value
+            .name
+            .and_then(|name| errors.check("name", ValidatedUserName::new(name)));
+        let email = value
+            .email
+            .and_then(|email| errors.check("email", ValidatedUserEmail::new(email)));
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        if name.is_none() && email.is_none() {
+            return Err(ValidationErrors::single("body", Violation::empty()));
+        }
+
         Ok(Self { name, email })
     }
 }
 
+impl From<NewUser> for UserPatch {
+    fn from(value: NewUser) -> Self {
+        Self {
+            name: Some(value.name),
+            email: Some(value.email),
+        }
+    }
+}
+
 pub struct ValidatedUserName(String);
 
 impl ValidatedUserName {
-    pub fn new(value: String) -> Result<Self, String> {
-        let is_empty_or_whitespace = value.trim().is_empty();
-        let size_too_big = value.chars().count() > 256;
-
-        if is_empty_or_whitespace || size_too_big {
-            Err(format!("'{}' is not a valid user name.", value))
-        } else {
-            Ok(Self(value))
-        }
+    pub fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 256)?;
+        Ok(Self(value))
     }
 }
 
@@ -40,15 +89,14 @@ impl AsRef<str> for ValidatedUserName {
 pub struct ValidatedUserEmail(String);
 
 impl ValidatedUserEmail {
-    fn new(value: String) -> Result<Self, String> {
-        let is_empty_or_whitespace = value.trim().is_empty();
-        let size_too_big = value.chars().count() > 90;
+    fn new(value: String) -> Result<Self, Violation> {
+        required_text(&value, 90)?;
 
         // https://html.spec.whatwg.org/multipage/input.html#valid-e-mail-address
         let email_regex = Regex::new(r"^[a-zA-Z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?(?:\.[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])+)+$").unwrap();
 
-        if is_empty_or_whitespace || size_too_big || !email_regex.is_match(&value) {
-            Err(format!("'{}' is not a valid user email.", value))
+        if !email_regex.is_match(&value) {
+            Err(Violation::bad_format("must be a valid email address"))
         } else {
             Ok(Self(value))
         }
@@ -61,6 +109,43 @@ impl AsRef<str> for ValidatedUserEmail {
     }
 }
 
+// Length is all that's asked of a password, as NIST 800-63B recommends
+pub struct ValidatedPassword(String);
+
+impl ValidatedPassword {
+    pub const MIN_LENGTH: usize = 8;
+    pub const MAX_LENGTH: usize = 128;
+
+    pub fn new(value: String) -> Result<Self, Violation> {
+        match value.chars().count() {
+            n if n < Self::MIN_LENGTH => Err(Violation::too_short(Self::MIN_LENGTH)),
+            n if n > Self::MAX_LENGTH => Err(Violation::too_long(Self::MAX_LENGTH)),
+            _ => Ok(Self(value)),
+        }
+    }
+}
+
+impl AsRef<str> for ValidatedPassword {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct ValidatedRole(Role);
+
+impl ValidatedRole {
+    pub fn new(value: String) -> Result<Self, Violation> {
+        value.parse().map(Self).map_err(|_| {
+            let roles: Vec<&str> = Role::ALL.iter().map(Role::as_str).collect();
+            Violation::not_allowed(&format!("must be one of {}", roles.join(", ")))
+        })
+    }
+
+    pub fn role(&self) -> Role {
+        self.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -188,6 +273,7 @@ mod tests {
         let new_user_data = NewUserData {
             name: "John Doe".to_string(),
             email: "user@example.com".to_string(),
+            password: None,
         };
         let new_user = NewUser::try_from(new_user_data);
         assert!(new_user.is_ok());
@@ -198,6 +284,7 @@ mod tests {
         let new_user_data = NewUserData {
             name: "".to_string(),
             email: "user@example.com".to_string(),
+            password: None,
         };
         let new_user = NewUser::try_from(new_user_data);
         assert!(new_user.is_err());
@@ -208,8 +295,70 @@ mod tests {
         let new_user_data = NewUserData {
             name: "John Doe".to_string(),
             email: "invalid_email".to_string(),
+            password: None,
         };
         let new_user = NewUser::try_from(new_user_data);
         assert!(new_user.is_err());
     }
+
+    #[test]
+    fn user_patch_valid_email() {
+        let user_patch_data = UserPatchData {
+            name: None,
+            email: Some("new@example.com".to_string()),
+        };
+        let user_patch = UserPatch::try_from(user_patch_data);
+        assert!(user_patch.is_ok());
+    }
+
+    #[test]
+    fn user_patch_invalid_email() {
+        let user_patch_data = UserPatchData {
+            name: Some("John Doe".to_string()),
+            email: Some("invalid_email".to_string()),
+        };
+        let user_patch = UserPatch::try_from(user_patch_data);
+        assert!(user_patch.is_err());
+    }
+
+    #[test]
+    fn user_patch_empty() {
+        let user_patch_data = UserPatchData {
+            name: None,
+            email: None,
+        };
+        let user_patch = UserPatch::try_from(user_patch_data);
+        assert!(user_patch.is_err());
+    }
+
+    #[test]
+    fn user_roles() {
+        let role = ValidatedRole::new("librarian".into()).unwrap();
+        assert_eq!(role.role(), Role::Librarian);
+
+        let violation = ValidatedRole::new("superuser".into()).err().unwrap();
+        assert_eq!(violation.message, "must be one of patron, librarian, admin");
+    }
+
+    #[test]
+    fn password_length() {
+        assert!(ValidatedPassword::new("correct horse".into()).is_ok());
+        assert_eq!(
+            ValidatedPassword::new("short".into()).err().unwrap().rule,
+            crate::validations::Rule::TooShort
+        );
+        assert!(ValidatedPassword::new("a".repeat(129)).is_err());
+    }
+
+    #[test]
+    fn new_user_with_short_password() {
+        let errors = NewUser::try_from(NewUserData {
+            name: "John Doe".into(),
+            email: "john@example.com".into(),
+            password: Some("short".into()),
+        })
+        .err()
+        .unwrap();
+        assert_eq!(errors.errors()[0].field, "password");
+    }
 }
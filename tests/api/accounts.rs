@@ -0,0 +1,278 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use reqwest::Method;
+use serde_json::Value;
+
+const EMAIL: &str = "nora@example.com";
+const REGISTRATION: &str =
+    r#"{"name":"Nora Seed", "email":"nora@example.com", "password":"correct horse battery"}"#;
+
+async fn post(app: &TestApp, path: &str, body: String) -> reqwest::Response {
+    app.request_as(Method::POST, "", path, &body).await
+}
+
+async fn json(response: reqwest::Response) -> Value {
+    response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.")
+}
+
+async fn log_in(app: &TestApp, password: &str) -> reqwest::Response {
+    app.login(format!(
+        r#"{{"email":"{}", "password":"{}"}}"#,
+        EMAIL, password
+    ))
+    .await
+}
+
+#[tokio::test]
+async fn signup_confirms_the_email() {
+    let app = spawn_app().await;
+    app.create_user(REGISTRATION.into()).await;
+
+    let emails = app.emails_to(EMAIL);
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0]["subject"], "Confirm your Midnight Library email");
+    assert!(emails[0]["body"]
+        .as_str()
+        .unwrap()
+        .starts_with("Hello Nora Seed,"));
+
+    let token = json(log_in(&app, "correct horse battery").await).await["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(json(app.me(&token).await).await["email_verified"], false);
+
+    let body = format!(r#"{{"token":"{}"}}"#, app.last_token_sent_to(EMAIL));
+    let response = post(&app, "/verify_email", body.clone()).await;
+    assert_eq!(
+        json(response).await["message"],
+        "Email verified successfully!"
+    );
+    assert_eq!(json(app.me(&token).await).await["email_verified"], true);
+
+    let problem = json(post(&app, "/verify_email", body).await).await;
+    assert_eq!(problem["status"], 422);
+    assert_eq!(problem["errors"][0]["field"], "token");
+
+    let response = app
+        .request_as(Method::POST, &token, "/verify_email/resend", "")
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn a_new_email_has_to_be_confirmed_again() {
+    let app = spawn_app().await;
+    let user = json(app.create_user(REGISTRATION.into()).await).await;
+    let user_id = user["user_id"].as_str().unwrap();
+    let old_token = app.last_token_sent_to(EMAIL);
+
+    app.patch_user(user_id, r#"{"email":"nora.seed@example.com"}"#.into())
+        .await;
+    let record =
+        sqlx::query!("SELECT email_verified_at FROM users WHERE email = 'nora.seed@example.com'")
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch saved user.");
+    assert!(record.email_verified_at.is_none());
+    let emails = app.emails_to("nora.seed@example.com");
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0]["subject"], "Confirm your Midnight Library email");
+
+    // The token sent to the old address can't confirm the new one
+    let response = post(
+        &app,
+        "/verify_email",
+        format!(r#"{{"token":"{}"}}"#, old_token),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = post(
+        &app,
+        "/verify_email",
+        format!(
+            r#"{{"token":"{}"}}"#,
+            app.last_token_sent_to("nora.seed@example.com")
+        ),
+    )
+    .await;
+    assert!(response.status().is_success());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn password_reset_replaces_the_password_once() {
+    let app = spawn_app().await;
+    app.create_user(REGISTRATION.into()).await;
+    let old_session = json(log_in(&app, "correct horse battery").await).await["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let response = post(
+        &app,
+        "/password_reset",
+        format!(r#"{{"email":"{}"}}"#, EMAIL),
+    )
+    .await;
+    assert!(response.status().is_success());
+    let emails = app.emails_to(EMAIL);
+    assert_eq!(
+        emails.last().unwrap()["subject"],
+        "Reset your Midnight Library password"
+    );
+    let token = app.last_token_sent_to(EMAIL);
+
+    let problem = json(
+        post(
+            &app,
+            "/password_reset/confirm",
+            format!(r#"{{"token":"{}", "password":"short"}}"#, token),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(problem["errors"][0]["field"], "password");
+
+    let confirm = format!(
+        r#"{{"token":"{}", "password":"battery staple horse"}}"#,
+        token
+    );
+    let response = post(&app, "/password_reset/confirm", confirm.clone()).await;
+    assert_eq!(
+        json(response).await["message"],
+        "Password reset successfully!"
+    );
+
+    assert_eq!(app.me(&old_session).await.status().as_u16(), 401);
+    assert_eq!(
+        log_in(&app, "correct horse battery")
+            .await
+            .status()
+            .as_u16(),
+        401
+    );
+    let token = json(log_in(&app, "battery staple horse").await).await["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(json(app.me(&token).await).await["email_verified"], true);
+
+    let problem = json(post(&app, "/password_reset/confirm", confirm).await).await;
+    assert_eq!(problem["status"], 422);
+    assert_eq!(
+        problem["errors"][0]["message"],
+        "is invalid, expired or already used"
+    );
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn unknown_emails_and_expired_tokens_get_nowhere() {
+    let app = spawn_app().await;
+    app.create_user(REGISTRATION.into()).await;
+
+    let response = post(
+        &app,
+        "/password_reset",
+        r#"{"email":"nobody@example.com"}"#.into(),
+    )
+    .await;
+    assert!(response.status().is_success());
+    assert!(app.emails_to("nobody@example.com").is_empty());
+
+    post(
+        &app,
+        "/password_reset",
+        format!(r#"{{"email":"{}"}}"#, EMAIL),
+    )
+    .await;
+    let first = app.last_token_sent_to(EMAIL);
+    post(
+        &app,
+        "/password_reset",
+        format!(r#"{{"email":"{}"}}"#, EMAIL),
+    )
+    .await;
+    let second = app.last_token_sent_to(EMAIL);
+    sqlx::query!("UPDATE email_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to expire tokens.");
+
+    for token in [first, second] {
+        let response = post(
+            &app,
+            "/password_reset/confirm",
+            format!(
+                r#"{{"token":"{}", "password":"battery staple horse"}}"#,
+                token
+            ),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 422);
+    }
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn a_changed_email_gets_no_reset_until_confirmed() {
+    let app = spawn_app().await;
+    let user = json(app.create_user(REGISTRATION.into()).await).await;
+    let user_id = user["user_id"].as_str().unwrap();
+    post(
+        &app,
+        "/verify_email",
+        format!(r#"{{"token":"{}"}}"#, app.last_token_sent_to(EMAIL)),
+    )
+    .await;
+
+    let new_email = "someone.else@example.com";
+    app.patch_user(user_id, format!(r#"{{"email":"{}"}}"#, new_email))
+        .await;
+    let notice = app.emails_to(EMAIL).pop().unwrap();
+    assert_eq!(notice["subject"], "Your Midnight Library email was changed");
+    assert!(notice["body"].as_str().unwrap().contains(new_email));
+
+    let response = post(
+        &app,
+        "/password_reset",
+        format!(r#"{{"email":"{}"}}"#, new_email),
+    )
+    .await;
+    assert!(response.status().is_success());
+    assert_eq!(app.emails_to(new_email).len(), 1);
+    let tokens =
+        sqlx::query_scalar!("SELECT count(*) FROM email_tokens WHERE purpose = 'reset_password'")
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to count tokens.");
+    assert_eq!(tokens, Some(0));
+
+    post(
+        &app,
+        "/verify_email",
+        format!(r#"{{"token":"{}"}}"#, app.last_token_sent_to(new_email)),
+    )
+    .await;
+    post(
+        &app,
+        "/password_reset",
+        format!(r#"{{"email":"{}"}}"#, new_email),
+    )
+    .await;
+    assert_eq!(
+        app.emails_to(new_email).last().unwrap()["subject"],
+        "Reset your Midnight Library password"
+    );
+
+    drop_db(app.db_name, app.db_url).await;
+}
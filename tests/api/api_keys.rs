@@ -0,0 +1,215 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use midnight_library::auth::Role;
+use reqwest::Method;
+use serde_json::Value;
+
+async fn create_key(app: &TestApp, admin: &str, body: &str) -> Value {
+    let response = app.request_as(Method::POST, admin, "/api_keys", body).await;
+    assert!(response.status().is_success());
+    response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.")
+}
+
+async fn problem(response: reqwest::Response) -> Value {
+    response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.")
+}
+
+#[tokio::test]
+async fn admins_create_and_list_keys() {
+    let app = spawn_app().await;
+    let (admin_id, admin) = app.session_for(Role::Admin).await;
+
+    let created = create_key(
+        &app,
+        &admin,
+        r#"{"name":"Nightly sync", "scope":"catalog_read", "expires_in_days":30}"#,
+    )
+    .await;
+    let key = created["key"].as_str().unwrap();
+    assert_eq!(created["message"], "API key created successfully!");
+    assert!(key.starts_with("mlk_"));
+    assert!(created["expires_at"].is_string());
+
+    let stored = sqlx::query!("SELECT key_hash FROM api_keys")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved API key.");
+    assert!(!stored.key_hash.contains(&key[4..]));
+
+    let response = app.request_as(Method::GET, &admin, "/api_keys", "").await;
+    let keys = response
+        .json::<Vec<Value>>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0]["id"], created["api_key_id"]);
+    assert_eq!(keys[0]["name"], "Nightly sync");
+    assert_eq!(keys[0]["prefix"], key[..12]);
+    assert_eq!(keys[0]["created_by"], admin_id.to_string());
+    assert!(keys[0]["revoked_at"].is_null());
+    assert!(keys[0].get("key").is_none());
+
+    let response = app
+        .request_as(Method::GET, &app.staff_token, "/api_keys", "")
+        .await;
+    assert_eq!(problem(response).await["detail"], "Requires the admin role");
+
+    let response = app
+        .request_as(
+            Method::POST,
+            &admin,
+            "/api_keys",
+            r#"{"name":"Kiosk", "scope":"everything"}"#,
+        )
+        .await;
+    let invalid = problem(response).await;
+    assert_eq!(invalid["status"], 422);
+    assert_eq!(invalid["errors"][0]["field"], "scope");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn keys_are_limited_to_their_scope() {
+    let app = spawn_app().await;
+    let (_, admin) = app.session_for(Role::Admin).await;
+    let (patron_id, _) = app.session_for(Role::Patron).await;
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    let book = app
+        .create_book(r#"{"title":"The Hobbit", "author":"JRR Tolkien", "genre":"Fantasy"}"#.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let book_id = book["book_id"].as_str().unwrap();
+    app.create_item(
+        book_id,
+        r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"Fantasy B-1"}"#.into(),
+    )
+    .await;
+    let sync = create_key(&app, &admin, r#"{"name":"Sync", "scope":"catalog_read"}"#).await;
+    let sync = sync["key"].as_str().unwrap();
+    let kiosk = create_key(&app, &admin, r#"{"name":"Kiosk", "scope":"circulation"}"#).await;
+    let kiosk = kiosk["key"].as_str().unwrap();
+
+    let new_book = r#"{"title":"Silmarillion", "author":"JRR Tolkien", "genre":"Fantasy"}"#;
+    for key in [sync, kiosk] {
+        let response = app.request_as(Method::POST, key, "/books", new_book).await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+    let response = app.request_as(Method::GET, sync, "/me", "").await;
+    assert!(response.status().is_success());
+
+    let loan = format!(
+        r#"{{"user_id": "{}", "book_id": "{}"}}"#,
+        patron_id, book_id
+    );
+    let response = app.request_as(Method::POST, sync, "/loans", &loan).await;
+    assert_eq!(
+        problem(response).await["detail"],
+        "API key scope catalog_read does not allow this"
+    );
+    let response = app.request_as(Method::POST, kiosk, "/loans", &loan).await;
+    assert!(response.status().is_success());
+
+    let used = sqlx::query!("SELECT last_used_at FROM api_keys WHERE name = 'Kiosk'")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved API key.");
+    assert!(used.last_used_at.is_some());
+
+    let response = app.request_as(Method::POST, kiosk, "/logout", "").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn revoked_and_expired_keys_are_unauthorized() {
+    let app = spawn_app().await;
+    let (_, admin) = app.session_for(Role::Admin).await;
+    let revoked = create_key(
+        &app,
+        &admin,
+        r#"{"name":"Old kiosk", "scope":"circulation"}"#,
+    )
+    .await;
+    let expired = create_key(&app, &admin, r#"{"name":"Trial", "scope":"circulation"}"#).await;
+
+    let path = format!("/api_keys/{}", revoked["api_key_id"].as_str().unwrap());
+    let response = app.request_as(Method::DELETE, &admin, &path, "").await;
+    assert!(response.status().is_success());
+    let response = app.request_as(Method::DELETE, &admin, &path, "").await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    sqlx::query!(
+        "UPDATE api_keys SET expires_at = now() - interval '1 minute' WHERE name = 'Trial'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to expire API key.");
+
+    for key in [
+        revoked["key"].as_str().unwrap(),
+        expired["key"].as_str().unwrap(),
+        "mlk_0000000000000000",
+    ] {
+        let response = app.request_as(Method::GET, key, "/me", "").await;
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(
+            problem(response).await["detail"],
+            "Invalid, expired or revoked API key"
+        );
+    }
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn keys_cannot_edit_accounts() {
+    let app = spawn_app().await;
+    let (_, admin) = app.session_for(Role::Admin).await;
+    let (patron_id, _) = app.session_for(Role::Patron).await;
+    let path = format!("/users/{}", patron_id);
+
+    for scope in ["circulation", "admin"] {
+        let key = create_key(
+            &app,
+            &admin,
+            &format!(r#"{{"name":"Kiosk", "scope":"{}"}}"#, scope),
+        )
+        .await;
+        let key = key["key"].as_str().unwrap();
+
+        let response = app
+            .request_as(
+                Method::PATCH,
+                key,
+                &path,
+                r#"{"email":"attacker@example.com"}"#,
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 403);
+        assert_eq!(
+            problem(response).await["detail"],
+            "API keys can't edit accounts"
+        );
+        let response = app
+            .request_as(
+                Method::PUT,
+                key,
+                &path,
+                r#"{"name":"Patron", "email":"attacker@example.com"}"#,
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+
+    drop_db(app.db_name, app.db_url).await;
+}
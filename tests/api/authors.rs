@@ -1,4 +1,5 @@
 use crate::test_helpers::{drop_db, spawn_app};
+use midnight_library::routes::create_missing_author;
 use serde_json::Value;
 
 #[tokio::test]
@@ -23,6 +24,50 @@ async fn authors_index() {
     drop_db(app.db_name, app.db_url).await;
 }
 
+#[tokio::test]
+async fn authors_index_filtered_and_sorted_by_name() {
+    let app = spawn_app().await;
+    for (name, nationality) in [
+        ("Virginia Woolf", "British"),
+        ("Herman Melville", "American"),
+        ("Jane Austen", "British"),
+    ] {
+        app.create_author(format!(
+            r#"{{"name":"{}", "nationality":"{}"}}"#,
+            name, nationality
+        ))
+        .await;
+    }
+
+    let first_page = app
+        .author_index_query("nationality=British&sort=name&limit=1")
+        .await;
+    let link = first_page.headers()["Link"].to_str().unwrap().to_string();
+    let first_authors = first_page
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let second_authors = reqwest::Client::new()
+        .get(format!(
+            "http://{}{}",
+            &app.address,
+            &link[1..link.find('>').unwrap()]
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(first_authors.as_array().unwrap().len(), 1);
+    assert_eq!(first_authors[0]["name"], "Jane Austen");
+    assert_eq!(second_authors.as_array().unwrap().len(), 1);
+    assert_eq!(second_authors[0]["name"], "Virginia Woolf");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
 #[tokio::test]
 async fn show_author() {
     let app = spawn_app().await;
@@ -99,8 +144,7 @@ async fn author_deletion() {
         .as_str()
         .expect("Failed to extract author id from response.");
 
-    app.delete_author(format!(r#"{{"id": "{}"}}"#, author_id))
-        .await;
+    app.delete_author(author_id, &app.staff_token).await;
     let record = sqlx::query!("SELECT * FROM authors")
         .fetch_optional(&app.db_pool)
         .await
@@ -110,3 +154,136 @@ async fn author_deletion() {
 
     drop_db(app.db_name, app.db_url).await;
 }
+
+#[tokio::test]
+async fn author_update() {
+    let app = spawn_app().await;
+    let create_response = app
+        .create_author(r#"{"name":"JRR Tolkein", "nationality":"American"}"#.into())
+        .await;
+    let response_body = create_response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let author_id = response_body["author_id"]
+        .as_str()
+        .expect("Failed to extract author id from response.");
+
+    let response = app
+        .update_author(
+            author_id,
+            r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into(),
+        )
+        .await;
+    let author = app
+        .show_author(author_id.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert!(response.status().is_success());
+    assert_eq!(author["id"], author_id);
+    assert_eq!(author["name"], "JRR Tolkien");
+    assert_eq!(author["nationality"], "British");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn author_partial_update() {
+    let app = spawn_app().await;
+    let create_response = app
+        .create_author(r#"{"name":"JRR Tolkien", "nationality":"American"}"#.into())
+        .await;
+    let response_body = create_response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let author_id = response_body["author_id"]
+        .as_str()
+        .expect("Failed to extract author id from response.");
+
+    let response = app
+        .patch_author(author_id, r#"{"nationality":"British"}"#.into())
+        .await;
+    let empty_response = app.patch_author(author_id, r#"{}"#.into()).await;
+    let author = app
+        .show_author(author_id.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert!(response.status().is_success());
+    assert!(empty_response.status().is_client_error());
+    assert_eq!(author["name"], "JRR Tolkien");
+    assert_eq!(author["nationality"], "British");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn author_suggestions() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"Herman Melville", "nationality":"American"}"#.into())
+        .await;
+    app.create_author(r#"{"name":"Hermann Hesse", "nationality":"German"}"#.into())
+        .await;
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+
+    let response = app.suggest_authors("q=melvil").await;
+    assert!(response.status().is_success());
+    let authors = response
+        .json::<Vec<Value>>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(authors.len(), 1);
+    assert_eq!(authors[0]["name"], "Herman Melville");
+
+    let authors = app
+        .suggest_authors("q=herman")
+        .await
+        .json::<Vec<Value>>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(authors.len(), 2);
+    assert_eq!(authors[0]["name"], "Herman Melville");
+    assert_eq!(authors[1]["name"], "Hermann Hesse");
+
+    let response = app.suggest_authors("q=").await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn concurrent_imports_add_a_missing_author_once() {
+    let app = spawn_app().await;
+
+    let mut first = app.db_pool.begin().await.expect("Failed to begin.");
+    create_missing_author("Mary Shelley", &mut first)
+        .await
+        .expect("Failed to create author.");
+    // The second import waits on the first, then finds the author it added
+    let pool = app.db_pool.clone();
+    let second = tokio::spawn(async move {
+        let mut second = pool.begin().await.expect("Failed to begin.");
+        create_missing_author("Mary Shelley", &mut second)
+            .await
+            .expect("Failed to create author.");
+        second.commit().await.expect("Failed to commit.");
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    first.commit().await.expect("Failed to commit.");
+    second.await.expect("Second import panicked.");
+
+    let count = sqlx::query_scalar!("SELECT count(*) FROM authors WHERE name = 'Mary Shelley'")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count authors.");
+    assert_eq!(count, Some(1));
+
+    drop_db(app.db_name, app.db_url).await;
+}
@@ -77,7 +77,10 @@ async fn book_creation() {
             books.title,
             authors.name AS "authors_name",
             books.genre,
-            books.created_at  FROM books JOIN authors ON books.author_id = authors.id"#
+            book_contributors.role,
+            books.created_at  FROM books
+            JOIN book_contributors ON book_contributors.book_id = books.id
+            JOIN authors ON book_contributors.author_id = authors.id"#
     )
     .fetch_one(&app.db_pool)
     .await
@@ -86,11 +89,118 @@ async fn book_creation() {
     assert!(response.status().is_success());
     assert_eq!(record.title, "Lord of the Rings");
     assert_eq!(record.authors_name, "JRR Tolkien");
+    assert_eq!(record.role, "author");
     assert_eq!(record.genre, "Fiction");
 
     drop_db(app.db_name, app.db_url).await;
 }
 
+#[tokio::test]
+async fn books_index_pages_through_sorted_books() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    for title in ["The Silmarillion", "Lord of the Rings", "The Hobbit"] {
+        app.create_book(format!(
+            r#"{{"title":"{}", "author":"JRR Tolkien", "genre": "Fiction"}}"#,
+            title
+        ))
+        .await;
+    }
+
+    let first_page = app.book_index_query("sort=title&limit=2").await;
+    let link = first_page.headers()["Link"].to_str().unwrap().to_string();
+    let first_books = first_page
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let next_path = &link[1..link.find('>').unwrap()];
+    let second_page = reqwest::Client::new()
+        .get(format!("http://{}{}", &app.address, next_path))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let has_third_page = second_page.headers().contains_key("Link");
+    let second_books = second_page
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert!(link.ends_with(r#"rel="next""#));
+    assert!(next_path.contains("sort=title"));
+    assert_eq!(first_books.as_array().unwrap().len(), 2);
+    assert_eq!(first_books[0]["title"], "Lord of the Rings");
+    assert_eq!(first_books[1]["title"], "The Hobbit");
+    assert_eq!(second_books.as_array().unwrap().len(), 1);
+    assert_eq!(second_books[0]["title"], "The Silmarillion");
+    assert!(!has_third_page);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn books_index_filtered_by_genre_and_author() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    let author_id = app
+        .create_author(r#"{"name":"Herman Melville", "nationality":"American"}"#.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.")["author_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    app.create_book(r#"{"title":"The Hobbit", "author":"JRR Tolkien", "genre": "Fantasy"}"#.into())
+        .await;
+    app.create_book(
+        r#"{"title":"Moby Dick", "author":"Herman Melville", "genre": "Adventure"}"#.into(),
+    )
+    .await;
+    app.create_book(r#"{"title":"Typee", "author":"Herman Melville", "genre": "Travel"}"#.into())
+        .await;
+
+    let by_genre = app
+        .book_index_query("genre=Fantasy")
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let by_author = app
+        .book_index_query(&format!("author_id={}&sort=title", author_id))
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(by_genre.as_array().unwrap().len(), 1);
+    assert_eq!(by_genre[0]["title"], "The Hobbit");
+    assert_eq!(by_author.as_array().unwrap().len(), 2);
+    assert_eq!(by_author[0]["title"], "Moby Dick");
+    assert_eq!(by_author[1]["title"], "Typee");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn books_index_with_invalid_query() {
+    let app = spawn_app().await;
+
+    let response = app.book_index_query("limit=500&cursor=nope").await;
+    let status = response.status().as_u16();
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(status, 422);
+    assert_eq!(problem["errors"][0]["field"], "limit");
+    assert_eq!(problem["errors"][1]["field"], "cursor");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
 #[tokio::test]
 async fn book_creation_with_incomplete_data() {
     let app = spawn_app().await;
@@ -100,7 +210,7 @@ async fn book_creation_with_incomplete_data() {
     let response = app
         .create_book(r#"{"title":"Lord of the Rings", "author":"JRR Tolkien"}"#.into())
         .await;
-    let record = sqlx::query!("SELECT * FROM books")
+    let record = sqlx::query!("SELECT id FROM books")
         .fetch_optional(&app.db_pool)
         .await
         .expect("Failed to fetch saved book.");
@@ -111,6 +221,97 @@ async fn book_creation_with_incomplete_data() {
     drop_db(app.db_name, app.db_url).await;
 }
 
+#[tokio::test]
+async fn book_creation_reports_every_invalid_field() {
+    let app = spawn_app().await;
+
+    let response = app
+        .create_book(format!(
+            r#"{{"title":" ", "author":"JRR Tolkien", "genre": "{}"}}"#,
+            "a".repeat(81)
+        ))
+        .await;
+    let status = response.status().as_u16();
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(status, 422);
+    assert_eq!(problem["errors"].as_array().unwrap().len(), 2);
+    assert_eq!(problem["errors"][0]["field"], "title");
+    assert_eq!(problem["errors"][0]["rule"], "empty");
+    assert_eq!(problem["errors"][1]["field"], "genre");
+    assert_eq!(problem["errors"][1]["rule"], "too_long");
+    assert_eq!(problem["errors"][1]["max"], 80);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_creation_with_malformed_body() {
+    let app = spawn_app().await;
+
+    let response = app.create_book(r#"{"title":"Lord of the"#.into()).await;
+    let status = response.status().as_u16();
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(status, 400);
+    assert_eq!(problem["code"], "malformed_request");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_creation_with_misspelt_author() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"Herman Melville", "nationality":"American"}"#.into())
+        .await;
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+
+    let response = app
+        .create_book(
+            r#"{"title":"Moby Dick", "author":"Herman Melvile", "genre": "Adventure"}"#.into(),
+        )
+        .await;
+    let status = response.status().as_u16();
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(status, 404);
+    assert_eq!(problem["code"], "author_not_found");
+    assert_eq!(problem["candidates"].as_array().unwrap().len(), 1);
+    assert_eq!(problem["candidates"][0]["name"], "Herman Melville");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn show_unknown_book() {
+    let app = spawn_app().await;
+
+    let response = app
+        .show_book("b1d1b7e0-7d0e-4c2a-9d6a-3f0b1f3f8c1a".into())
+        .await;
+    let status = response.status().as_u16();
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(status, 404);
+    assert_eq!(problem["code"], "not_found");
+    assert_eq!(problem["title"], "Not Found");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
 #[tokio::test]
 async fn book_deletion() {
     let app = spawn_app().await;
@@ -129,8 +330,8 @@ async fn book_deletion() {
         .as_str()
         .expect("Failed to extract author id from response.");
 
-    app.book_delete(format!(r#"{{"id": "{}"}}"#, book_id)).await;
-    let record = sqlx::query!("SELECT * FROM books")
+    app.book_delete(book_id, &app.staff_token).await;
+    let record = sqlx::query!("SELECT id FROM books")
         .fetch_optional(&app.db_pool)
         .await
         .expect("Failed to fetch saved book.");
@@ -139,3 +340,425 @@ async fn book_deletion() {
 
     drop_db(app.db_name, app.db_url).await;
 }
+
+#[tokio::test]
+async fn book_update() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    app.create_author(r#"{"name":"Herman Melville", "nationality":"American"}"#.into())
+        .await;
+    let create_response = app
+        .create_book(r#"{"title":"Moby Dik", "author":"JRR Tolkien", "genre": "Fiction"}"#.into())
+        .await;
+    let response_body = create_response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let book_id = response_body["book_id"]
+        .as_str()
+        .expect("Failed to extract book id from response.");
+
+    let response = app
+        .update_book(
+            book_id,
+            r#"{"title":"Moby Dick", "author":"Herman Melville", "genre": "Adventure"}"#.into(),
+        )
+        .await;
+    let book = app
+        .show_book(book_id.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert!(response.status().is_success());
+    assert_eq!(book["id"], book_id);
+    assert_eq!(book["title"], "Moby Dick");
+    assert_eq!(book["author"], "Herman Melville");
+    assert_eq!(book["genre"], "Adventure");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_partial_update() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    let create_response = app
+        .create_book(r#"{"title":"The Hobit", "author":"JRR Tolkien", "genre": "Fiction"}"#.into())
+        .await;
+    let response_body = create_response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let book_id = response_body["book_id"]
+        .as_str()
+        .expect("Failed to extract book id from response.");
+
+    let response = app
+        .patch_book(book_id, r#"{"title":"The Hobbit"}"#.into())
+        .await;
+    let invalid_response = app.patch_book(book_id, r#"{"genre":""}"#.into()).await;
+    let book = app
+        .show_book(book_id.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert!(response.status().is_success());
+    assert!(invalid_response.status().is_client_error());
+    assert_eq!(book["title"], "The Hobbit");
+    assert_eq!(book["author"], "JRR Tolkien");
+    assert_eq!(book["genre"], "Fiction");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_update_not_found() {
+    let app = spawn_app().await;
+
+    let response = app
+        .patch_book(
+            "5c1b7a0e-4f1c-4d55-9b43-0c3b0a6f9d11",
+            r#"{"title":"The Hobbit"}"#.into(),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 404);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_creation_by_author_id() {
+    let app = spawn_app().await;
+    let author = app
+        .create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let author_id = author["author_id"].as_str().unwrap();
+
+    let response = app
+        .create_book(format!(
+            r#"{{"title":"The Hobbit", "author_id":"{}", "genre": "Fantasy"}}"#,
+            author_id
+        ))
+        .await;
+    assert!(response.status().is_success());
+    let book_id = response.json::<Value>().await.unwrap()["book_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let book = app
+        .show_book(book_id)
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(book["author"], "JRR Tolkien");
+
+    let response = app
+        .create_book(
+            r#"{"title":"The Hobbit", "author_id":"b1d1b7e0-7d0e-4c2a-9d6a-3f0b1f3f8c1a", "genre": "Fantasy"}"#
+                .into(),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_creation_with_ambiguous_author_name() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"John Smith", "nationality":"British"}"#.into())
+        .await;
+    let author = app
+        .create_author(r#"{"name":"John Smith", "nationality":"American"}"#.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    let response = app
+        .create_book(r#"{"title":"A Voyage", "author":"John Smith", "genre": "Travel"}"#.into())
+        .await;
+    let status = response.status().as_u16();
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(status, 409);
+    assert_eq!(problem["code"], "ambiguous_author");
+    assert_eq!(problem["candidates"].as_array().unwrap().len(), 2);
+    assert_eq!(problem["candidates"][0]["nationality"], "British");
+    assert_eq!(problem["candidates"][1]["id"], author["author_id"]);
+
+    let response = app
+        .create_book(format!(
+            r#"{{"title":"A Voyage", "author":"John Smith", "author_id":"{}", "genre": "Travel"}}"#,
+            author["author_id"].as_str().unwrap()
+        ))
+        .await;
+    assert!(response.status().is_success());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_with_several_contributors() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"Homer", "nationality":"Greek"}"#.into())
+        .await;
+    let translator = app
+        .create_author(r#"{"name":"Emily Wilson", "nationality":"British"}"#.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let translator_id = translator["author_id"].as_str().unwrap();
+
+    let response = app
+        .create_book(format!(
+            r#"{{"title":"The Odyssey", "genre": "Epic", "contributors": [
+                {{"author":"Homer"}},
+                {{"author_id":"{}", "role":"translator"}}
+            ]}}"#,
+            translator_id
+        ))
+        .await;
+    assert!(response.status().is_success());
+    let book_id = response.json::<Value>().await.unwrap()["book_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let book = app
+        .show_book(book_id.clone())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(book["author"], "Homer");
+    assert_eq!(book["contributors"][0]["name"], "Homer");
+    assert_eq!(book["contributors"][0]["role"], "author");
+    assert_eq!(book["contributors"][1]["author_id"], translator_id);
+    assert_eq!(book["contributors"][1]["role"], "translator");
+
+    let books = app
+        .book_index_query(&format!("author_id={}", translator_id))
+        .await
+        .json::<Vec<Value>>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(books.len(), 1);
+    assert_eq!(books[0]["contributors"].as_array().unwrap().len(), 2);
+
+    let results = app
+        .search("q=wilson")
+        .await
+        .json::<Vec<Value>>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(results[0]["title"], "The Odyssey");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_contributors_are_replaced_on_patch() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"Homer", "nationality":"Greek"}"#.into())
+        .await;
+    app.create_author(r#"{"name":"Emily Wilson", "nationality":"British"}"#.into())
+        .await;
+    let book_id = app
+        .create_book(r#"{"title":"The Odyssey", "author":"Homer", "genre": "Epic"}"#.into())
+        .await
+        .json::<Value>()
+        .await
+        .unwrap()["book_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let duplicate = app
+        .patch_book(
+            &book_id,
+            r#"{"contributors": [{"author":"Homer"}, {"author":"Homer"}]}"#.into(),
+        )
+        .await;
+    assert_eq!(duplicate.status().as_u16(), 409);
+
+    let response = app
+        .patch_book(
+            &book_id,
+            r#"{"contributors": [{"author":"Emily Wilson", "role":"editor"}, {"author":"Homer"}]}"#
+                .into(),
+        )
+        .await;
+    assert!(response.status().is_success());
+
+    let book = app
+        .show_book(book_id)
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(book["author"], "Emily Wilson");
+    assert_eq!(book["contributors"][0]["role"], "editor");
+    assert_eq!(book["contributors"][1]["name"], "Homer");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_metadata() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"Herman Melville", "nationality":"American"}"#.into())
+        .await;
+
+    let response = app
+        .create_book(
+            r#"{"title":"Moby Dick", "author":"Herman Melville", "genre": "Adventure",
+                "isbn":"0-14-243724-7", "publisher":"Penguin Classics", "publication_year":2003,
+                "language":"EN", "page_count":720, "edition":"Reprint",
+                "description":"The voyage of the Pequod"}"#
+                .into(),
+        )
+        .await;
+    assert!(response.status().is_success());
+    let book_id = response.json::<Value>().await.unwrap()["book_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let book = app
+        .show_book(book_id.clone())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(book["isbn_13"], "9780142437247");
+    assert_eq!(book["isbn_10"], "0142437247");
+    assert_eq!(book["publisher"], "Penguin Classics");
+    assert_eq!(book["publication_year"], 2003);
+    assert_eq!(book["language"], "en");
+    assert_eq!(book["page_count"], 720);
+    assert_eq!(book["edition"], "Reprint");
+
+    let response = app
+        .patch_book(&book_id, r#"{"page_count":654}"#.into())
+        .await;
+    assert!(response.status().is_success());
+    let book = app
+        .show_book(book_id)
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(book["page_count"], 654);
+    assert_eq!(book["publisher"], "Penguin Classics");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_update_clears_omitted_metadata() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"Herman Melville", "nationality":"American"}"#.into())
+        .await;
+    let response = app
+        .create_book(
+            r#"{"title":"Moby Dick", "author":"Herman Melville", "genre": "Adventure",
+                "isbn":"0-14-243724-7", "publisher":"Penguin Classics", "publication_year":2003,
+                "language":"en", "page_count":720, "edition":"Reprint",
+                "description":"The voyage of the Pequod"}"#
+                .into(),
+        )
+        .await;
+    let book_id = response.json::<Value>().await.unwrap()["book_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let response = app
+        .update_book(
+            &book_id,
+            r#"{"title":"Moby-Dick", "author":"Herman Melville", "genre": "Adventure",
+                "page_count":654}"#
+                .into(),
+        )
+        .await;
+    assert!(response.status().is_success());
+    let book = app
+        .show_book(book_id)
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(book["title"], "Moby-Dick");
+    assert_eq!(book["page_count"], 654);
+    for field in [
+        "isbn_13",
+        "publisher",
+        "publication_year",
+        "language",
+        "edition",
+        "description",
+    ] {
+        assert!(book[field].is_null(), "{} was kept", field);
+    }
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn book_isbn_is_unique() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"Herman Melville", "nationality":"American"}"#.into())
+        .await;
+    app.create_book(
+        r#"{"title":"Moby Dick", "author":"Herman Melville", "genre": "Adventure", "isbn":"9780142437247"}"#
+            .into(),
+    )
+    .await;
+
+    // The same ISBN written as an ISBN-10
+    let response = app
+        .create_book(
+            r#"{"title":"Moby-Dick", "author":"Herman Melville", "genre": "Adventure", "isbn":"0142437247"}"#
+                .into(),
+        )
+        .await;
+    let status = response.status().as_u16();
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(status, 409);
+    assert_eq!(problem["detail"], "A book with this ISBN already exists");
+
+    let response = app
+        .create_book(
+            r#"{"title":"Moby-Dick", "author":"Herman Melville", "genre": "Adventure", "isbn":"0142437248"}"#
+                .into(),
+        )
+        .await;
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(problem["errors"][0]["field"], "isbn");
+
+    drop_db(app.db_name, app.db_url).await;
+}
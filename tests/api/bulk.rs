@@ -0,0 +1,208 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use serde_json::Value;
+
+const AUTHORS_CSV: &str = "name,nationality
+Eiichiro Oda,Japanese
+Akira Toriyama,Japanese
+";
+
+async fn import_authors(app: &TestApp) {
+    let response = app.import_csv("authors", "", AUTHORS_CSV.into()).await;
+    assert!(response.status().is_success());
+}
+
+async fn json(response: reqwest::Response) -> Value {
+    response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.")
+}
+
+#[tokio::test]
+async fn import_authors_csv() {
+    let app = spawn_app().await;
+
+    let body = json(app.import_csv("authors", "", AUTHORS_CSV.into()).await).await;
+    assert_eq!(body["imported"], 2);
+
+    let authors = json(app.author_index().await).await;
+    assert_eq!(authors.as_array().unwrap().len(), 2);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn import_books_csv_dry_run_then_for_real() {
+    let app = spawn_app().await;
+    import_authors(&app).await;
+    let csv = "title,author,genre,isbn,page_count
+One Piece,Eiichiro Oda,Shounen,978-1-56931-901-7,216
+\"Oda & Toriyama: Cross Epoch\",Eiichiro Oda; Akira Toriyama,Shounen,,
+";
+
+    let body = json(app.import_csv("books", "dry_run=true", csv.into()).await).await;
+    assert_eq!(body["dry_run"], true);
+    assert_eq!(body["rows"], 2);
+    assert_eq!(body["imported"], 0);
+    assert_eq!(json(app.book_index().await).await, Value::Array(vec![]));
+
+    let body = json(app.import_csv("books", "", csv.into()).await).await;
+    assert_eq!(body["imported"], 2);
+
+    let books = json(app.book_index_query("sort=title").await).await;
+    assert_eq!(books[0]["title"], "Oda & Toriyama: Cross Epoch");
+    assert_eq!(books[0]["contributors"][1]["name"], "Akira Toriyama");
+    assert_eq!(books[1]["isbn_13"], "9781569319017");
+    assert_eq!(books[1]["page_count"], 216);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn import_books_csv_reports_every_bad_row() {
+    let app = spawn_app().await;
+    import_authors(&app).await;
+    let csv = "title,author,genre,isbn,page_count
+One Piece,Eiichiro Oda,Shounen,9781569319017,
+,Eiichiro Oda,Shounen,,lots
+Dragon Ball,Akira Toriyam,Shounen,,
+One Piece again,Eiichiro Oda,Shounen,9781569319017,
+";
+
+    let response = app.import_csv("books", "", csv.into()).await;
+    assert_eq!(response.status().as_u16(), 422);
+    let problem = json(response).await;
+    assert_eq!(problem["code"], "import_rejected");
+
+    let rows = problem["rows"].as_array().unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0]["row"], 3);
+    assert_eq!(rows[0]["code"], "validation_failed");
+    assert_eq!(rows[0]["errors"][0]["field"], "page_count");
+    assert_eq!(rows[0]["errors"][1]["field"], "title");
+    assert_eq!(rows[1]["row"], 4);
+    assert_eq!(rows[1]["code"], "author_not_found");
+    assert_eq!(rows[1]["candidates"][0]["name"], "Akira Toriyama");
+    assert_eq!(rows[2]["row"], 5);
+    assert_eq!(rows[2]["code"], "conflict");
+
+    // The valid first row went back out with the rest
+    assert_eq!(json(app.book_index().await).await, Value::Array(vec![]));
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn import_csv_with_missing_columns() {
+    let app = spawn_app().await;
+
+    let response = app
+        .import_csv("books", "", "title,author\nOne Piece,Eiichiro Oda\n".into())
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .import_csv("authors", "", "name,nationality\n".into())
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn export_books_csv_round_trips() {
+    let app = spawn_app().await;
+    import_authors(&app).await;
+    let csv = "title,author,genre,isbn,publication_year
+One Piece,Eiichiro Oda,Shounen,9781569319017,1997
+\"Dragon Ball, Vol. 1\",Akira Toriyama,Shounen,,
+";
+    app.import_csv("books", "", csv.into()).await;
+
+    let response = app.export_csv("books").await;
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    let export = response.text().await.unwrap();
+    let lines: Vec<&str> = export.lines().collect();
+    assert_eq!(
+        lines[0],
+        "id,title,author,genre,isbn,publisher,publication_year,language,page_count,edition,description"
+    );
+    assert_eq!(lines.len(), 3);
+    assert!(export.contains(",\"Dragon Ball, Vol. 1\",Akira Toriyama,Shounen,"));
+    assert!(export.contains(",One Piece,Eiichiro Oda,Shounen,9781569319017,,1997,"));
+
+    // What comes out goes back in, here clashing with the ISBN already in the catalog
+    let response = app.import_csv("books", "dry_run=true", export).await;
+    let problem = json(response).await;
+    assert_eq!(problem["rows"].as_array().unwrap().len(), 1);
+    assert_eq!(problem["rows"][0]["code"], "conflict");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn export_keeps_contributor_roles() {
+    let app = spawn_app().await;
+    let authors = "name,nationality
+Haruki Murakami,Japanese
+Jay Rubin,American
+Neil Gaiman,British
+";
+    app.import_csv("authors", "", authors.into()).await;
+    for book in [
+        r#"{"title":"Norwegian Wood", "genre":"Fiction", "contributors":[
+            {"author":"Haruki Murakami"}, {"author":"Jay Rubin", "role":"translator"}]}"#,
+        r#"{"title":"Unnatural Creatures", "genre":"Fantasy", "contributors":[
+            {"author":"Neil Gaiman", "role":"editor"}]}"#,
+    ] {
+        let response = app.create_book(book.into()).await;
+        assert!(response.status().is_success());
+    }
+
+    let export = app.export_csv("books").await.text().await.unwrap();
+    assert!(export.contains(",Norwegian Wood,Haruki Murakami; Jay Rubin (translator),Fiction,"));
+    assert!(export.contains(",Unnatural Creatures,Neil Gaiman (editor),Fantasy,"));
+
+    let before = json(app.book_index_query("sort=title").await).await;
+    for book in before.as_array().unwrap() {
+        app.book_delete(book["id"].as_str().unwrap(), &app.staff_token)
+            .await;
+    }
+    let body = json(app.import_csv("books", "", export).await).await;
+    assert_eq!(body["imported"], 2);
+
+    let after = json(app.book_index_query("sort=title").await).await;
+    for (before, after) in before
+        .as_array()
+        .unwrap()
+        .iter()
+        .zip(after.as_array().unwrap())
+    {
+        assert_eq!(before["title"], after["title"]);
+        assert_eq!(before["contributors"], after["contributors"]);
+    }
+    assert_eq!(after[0]["contributors"][1]["role"], "translator");
+    assert_eq!(after[1]["contributors"][0]["role"], "editor");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn export_authors_csv_in_chunks() {
+    let app = spawn_app().await;
+    let mut csv = String::from("name,nationality\n");
+    for i in 0..1200 {
+        csv.push_str(&format!("Author {},Brazilian\n", i));
+    }
+    app.import_csv("authors", "", csv).await;
+
+    let export = app.export_csv("authors").await.text().await.unwrap();
+    assert_eq!(export.lines().count(), 1201);
+    assert_eq!(export.lines().next(), Some("id,name,nationality"));
+
+    drop_db(app.db_name, app.db_url).await;
+}
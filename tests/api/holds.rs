@@ -0,0 +1,256 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use serde_json::Value;
+
+async fn json_body(response: reqwest::Response) -> Value {
+    response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.")
+}
+
+async fn create_user(app: &TestApp, email: &str) -> String {
+    let response = app
+        .create_user(format!(r#"{{"name":"Richard", "email":"{}"}}"#, email))
+        .await;
+    json_body(response).await["user_id"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+// Creates a book with a single copy and lends it to a patron, returning the book and loan ids
+async fn create_book_on_loan(app: &TestApp) -> (String, String) {
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    let book_response = app
+        .create_book(
+            r#"{"title":"Lord of the Rings", "author":"JRR Tolkien", "genre": "Fiction"}"#.into(),
+        )
+        .await;
+    let book_id = json_body(book_response).await["book_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    app.create_item(
+        &book_id,
+        r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"Fiction A-3"}"#.into(),
+    )
+    .await;
+    let borrower_id = create_user(app, "borrower@email.com").await;
+    let loan_response = app
+        .checkout_loan(format!(
+            r#"{{"user_id": "{}", "book_id": "{}"}}"#,
+            borrower_id, book_id
+        ))
+        .await;
+    let loan_id = json_body(loan_response).await["loan_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    (book_id, loan_id)
+}
+
+#[tokio::test]
+async fn hold_creation() {
+    let app = spawn_app().await;
+    let (book_id, _) = create_book_on_loan(&app).await;
+    let user_id = create_user(&app, "richard@email.com").await;
+
+    let response = app
+        .create_hold(&book_id, format!(r#"{{"user_id": "{}"}}"#, user_id))
+        .await;
+    let holds = json_body(app.user_holds(&user_id).await).await;
+
+    assert!(response.status().is_success());
+    assert_eq!(holds[0]["book_id"], book_id);
+    assert_eq!(holds[0]["title"], "Lord of the Rings");
+    assert_eq!(holds[0]["status"], "waiting");
+    assert_eq!(holds[0]["queue_position"], 1);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn hold_creation_with_available_copies() {
+    let app = spawn_app().await;
+    let (book_id, loan_id) = create_book_on_loan(&app).await;
+    app.return_loan(loan_id).await;
+    let user_id = create_user(&app, "richard@email.com").await;
+
+    let response = app
+        .create_hold(&book_id, format!(r#"{{"user_id": "{}"}}"#, user_id))
+        .await;
+    let record = sqlx::query!("SELECT * FROM holds")
+        .fetch_optional(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved hold.");
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert!(record.is_none());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn hold_creation_twice_for_same_book() {
+    let app = spawn_app().await;
+    let (book_id, _) = create_book_on_loan(&app).await;
+    let user_id = create_user(&app, "richard@email.com").await;
+    let body = format!(r#"{{"user_id": "{}"}}"#, user_id);
+
+    app.create_hold(&book_id, body.clone()).await;
+    let response = app.create_hold(&book_id, body).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn holds_queue_is_promoted_in_order_on_return() {
+    let app = spawn_app().await;
+    let (book_id, loan_id) = create_book_on_loan(&app).await;
+    let first_user_id = create_user(&app, "first@email.com").await;
+    let second_user_id = create_user(&app, "second@email.com").await;
+    app.create_hold(&book_id, format!(r#"{{"user_id": "{}"}}"#, first_user_id))
+        .await;
+    app.create_hold(&book_id, format!(r#"{{"user_id": "{}"}}"#, second_user_id))
+        .await;
+
+    app.return_loan(loan_id).await;
+    let holds = json_body(app.book_holds(&book_id).await).await;
+    let second_user_checkout = app
+        .checkout_loan(format!(
+            r#"{{"user_id": "{}", "book_id": "{}"}}"#,
+            second_user_id, book_id
+        ))
+        .await;
+    let first_user_checkout = app
+        .checkout_loan(format!(
+            r#"{{"user_id": "{}", "book_id": "{}"}}"#,
+            first_user_id, book_id
+        ))
+        .await;
+    let first_user_holds = json_body(app.user_holds(&first_user_id).await).await;
+
+    assert_eq!(holds[0]["user_id"], first_user_id);
+    assert_eq!(holds[0]["status"], "ready");
+    assert!(holds[0]["expires_at"].is_string());
+    assert_eq!(holds[1]["user_id"], second_user_id);
+    assert_eq!(holds[1]["status"], "waiting");
+    assert_eq!(holds[1]["queue_position"], 1);
+    assert_eq!(second_user_checkout.status().as_u16(), 409);
+    assert!(first_user_checkout.status().is_success());
+    assert_eq!(first_user_holds[0]["status"], "fulfilled");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn hold_cancellation_promotes_next_patron() {
+    let app = spawn_app().await;
+    let (book_id, loan_id) = create_book_on_loan(&app).await;
+    let first_user_id = create_user(&app, "first@email.com").await;
+    let second_user_id = create_user(&app, "second@email.com").await;
+    let hold_response = app
+        .create_hold(&book_id, format!(r#"{{"user_id": "{}"}}"#, first_user_id))
+        .await;
+    let hold_id = json_body(hold_response).await["hold_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    app.create_hold(&book_id, format!(r#"{{"user_id": "{}"}}"#, second_user_id))
+        .await;
+    app.return_loan(loan_id).await;
+
+    let response = app.cancel_hold(&first_user_id, &hold_id).await;
+    let second_response = app.cancel_hold(&first_user_id, &hold_id).await;
+    let holds = json_body(app.book_holds(&book_id).await).await;
+
+    assert!(response.status().is_success());
+    assert_eq!(second_response.status().as_u16(), 404);
+    assert_eq!(holds.as_array().unwrap().len(), 1);
+    assert_eq!(holds[0]["user_id"], second_user_id);
+    assert_eq!(holds[0]["status"], "ready");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn expired_hold_releases_copy() {
+    let app = spawn_app().await;
+    let (book_id, loan_id) = create_book_on_loan(&app).await;
+    let user_id = create_user(&app, "richard@email.com").await;
+    app.create_hold(&book_id, format!(r#"{{"user_id": "{}"}}"#, user_id))
+        .await;
+    app.return_loan(loan_id).await;
+    sqlx::query!("UPDATE holds SET expires_at = now() - interval '1 day'")
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to update saved hold.");
+
+    let holds = json_body(app.book_holds(&book_id).await).await;
+    let user_holds = json_body(app.user_holds(&user_id).await).await;
+    let item = sqlx::query!("SELECT status FROM items")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved item.");
+
+    assert!(holds.as_array().unwrap().is_empty());
+    assert_eq!(user_holds[0]["status"], "expired");
+    assert_eq!(item.status, "available");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn new_and_repaired_copies_go_to_waiting_patrons_first() {
+    let app = spawn_app().await;
+    let (book_id, _) = create_book_on_loan(&app).await;
+    let first_user_id = create_user(&app, "first@email.com").await;
+    let second_user_id = create_user(&app, "second@email.com").await;
+    let walk_in_id = create_user(&app, "walk.in@email.com").await;
+    for user_id in [&first_user_id, &second_user_id] {
+        app.create_hold(&book_id, format!(r#"{{"user_id": "{}"}}"#, user_id))
+            .await;
+    }
+
+    app.create_item(
+        &book_id,
+        r#"{"barcode":"ML-0002", "condition":"New", "shelf_location":"Fiction A-3"}"#.into(),
+    )
+    .await;
+    let repaired_response = app
+        .create_item(
+            &book_id,
+            r#"{"barcode":"ML-0003", "condition":"Worn", "shelf_location":"Fiction A-3", "status":"in_repair"}"#.into(),
+        )
+        .await;
+    let repaired_id = json_body(repaired_response).await["item_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    app.update_item(
+        &book_id,
+        &repaired_id,
+        r#"{"barcode":"ML-0003", "condition":"Good", "shelf_location":"Fiction A-3", "status":"available"}"#.into(),
+    )
+    .await;
+    let walk_in_response = app
+        .checkout_loan(format!(
+            r#"{{"user_id": "{}", "book_id": "{}"}}"#,
+            walk_in_id, book_id
+        ))
+        .await;
+
+    let holds = json_body(app.book_holds(&book_id).await).await;
+    assert_eq!(holds[0]["user_id"], first_user_id);
+    assert_eq!(holds[0]["status"], "ready");
+    assert_eq!(holds[1]["user_id"], second_user_id);
+    assert_eq!(holds[1]["status"], "ready");
+    assert_eq!(holds[1]["item_id"], repaired_id);
+    assert_eq!(walk_in_response.status().as_u16(), 409);
+
+    drop_db(app.db_name, app.db_url).await;
+}
@@ -0,0 +1,245 @@
+use crate::test_helpers::{drop_db, spawn_app, spawn_app_with, TestApp, METADATA_FIXTURE};
+use actix_web::{web, App, HttpResponse, HttpServer};
+use midnight_library::configuration::MetadataConfig;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::TcpListener;
+
+// Serves the fixture the way Open Library's books API would
+fn spawn_open_library() -> String {
+    let listener = TcpListener::bind("localhost:0").expect("Failed to bind random port");
+    let address = listener.local_addr().unwrap().to_string();
+    let fixture: Value =
+        serde_json::from_str(&std::fs::read_to_string(METADATA_FIXTURE).unwrap()).unwrap();
+
+    let server = HttpServer::new(move || {
+        let fixture = fixture.clone();
+        App::new().route(
+            "/api/books",
+            web::get().to(move |query: web::Query<HashMap<String, String>>| {
+                let bibkey = query.get("bibkeys").cloned().unwrap_or_default();
+                let mut body = serde_json::Map::new();
+                if let Some(record) = fixture.get(&bibkey) {
+                    body.insert(bibkey, record.clone());
+                }
+                async move { HttpResponse::Ok().json(body) }
+            }),
+        )
+    })
+    .listen(listener)
+    .unwrap()
+    .run();
+    tokio::spawn(server);
+
+    format!("http://{}", address)
+}
+
+// Serves the fixture pages the way Gutendex does, each linking to the next
+fn spawn_gutendex() -> String {
+    let listener = TcpListener::bind("localhost:0").expect("Failed to bind random port");
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let pages: Vec<Value> =
+        serde_json::from_str(&std::fs::read_to_string("tests/fixtures/gutendex.json").unwrap())
+            .unwrap();
+
+    let base_url = address.clone();
+    let server = HttpServer::new(move || {
+        let pages = pages.clone();
+        let base_url = base_url.clone();
+        App::new().route(
+            "/books/",
+            web::get().to(move |query: web::Query<HashMap<String, String>>| {
+                let page: usize = query.get("page").and_then(|p| p.parse().ok()).unwrap_or(1);
+                let next =
+                    (page < pages.len()).then(|| format!("{}/books/?page={}", base_url, page + 1));
+                let body = json!({
+                    "count": pages.iter().map(|page| page.as_array().unwrap().len()).sum::<usize>(),
+                    "next": next,
+                    "results": pages.get(page - 1).cloned().unwrap_or(json!([]))
+                });
+                async move { HttpResponse::Ok().json(body) }
+            }),
+        )
+    })
+    .listen(listener)
+    .unwrap()
+    .run();
+    tokio::spawn(server);
+
+    address
+}
+
+// Queues the import and hands back the job once the workers are done with it
+async fn import_gutendex(app: &TestApp) -> Value {
+    let response = app.import_gutendex().await;
+    assert_eq!(response.status().as_u16(), 202);
+    let body = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    app.wait_for_job(body["job_id"].as_str().unwrap()).await
+}
+
+#[tokio::test]
+async fn import_gutendex_catalog() {
+    let base_url = spawn_gutendex();
+    let app = spawn_app_with(|config| config.gutendex.base_url = base_url).await;
+
+    let job = import_gutendex(&app).await;
+    assert_eq!(job["status"], "succeeded");
+    let summary = &job["result"];
+    assert_eq!(summary["pages"], 2);
+    assert_eq!(summary["created"], 2);
+    assert_eq!(summary["skipped"], 1);
+    assert_eq!(summary["failed"], 1);
+    assert_eq!(summary["failures"][0]["gutenberg_id"], 10);
+
+    let books = app
+        .book_index_query("sort=title")
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(books[0]["title"], "Moby Dick; Or, The Whale");
+    assert_eq!(books[0]["author"], "Herman Melville");
+    assert_eq!(books[0]["genre"], "Whaling");
+    assert_eq!(books[1]["title"], "The Odyssey");
+    assert_eq!(books[1]["contributors"][1]["name"], "Samuel Butler");
+    assert_eq!(books[1]["contributors"][1]["role"], "translator");
+
+    let authors = app
+        .author_index()
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(authors.as_array().unwrap().len(), 3);
+    assert_eq!(authors[0]["nationality"], "Unknown");
+
+    // Running it again only skips what is already there
+    let summary = &import_gutendex(&app).await["result"];
+    assert_eq!(summary["created"], 0);
+    assert_eq!(summary["skipped"], 3);
+    assert_eq!(summary["failed"], 1);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn import_gutendex_stops_at_max_pages() {
+    let base_url = spawn_gutendex();
+    let app = spawn_app_with(|config| {
+        config.gutendex.base_url = base_url;
+        config.gutendex.max_pages = 1;
+    })
+    .await;
+
+    let summary = &import_gutendex(&app).await["result"];
+    assert_eq!(summary["pages"], 1);
+    assert_eq!(summary["created"], 2);
+    assert_eq!(summary["failed"], 0);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn import_isbn_creates_book_and_authors() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"Homer", "nationality":"Greek"}"#.into())
+        .await;
+
+    let response = app
+        .import_isbn(r#"{"isbn":"0-14-044913-2", "genre":"Epic"}"#.into())
+        .await;
+    assert!(response.status().is_success());
+    let body = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(body["metadata"]["title"], "The Odyssey");
+
+    let book = app
+        .show_book(body["book_id"].as_str().unwrap().into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(book["title"], "The Odyssey");
+    assert_eq!(book["genre"], "Epic");
+    assert_eq!(book["isbn_13"], "9780140449136");
+    assert_eq!(book["publisher"], "Penguin Books");
+    assert_eq!(book["publication_year"], 2003);
+    assert_eq!(book["page_count"], 416);
+    assert_eq!(book["contributors"][0]["name"], "Homer");
+    assert_eq!(book["contributors"][1]["name"], "E. V. Rieu");
+
+    // Homer already existed, only the translator was added
+    let authors = app
+        .author_index()
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(authors.as_array().unwrap().len(), 2);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn import_isbn_twice_conflicts() {
+    let app = spawn_app().await;
+
+    let response = app.import_isbn(r#"{"isbn":"9780142437247"}"#.into()).await;
+    assert!(response.status().is_success());
+    let book_id = response.json::<Value>().await.unwrap()["book_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let book = app
+        .show_book(book_id)
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(book["genre"], "Whaling");
+
+    let response = app.import_isbn(r#"{"isbn":"9780142437247"}"#.into()).await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn import_unknown_isbn() {
+    let app = spawn_app().await;
+
+    let response = app.import_isbn(r#"{"isbn":"9780306406157"}"#.into()).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.import_isbn(r#"{"isbn":"9780306406158"}"#.into()).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn import_isbn_from_open_library() {
+    let base_url = spawn_open_library();
+    let app =
+        spawn_app_with(|config| config.metadata = MetadataConfig::OpenLibrary { base_url }).await;
+
+    let response = app.import_isbn(r#"{"isbn":"9780142437247"}"#.into()).await;
+    assert!(response.status().is_success());
+    let body = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(body["metadata"]["title"], "Moby-Dick, or, The Whale");
+    assert_eq!(body["metadata"]["authors"][0], "Herman Melville");
+
+    let response = app.import_isbn(r#"{"isbn":"9780306406157"}"#.into()).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    drop_db(app.db_name, app.db_url).await;
+}
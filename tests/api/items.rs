@@ -0,0 +1,272 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use midnight_library::auth::Role;
+use serde_json::Value;
+
+async fn create_book(app: &TestApp) -> String {
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    let response_body = app
+        .create_book(
+            r#"{"title":"Lord of the Rings", "author":"JRR Tolkien", "genre": "Fiction"}"#.into(),
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    response_body["book_id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn item_creation() {
+    let app = spawn_app().await;
+    let book_id = create_book(&app).await;
+
+    let response = app
+        .create_item(
+            &book_id,
+            r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"Fiction A-3"}"#.into(),
+        )
+        .await;
+    let record = sqlx::query!("SELECT * FROM items")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved item.");
+
+    assert!(response.status().is_success());
+    assert_eq!(record.book_id.to_string(), book_id);
+    assert_eq!(record.barcode, "ML-0001");
+    assert_eq!(record.condition, "New");
+    assert_eq!(record.shelf_location, "Fiction A-3");
+    assert_eq!(record.status, "available");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn item_creation_with_duplicate_barcode() {
+    let app = spawn_app().await;
+    let book_id = create_book(&app).await;
+    let body = r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"Fiction A-3"}"#;
+
+    app.create_item(&book_id, body.into()).await;
+    let response = app.create_item(&book_id, body.into()).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn item_creation_with_invalid_status() {
+    let app = spawn_app().await;
+    let book_id = create_book(&app).await;
+
+    let response = app
+        .create_item(
+            &book_id,
+            r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"A-3", "status":"on_loan"}"#
+                .into(),
+        )
+        .await;
+    let record = sqlx::query!("SELECT * FROM items")
+        .fetch_optional(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved item.");
+
+    assert!(response.status().is_client_error());
+    assert!(record.is_none());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn items_index_and_book_copy_counts() {
+    let app = spawn_app().await;
+    let book_id = create_book(&app).await;
+    app.create_item(
+        &book_id,
+        r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"Fiction A-3"}"#.into(),
+    )
+    .await;
+    app.create_item(
+        &book_id,
+        r#"{"barcode":"ML-0002", "condition":"Torn page", "shelf_location":"Repairs", "status":"in_repair"}"#
+            .into(),
+    )
+    .await;
+
+    let items = app
+        .item_index(&book_id)
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let book = app
+        .show_book(book_id.clone())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let books = app
+        .book_index()
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(items[0]["barcode"], "ML-0001");
+    assert_eq!(items[0]["status"], "available");
+    assert_eq!(items[1]["barcode"], "ML-0002");
+    assert_eq!(items[1]["status"], "in_repair");
+    assert_eq!(book["available_copies"], 1);
+    assert_eq!(book["total_copies"], 2);
+    assert_eq!(books[0]["available_copies"], 1);
+    assert_eq!(books[0]["total_copies"], 2);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn item_update() {
+    let app = spawn_app().await;
+    let book_id = create_book(&app).await;
+    let create_response = app
+        .create_item(
+            &book_id,
+            r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"Fiction A-3"}"#.into(),
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let item_id = create_response["item_id"].as_str().unwrap();
+
+    let response = app
+        .update_item(
+            &book_id,
+            item_id,
+            r#"{"barcode":"ML-0001", "condition":"Water damage", "shelf_location":"Basement", "status":"lost"}"#
+                .into(),
+        )
+        .await;
+    let item = app
+        .show_item(&book_id, item_id)
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert!(response.status().is_success());
+    assert_eq!(item["condition"], "Water damage");
+    assert_eq!(item["shelf_location"], "Basement");
+    assert_eq!(item["status"], "lost");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn item_deletion() {
+    let app = spawn_app().await;
+    let book_id = create_book(&app).await;
+    let create_response = app
+        .create_item(
+            &book_id,
+            r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"Fiction A-3"}"#.into(),
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let item_id = create_response["item_id"].as_str().unwrap();
+
+    let response = app.delete_item(&book_id, item_id).await;
+    let record = sqlx::query!("SELECT * FROM items")
+        .fetch_optional(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved item.");
+
+    assert!(response.status().is_success());
+    assert!(record.is_none());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn copies_with_loan_history_are_kept() {
+    let app = spawn_app().await;
+    let book_id = create_book(&app).await;
+    let lent = app
+        .create_item(
+            &book_id,
+            r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"Fiction A-3"}"#.into(),
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let (user_id, _) = app.session_for(Role::Patron).await;
+    let loan = app
+        .checkout_loan(format!(
+            r#"{{"user_id": "{}", "book_id": "{}"}}"#,
+            user_id, book_id
+        ))
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    app.return_loan(loan["loan_id"].as_str().unwrap().to_string())
+        .await;
+
+    let response = app
+        .delete_item(&book_id, lent["item_id"].as_str().unwrap())
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(
+        problem["detail"],
+        "Item has loans or holds and can't be deleted"
+    );
+
+    let response = app.book_delete(&book_id, &app.staff_token).await;
+    assert_eq!(response.status().as_u16(), 409);
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(
+        problem["detail"],
+        "Book has loans or holds and can't be deleted"
+    );
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn deleting_a_book_deletes_its_unlent_copies() {
+    let app = spawn_app().await;
+    let book_id = create_book(&app).await;
+    for barcode in ["ML-0001", "ML-0002"] {
+        app.create_item(
+            &book_id,
+            format!(
+                r#"{{"barcode":"{}", "condition":"New", "shelf_location":"Fiction A-3"}}"#,
+                barcode
+            ),
+        )
+        .await;
+    }
+
+    let response = app.book_delete(&book_id, &app.staff_token).await;
+    assert!(response.status().is_success());
+    let items = sqlx::query!("SELECT id FROM items")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved items.");
+    assert!(items.is_empty());
+
+    drop_db(app.db_name, app.db_url).await;
+}
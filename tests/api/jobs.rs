@@ -0,0 +1,118 @@
+use crate::test_helpers::{drop_db, spawn_app, spawn_app_with, TestApp};
+use serde_json::Value;
+
+async fn queue_import(app: &TestApp) -> String {
+    let response = app.import_gutendex().await;
+    assert_eq!(response.status().as_u16(), 202);
+    assert!(response.headers().contains_key("Location"));
+    response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.")["job_id"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn failing_job_is_retried_then_failed() {
+    let app = spawn_app_with(|config| {
+        config.gutendex.base_url = "http://localhost:1".into();
+        config.jobs.max_attempts = 2;
+    })
+    .await;
+
+    let job_id = queue_import(&app).await;
+    let job = app.wait_for_job(&job_id).await;
+    assert_eq!(job["status"], "failed");
+    assert_eq!(job["kind"], "gutendex_import");
+    assert_eq!(job["attempts"], 2);
+    assert!(job["last_error"].is_string());
+    assert!(job["finished_at"].is_string());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn cancel_queued_job() {
+    // Without workers the job stays queued until cancelled
+    let app = spawn_app_with(|config| config.jobs.workers = 0).await;
+
+    let job_id = queue_import(&app).await;
+    let job = app
+        .show_job(&job_id)
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(job["status"], "queued");
+    assert_eq!(job["attempts"], 0);
+
+    let response = app.cancel_job(&job_id).await;
+    assert!(response.status().is_success());
+    let job = app.wait_for_job(&job_id).await;
+    assert_eq!(job["status"], "cancelled");
+
+    let response = app.cancel_job(&job_id).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn show_unknown_job() {
+    let app = spawn_app().await;
+
+    let response = app.show_job("123").await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.cancel_job(&uuid::Uuid::new_v4().to_string()).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn jobs_of_dead_workers_are_taken_back() {
+    let app = spawn_app_with(|config| {
+        config.gutendex.base_url = "http://localhost:1".into();
+        config.jobs.max_attempts = 2;
+        config.jobs.lease_timeout_ms = 300;
+    })
+    .await;
+
+    // As a worker that crashed mid-run would leave them: one attempt left, and none
+    let mut job_ids = Vec::new();
+    for attempts in [1, 2] {
+        let job_id = sqlx::query_scalar!(
+            "INSERT INTO jobs (kind, status, attempts, max_attempts, run_at, created_at,
+                started_at, locked_at)
+            VALUES ('gutendex_import', 'running', $1, 2, now(), now(), now(),
+                now() - interval '1 minute')
+            RETURNING id",
+            attempts
+        )
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to save job.");
+        job_ids.push(job_id.to_string());
+    }
+
+    let retried = app.wait_for_job(&job_ids[0]).await;
+    assert_eq!(retried["status"], "failed");
+    assert_eq!(retried["attempts"], 2);
+    assert_ne!(
+        retried["last_error"],
+        "The worker running this job stopped before it finished"
+    );
+
+    let spent = app.wait_for_job(&job_ids[1]).await;
+    assert_eq!(spent["status"], "failed");
+    assert_eq!(spent["attempts"], 2);
+    assert_eq!(
+        spent["last_error"],
+        "The worker running this job stopped before it finished"
+    );
+
+    drop_db(app.db_name, app.db_url).await;
+}
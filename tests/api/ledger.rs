@@ -0,0 +1,206 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use serde_json::Value;
+
+async fn json_body(response: reqwest::Response) -> Value {
+    response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.")
+}
+
+async fn create_book_with_copy(app: &TestApp, title: &str, barcode: &str) -> String {
+    let book_response = app
+        .create_book(format!(
+            r#"{{"title":"{}", "author":"JRR Tolkien", "genre": "Fiction"}}"#,
+            title
+        ))
+        .await;
+    let book_id = json_body(book_response).await["book_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    app.create_item(
+        &book_id,
+        format!(
+            r#"{{"barcode":"{}", "condition":"New", "shelf_location":"Fiction A-3"}}"#,
+            barcode
+        ),
+    )
+    .await;
+
+    book_id
+}
+
+// Lends a book to a new patron and moves its due date the given number of days into the past
+async fn create_overdue_loan(app: &TestApp, days_overdue: i32) -> (String, String) {
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    let book_id = create_book_with_copy(app, "Lord of the Rings", "ML-0001").await;
+    let user_response = app
+        .create_user(r#"{"name":"Richard", "email":"richard@email.com"}"#.into())
+        .await;
+    let user_id = json_body(user_response).await["user_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let loan_response = app
+        .checkout_loan(format!(
+            r#"{{"user_id": "{}", "book_id": "{}"}}"#,
+            user_id, book_id
+        ))
+        .await;
+    let loan_id = json_body(loan_response).await["loan_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    sqlx::query!(
+        "UPDATE loans SET due_at = now() - make_interval(days => $1, mins => 1)",
+        days_overdue
+    )
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to update saved loan.");
+
+    (user_id, loan_id)
+}
+
+#[tokio::test]
+async fn balance_accrues_daily_fines() {
+    let app = spawn_app().await;
+    let (user_id, _) = create_overdue_loan(&app, 3).await;
+
+    let response = app.show_balance(&user_id).await;
+    let first_balance = json_body(response).await;
+    let second_balance = json_body(app.show_balance(&user_id).await).await;
+
+    assert_eq!(first_balance["balance_cents"], 75);
+    assert_eq!(first_balance["entries"][0]["kind"], "fine");
+    assert_eq!(first_balance["entries"][0]["amount_cents"], 75);
+    assert_eq!(second_balance["balance_cents"], 75);
+    assert_eq!(second_balance["entries"].as_array().unwrap().len(), 1);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn balance_of_unknown_user() {
+    let app = spawn_app().await;
+
+    let response = app
+        .show_balance("b1d1b7e0-7d0e-4c2a-9d6a-3f0b1f3f8c1a")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 404);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn fines_stop_accruing_after_return() {
+    let app = spawn_app().await;
+    let (user_id, loan_id) = create_overdue_loan(&app, 2).await;
+
+    app.return_loan(loan_id).await;
+    // Five more days go by after the book was returned
+    sqlx::query!(
+        "UPDATE loans
+        SET due_at = due_at - interval '5 days', returned_at = returned_at - interval '5 days'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to update saved loan.");
+    let balance = json_body(app.show_balance(&user_id).await).await;
+
+    assert_eq!(balance["balance_cents"], 50);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn renewal_does_not_write_off_fines() {
+    let app = spawn_app().await;
+    let (user_id, loan_id) = create_overdue_loan(&app, 3).await;
+
+    let response = app.renew_loan(loan_id).await;
+    let balance = json_body(app.show_balance(&user_id).await).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(balance["balance_cents"], 75);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn payment_and_waiver_reduce_balance() {
+    let app = spawn_app().await;
+    let (user_id, _) = create_overdue_loan(&app, 4).await;
+
+    let payment_response = app
+        .create_payment(&user_id, r#"{"amount_cents": 60}"#.into())
+        .await;
+    let waiver_response = app
+        .create_waiver(
+            &user_id,
+            r#"{"amount_cents": 40, "note": "Book drop was broken"}"#.into(),
+        )
+        .await;
+    let balance = json_body(app.show_balance(&user_id).await).await;
+
+    assert!(payment_response.status().is_success());
+    assert!(waiver_response.status().is_success());
+    assert_eq!(balance["balance_cents"], 0);
+    assert_eq!(balance["entries"][1]["kind"], "payment");
+    assert_eq!(balance["entries"][1]["amount_cents"], -60);
+    assert_eq!(balance["entries"][2]["kind"], "waiver");
+    assert_eq!(balance["entries"][2]["note"], "Book drop was broken");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn payment_over_balance() {
+    let app = spawn_app().await;
+    let (user_id, _) = create_overdue_loan(&app, 1).await;
+
+    let response = app
+        .create_payment(&user_id, r#"{"amount_cents": 1000}"#.into())
+        .await;
+    let balance = json_body(app.show_balance(&user_id).await).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(balance["balance_cents"], 25);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn payment_with_invalid_amount() {
+    let app = spawn_app().await;
+    let (user_id, _) = create_overdue_loan(&app, 1).await;
+
+    let response = app
+        .create_payment(&user_id, r#"{"amount_cents": -25}"#.into())
+        .await;
+
+    assert!(response.status().is_client_error());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn checkout_blocked_over_balance_threshold() {
+    let app = spawn_app().await;
+    let (user_id, _) = create_overdue_loan(&app, 30).await;
+    let book_id = create_book_with_copy(&app, "The Hobbit", "ML-0002").await;
+    let body = format!(r#"{{"user_id": "{}", "book_id": "{}"}}"#, user_id, book_id);
+
+    let blocked_response = app.checkout_loan(body.clone()).await;
+    app.create_payment(&user_id, r#"{"amount_cents": 300}"#.into())
+        .await;
+    let allowed_response = app.checkout_loan(body).await;
+
+    assert_eq!(blocked_response.status().as_u16(), 409);
+    assert!(allowed_response.status().is_success());
+
+    drop_db(app.db_name, app.db_url).await;
+}
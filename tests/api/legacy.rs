@@ -0,0 +1,65 @@
+use crate::test_helpers::{drop_db, spawn_app};
+use serde_json::Value;
+
+#[tokio::test]
+async fn legacy_paths_are_deprecated() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("http://{}/books", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    assert_eq!(response.headers()["Deprecation"], "true");
+    assert_eq!(
+        response.headers()["Link"],
+        r#"</api/v1>; rel="successor-version""#
+    );
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn versioned_paths_are_not_deprecated() {
+    let app = spawn_app().await;
+
+    let response = app.book_index().await;
+
+    assert!(response.status().is_success());
+    assert!(response.headers().get("Deprecation").is_none());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn legacy_delete_with_id_in_body() {
+    let app = spawn_app().await;
+    let response_body = app
+        .create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let author_id = response_body["author_id"].as_str().unwrap();
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/authors/delete", &app.address))
+        .bearer_auth(&app.staff_token)
+        .header("Content-Type", "application/json")
+        .body(format!(r#"{{"id": "{}"}}"#, author_id))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let record = sqlx::query!("SELECT * FROM authors")
+        .fetch_optional(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved author.");
+
+    assert!(response.status().is_success());
+    assert_eq!(response.headers()["Deprecation"], "true");
+    assert!(record.is_none());
+
+    drop_db(app.db_name, app.db_url).await;
+}
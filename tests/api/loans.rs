@@ -0,0 +1,263 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use serde_json::Value;
+
+async fn create_book_and_user(app: &TestApp, email: &str) -> (String, String) {
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    let book_response = app
+        .create_book(
+            r#"{"title":"Lord of the Rings", "author":"JRR Tolkien", "genre": "Fiction"}"#.into(),
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let book_id = book_response["book_id"].as_str().unwrap().to_string();
+    app.create_item(
+        &book_id,
+        r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"Fiction A-3"}"#.into(),
+    )
+    .await;
+    let user_response = app
+        .create_user(format!(r#"{{"name":"Richard", "email":"{}"}}"#, email))
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    (
+        book_id,
+        user_response["user_id"].as_str().unwrap().to_string(),
+    )
+}
+
+#[tokio::test]
+async fn loan_checkout() {
+    let app = spawn_app().await;
+    let (book_id, user_id) = create_book_and_user(&app, "richard@email.com").await;
+
+    let response = app
+        .checkout_loan(format!(
+            r#"{{"user_id": "{}", "book_id": "{}"}}"#,
+            user_id, book_id
+        ))
+        .await;
+    let record = sqlx::query!("SELECT * FROM loans")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved loan.");
+
+    assert!(response.status().is_success());
+    assert_eq!(record.book_id.to_string(), book_id);
+    assert_eq!(record.user_id.to_string(), user_id);
+    assert_eq!((record.due_at - record.checked_out_at).num_days(), 14);
+    assert!(record.returned_at.is_none());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn loan_checkout_of_book_already_on_loan() {
+    let app = spawn_app().await;
+    let (book_id, user_id) = create_book_and_user(&app, "richard@email.com").await;
+    let body = format!(r#"{{"user_id": "{}", "book_id": "{}"}}"#, user_id, book_id);
+
+    app.checkout_loan(body.clone()).await;
+    let response = app.checkout_loan(body).await;
+    let loans_count = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM loans"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count saved loans.");
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(loans_count.count, 1);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn loan_checkout_of_book_with_several_copies() {
+    let app = spawn_app().await;
+    let (book_id, user_id) = create_book_and_user(&app, "richard@email.com").await;
+    app.create_item(
+        &book_id,
+        r#"{"barcode":"ML-0002", "condition":"New", "shelf_location":"Fiction A-3"}"#.into(),
+    )
+    .await;
+    let body = format!(r#"{{"user_id": "{}", "book_id": "{}"}}"#, user_id, book_id);
+
+    let first_response = app.checkout_loan(body.clone()).await;
+    let second_response = app.checkout_loan(body.clone()).await;
+    let third_response = app.checkout_loan(body).await;
+    let items = sqlx::query!("SELECT status FROM items")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved items.");
+
+    assert!(first_response.status().is_success());
+    assert!(second_response.status().is_success());
+    assert_eq!(third_response.status().as_u16(), 409);
+    assert!(items.iter().all(|item| item.status == "on_loan"));
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn loan_checkout_over_active_loan_limit() {
+    let app = spawn_app().await;
+    let (_, user_id) = create_book_and_user(&app, "richard@email.com").await;
+
+    let mut statuses = Vec::new();
+    for i in 0..6 {
+        let book_response = app
+            .create_book(format!(
+                r#"{{"title":"Book {}", "author":"JRR Tolkien", "genre": "Fiction"}}"#,
+                i
+            ))
+            .await
+            .json::<Value>()
+            .await
+            .expect("Failed to deserialize response body.");
+        let book_id = book_response["book_id"].as_str().unwrap();
+        app.create_item(
+            book_id,
+            format!(
+                r#"{{"barcode":"ML-1{}", "condition":"New", "shelf_location":"A-3"}}"#,
+                i
+            ),
+        )
+        .await;
+        let response = app
+            .checkout_loan(format!(
+                r#"{{"user_id": "{}", "book_id": "{}"}}"#,
+                user_id, book_id
+            ))
+            .await;
+        statuses.push(response.status().as_u16());
+    }
+
+    assert_eq!(statuses, vec![200, 200, 200, 200, 200, 409]);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn concurrent_checkouts_respect_active_loan_limit() {
+    let app = spawn_app().await;
+    let (book_id, user_id) = create_book_and_user(&app, "richard@email.com").await;
+    for i in 2..=10 {
+        app.create_item(
+            &book_id,
+            format!(
+                r#"{{"barcode":"ML-{:04}", "condition":"New", "shelf_location":"A-3"}}"#,
+                i
+            ),
+        )
+        .await;
+    }
+
+    let loan = format!(r#"{{"user_id": "{}", "book_id": "{}"}}"#, user_id, book_id);
+    let responses =
+        futures_util::future::join_all((0..10).map(|_| app.checkout_loan(loan.clone()))).await;
+    let checked_out = responses
+        .iter()
+        .filter(|response| response.status().is_success())
+        .count();
+    let active_loans =
+        sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM loans WHERE returned_at IS NULL"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to count loans.");
+
+    assert_eq!(checked_out, 5);
+    assert_eq!(active_loans, 5);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn loan_checkout_with_invalid_data() {
+    let app = spawn_app().await;
+    let (_, user_id) = create_book_and_user(&app, "richard@email.com").await;
+
+    let response = app
+        .checkout_loan(format!(
+            r#"{{"user_id": "{}", "book_id": "Moby Dick"}}"#,
+            user_id
+        ))
+        .await;
+    let record = sqlx::query!("SELECT * FROM loans")
+        .fetch_optional(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved loan.");
+
+    assert!(response.status().is_client_error());
+    assert!(record.is_none());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn loan_return() {
+    let app = spawn_app().await;
+    let (book_id, user_id) = create_book_and_user(&app, "richard@email.com").await;
+    let body = format!(r#"{{"user_id": "{}", "book_id": "{}"}}"#, user_id, book_id);
+    let checkout_response = app
+        .checkout_loan(body.clone())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let loan_id = checkout_response["loan_id"].as_str().unwrap();
+
+    let response = app.return_loan(loan_id.into()).await;
+    let second_response = app.return_loan(loan_id.into()).await;
+    let record = sqlx::query!("SELECT * FROM loans")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved loan.");
+
+    assert!(response.status().is_success());
+    assert_eq!(second_response.status().as_u16(), 404);
+    assert!(record.returned_at.is_some());
+    let item = sqlx::query!("SELECT status FROM items WHERE id = $1", record.item_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved item.");
+    assert_eq!(item.status, "available");
+    assert!(app.checkout_loan(body).await.status().is_success());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn loan_renewal() {
+    let app = spawn_app().await;
+    let (book_id, user_id) = create_book_and_user(&app, "richard@email.com").await;
+    let checkout_response = app
+        .checkout_loan(format!(
+            r#"{{"user_id": "{}", "book_id": "{}"}}"#,
+            user_id, book_id
+        ))
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let loan_id = checkout_response["loan_id"].as_str().unwrap();
+
+    let first_renewal = app.renew_loan(loan_id.into()).await;
+    let second_renewal = app.renew_loan(loan_id.into()).await;
+    let third_renewal = app.renew_loan(loan_id.into()).await;
+    let record = sqlx::query!("SELECT * FROM loans")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved loan.");
+
+    assert!(first_renewal.status().is_success());
+    assert!(second_renewal.status().is_success());
+    assert_eq!(third_renewal.status().as_u16(), 409);
+    assert_eq!(record.renewals, 2);
+    assert_eq!((record.due_at - record.checked_out_at).num_days(), 42);
+
+    drop_db(app.db_name, app.db_url).await;
+}
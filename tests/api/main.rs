@@ -1,4 +1,19 @@
+pub mod accounts;
+pub mod api_keys;
 pub mod authors;
 pub mod books;
+pub mod bulk;
+pub mod holds;
+pub mod imports;
+pub mod items;
+pub mod jobs;
+pub mod ledger;
+pub mod legacy;
+pub mod loans;
+pub mod marc;
+pub mod opds;
+pub mod roles;
+pub mod search;
+pub mod sessions;
 pub mod test_helpers;
 pub mod users;
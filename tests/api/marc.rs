@@ -0,0 +1,208 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use midnight_library::marc::{read_iso2709, read_marcxml, write_iso2709, Record};
+use serde_json::Value;
+
+const SAMPLE_MRC: &str = "tests/fixtures/sample.mrc";
+const SAMPLE_MARCXML: &str = "tests/fixtures/sample_marcxml.xml";
+
+async fn json(response: reqwest::Response) -> Value {
+    response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.")
+}
+
+async fn books_by_title(app: &TestApp) -> Vec<Value> {
+    let books = json(app.book_index_query("sort=title").await).await;
+    books.as_array().unwrap().clone()
+}
+
+// What a book looks like once the ids and timestamps of the library holding it are left out
+fn catalog_entry(book: &Value) -> Value {
+    let mut book = book.clone();
+    let fields = book.as_object_mut().unwrap();
+    fields.remove("id");
+    fields.remove("created_at");
+    for contributor in fields["contributors"].as_array_mut().unwrap() {
+        contributor.as_object_mut().unwrap().remove("author_id");
+    }
+    book
+}
+
+#[tokio::test]
+async fn import_marc_file_creates_books_and_authors() {
+    let app = spawn_app().await;
+
+    let response = app
+        .import_marc("", std::fs::read(SAMPLE_MRC).unwrap())
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(json(response).await["imported"], 2);
+
+    let books = books_by_title(&app).await;
+    assert_eq!(books[0]["title"], "Moby-Dick, or, The whale");
+    assert_eq!(books[0]["isbn_13"], "9780142437247");
+    assert_eq!(books[0]["publisher"], "Penguin Books");
+    assert_eq!(books[0]["publication_year"], 2003);
+    assert_eq!(books[0]["genre"], "Whaling");
+    // The writer of the introduction has no role in the catalog
+    let contributors = books[0]["contributors"].as_array().unwrap();
+    assert_eq!(contributors.len(), 2);
+    assert_eq!(contributors[0]["name"], "Herman Melville");
+    assert_eq!(contributors[1]["name"], "Harold Beaver");
+    assert_eq!(contributors[1]["role"], "editor");
+
+    assert_eq!(books[1]["title"], "The hobbit, or, There and back again");
+    assert_eq!(books[1]["isbn_13"], "9780395071229");
+    assert_eq!(books[1]["publisher"], "Houghton Mifflin");
+    assert_eq!(books[1]["publication_year"], 1966);
+    assert_eq!(books[1]["contributors"][0]["name"], "J. R. R. Tolkien");
+
+    let authors = json(app.author_index().await).await;
+    assert_eq!(authors.as_array().unwrap().len(), 3);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn import_marcxml_file() {
+    let app = spawn_app().await;
+
+    let body = std::fs::read(SAMPLE_MARCXML).unwrap();
+    assert_eq!(json(app.import_marc("", body).await).await["imported"], 1);
+
+    let books = books_by_title(&app).await;
+    assert_eq!(books[0]["title"], "The Odyssey");
+    assert_eq!(books[0]["genre"], "Epic poetry, Greek");
+    assert_eq!(books[0]["publication_year"], 1996);
+    assert_eq!(books[0]["contributors"][0]["name"], "Homer");
+    assert_eq!(books[0]["contributors"][1]["name"], "Robert Fagles");
+    assert_eq!(books[0]["contributors"][1]["role"], "translator");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn marc_export_round_trips() {
+    let app = spawn_app().await;
+    let other_app = spawn_app().await;
+    app.import_marc("", std::fs::read(SAMPLE_MRC).unwrap())
+        .await;
+    let moby_dick = books_by_title(&app).await[0].clone();
+
+    let response = app.export_marc(moby_dick["id"].as_str().unwrap(), "").await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "application/marc");
+    let exported = response.bytes().await.unwrap().to_vec();
+
+    let records = read_iso2709(&exported).unwrap();
+    assert_eq!(records[0].first("100", 'a'), Some("Melville, Herman"));
+    assert_eq!(
+        records[0].first("245", 'a'),
+        Some("Moby-Dick, or, The whale")
+    );
+    assert_eq!(records[0].first("700", 'e'), Some("editor"));
+
+    let response = other_app.import_marc("", exported).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let imported = books_by_title(&other_app).await[0].clone();
+    assert_eq!(catalog_entry(&imported), catalog_entry(&moby_dick));
+
+    drop_db(app.db_name, app.db_url).await;
+    drop_db(other_app.db_name, other_app.db_url).await;
+}
+
+#[tokio::test]
+async fn marcxml_export_round_trips() {
+    let app = spawn_app().await;
+    let other_app = spawn_app().await;
+    app.import_marc("", std::fs::read(SAMPLE_MARCXML).unwrap())
+        .await;
+    let odyssey = books_by_title(&app).await[0].clone();
+
+    let response = app
+        .export_marc(odyssey["id"].as_str().unwrap(), "format=marcxml")
+        .await;
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/marcxml+xml"
+    );
+    let exported = response.text().await.unwrap();
+
+    let records = read_marcxml(&exported).unwrap();
+    assert_eq!(records[0].first("100", 'a'), Some("Homer"));
+    assert_eq!(records[0].first("700", 'a'), Some("Fagles, Robert"));
+
+    other_app.import_marc("", exported.into_bytes()).await;
+    let imported = books_by_title(&other_app).await[0].clone();
+    assert_eq!(catalog_entry(&imported), catalog_entry(&odyssey));
+
+    drop_db(app.db_name, app.db_url).await;
+    drop_db(other_app.db_name, other_app.db_url).await;
+}
+
+#[tokio::test]
+async fn import_marc_dry_run_and_rejected_records() {
+    let app = spawn_app().await;
+
+    let body = json(
+        app.import_marc("dry_run=true", std::fs::read(SAMPLE_MRC).unwrap())
+            .await,
+    )
+    .await;
+    assert_eq!(body["dry_run"], true);
+    assert_eq!(body["imported"], 0);
+
+    // A record without a title statement spoils the whole file
+    let mut upload = std::fs::read(SAMPLE_MRC).unwrap();
+    upload.extend(write_iso2709(&Record::default()).unwrap());
+    let response = app.import_marc("", upload).await;
+    assert_eq!(response.status().as_u16(), 422);
+    let body = json(response).await;
+    assert_eq!(body["code"], "import_rejected");
+    assert_eq!(body["rows"][0]["row"], 3);
+    assert_eq!(body["rows"][0]["errors"][0]["field"], "title");
+
+    assert_eq!(json(app.book_index().await).await, Value::Array(vec![]));
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn malformed_marc_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .import_marc("", b"00042nam  not really marc".to_vec())
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.import_marc("", b"<collection><record>".to_vec()).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.import_marc("", Vec::new()).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn export_marc_unknown_book_or_format() {
+    let app = spawn_app().await;
+    app.import_marc("", std::fs::read(SAMPLE_MRC).unwrap())
+        .await;
+    let book_id = books_by_title(&app).await[0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let response = app.export_marc(&book_id, "format=dublin_core").await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = app
+        .export_marc("00000000-0000-0000-0000-000000000000", "")
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    drop_db(app.db_name, app.db_url).await;
+}
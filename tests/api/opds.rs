@@ -0,0 +1,220 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+
+const ACQUISITION_FEED: &str = "application/atom+xml;profile=opds-catalog;kind=acquisition";
+const NAVIGATION_FEED: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
+
+// The parts of a feed the tests look at: its own links and, per entry, the title and links
+#[derive(Default)]
+struct Feed {
+    title: String,
+    links: Vec<HashMap<String, String>>,
+    entries: Vec<(String, Vec<HashMap<String, String>>)>,
+}
+
+impl Feed {
+    fn link(&self, rel: &str) -> Option<&HashMap<String, String>> {
+        self.links.iter().find(|link| link["rel"] == rel)
+    }
+
+    fn facets(&self, group: &str) -> Vec<&HashMap<String, String>> {
+        self.links
+            .iter()
+            .filter(|link| link.get("opds:facetGroup").map(String::as_str) == Some(group))
+            .collect()
+    }
+
+    fn entry_titles(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .map(|(title, _)| title.as_str())
+            .collect()
+    }
+}
+
+fn parse_feed(xml: &str) -> Feed {
+    let mut reader = Reader::from_str(xml);
+    let mut feed = Feed::default();
+    let mut in_entry = false;
+    let mut path = Vec::new();
+
+    loop {
+        match reader.read_event().expect("Feed is not well-formed XML.") {
+            Event::Start(element) => {
+                let name = String::from_utf8(element.name().as_ref().to_vec()).unwrap();
+                if name == "entry" {
+                    in_entry = true;
+                    feed.entries.push((String::new(), Vec::new()));
+                }
+                path.push(name);
+            }
+            Event::Empty(element) if element.name().as_ref() == b"link" => {
+                let link: HashMap<String, String> = element
+                    .attributes()
+                    .map(|attribute| {
+                        let attribute = attribute.unwrap();
+                        (
+                            String::from_utf8(attribute.key.as_ref().to_vec()).unwrap(),
+                            attribute.unescape_value().unwrap().to_string(),
+                        )
+                    })
+                    .collect();
+                match feed.entries.last_mut() {
+                    Some((_, links)) if in_entry => links.push(link),
+                    _ => feed.links.push(link),
+                }
+            }
+            Event::Text(text) if path.last().map(String::as_str) == Some("title") => {
+                let text = text.unescape().unwrap().to_string();
+                match feed.entries.last_mut() {
+                    Some((title, _)) if in_entry => *title = text,
+                    _ => feed.title = text,
+                }
+            }
+            Event::End(element) => {
+                if element.name().as_ref() == b"entry" {
+                    in_entry = false;
+                }
+                path.pop();
+            }
+            Event::Eof => return feed,
+            _ => {}
+        }
+    }
+}
+
+async fn get_feed(app: &TestApp, path: &str, kind: &str) -> Feed {
+    let response = app.opds(path).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], kind);
+    parse_feed(&response.text().await.unwrap())
+}
+
+async fn seed_catalog(app: &TestApp) {
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    app.create_author(r#"{"name":"Herman Melville", "nationality":"American"}"#.into())
+        .await;
+    for body in [
+        r#"{"title":"The Hobbit", "author":"JRR Tolkien", "genre":"Fantasy", "isbn":"9780395071229"}"#,
+        r#"{"title":"Lord of the Rings", "author":"JRR Tolkien", "genre":"Fantasy"}"#,
+        r#"{"title":"Moby Dick", "author":"Herman Melville", "genre":"Adventure"}"#,
+    ] {
+        let response = app.create_book(body.into()).await;
+        assert!(response.status().is_success());
+    }
+}
+
+#[tokio::test]
+async fn opds_root_is_a_navigation_feed() {
+    let app = spawn_app().await;
+
+    let feed = get_feed(&app, "", NAVIGATION_FEED).await;
+
+    assert_eq!(feed.title, "Midnight Library");
+    assert_eq!(feed.link("search").unwrap()["href"], "/opds/opensearch.xml");
+    assert_eq!(feed.entry_titles(), vec!["Books", "Authors", "Genres"]);
+    assert_eq!(feed.entries[0].1[0]["href"], "/opds/books?sort=title");
+    assert_eq!(feed.entries[0].1[0]["type"], ACQUISITION_FEED);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn opds_books_are_paged_with_genre_facets() {
+    let app = spawn_app().await;
+    seed_catalog(&app).await;
+
+    let feed = get_feed(&app, "/books?limit=2", ACQUISITION_FEED).await;
+    assert_eq!(feed.entry_titles(), vec!["Lord of the Rings", "Moby Dick"]);
+    let entry_links = &feed.entries[0].1;
+    assert!(entry_links
+        .iter()
+        .any(|link| link["rel"] == "http://opds-spec.org/acquisition/borrow"));
+
+    let genres = feed.facets("Genre");
+    assert_eq!(genres.len(), 2);
+    assert_eq!(genres[0]["title"], "Adventure");
+    assert_eq!(genres[1]["thr:count"], "2");
+    assert_eq!(feed.facets("Author").len(), 2);
+
+    let next = feed.link("next").unwrap()["href"].clone();
+    let feed = get_feed(&app, next.trim_start_matches("/opds"), ACQUISITION_FEED).await;
+    assert_eq!(feed.entry_titles(), vec!["The Hobbit"]);
+    assert!(feed.link("next").is_none());
+    assert_eq!(
+        feed.link("first").unwrap()["href"],
+        "/opds/books?limit=2&sort=title"
+    );
+
+    let fantasy = genres[1]["href"].trim_start_matches("/opds").to_string();
+    let feed = get_feed(&app, &fantasy, ACQUISITION_FEED).await;
+    assert_eq!(feed.title, "Fantasy");
+    assert_eq!(feed.entry_titles(), vec!["Lord of the Rings", "The Hobbit"]);
+    assert_eq!(feed.facets("Genre")[1]["opds:activeFacet"], "true");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn opds_authors_lead_to_their_books() {
+    let app = spawn_app().await;
+    seed_catalog(&app).await;
+
+    let feed = get_feed(&app, "/authors", NAVIGATION_FEED).await;
+    assert_eq!(feed.entry_titles(), vec!["Herman Melville", "JRR Tolkien"]);
+
+    let tolkien = &feed.entries[1].1[0];
+    assert_eq!(tolkien["type"], ACQUISITION_FEED);
+    let path = tolkien["href"].trim_start_matches("/opds").to_string();
+    let feed = get_feed(&app, &path, ACQUISITION_FEED).await;
+    assert_eq!(feed.title, "Books by JRR Tolkien");
+    assert_eq!(feed.entry_titles(), vec!["Lord of the Rings", "The Hobbit"]);
+
+    let genres = get_feed(&app, "/genres", NAVIGATION_FEED).await;
+    assert_eq!(genres.entry_titles(), vec!["Adventure", "Fantasy"]);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn opds_search_and_description() {
+    let app = spawn_app().await;
+    seed_catalog(&app).await;
+
+    let feed = get_feed(&app, "/search?q=hobb", ACQUISITION_FEED).await;
+    assert_eq!(feed.entry_titles(), vec!["The Hobbit"]);
+
+    let response = app.opds("/opensearch.xml").await;
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/opensearchdescription+xml"
+    );
+    let description = response.text().await.unwrap();
+    assert!(description.contains(&format!(
+        r#"template="http://{}/opds/search?q={{searchTerms}}""#,
+        app.address
+    )));
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn opds_rejects_bad_queries() {
+    let app = spawn_app().await;
+
+    let response = app.opds("/books?limit=0").await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = app
+        .opds("/books?author_id=00000000-0000-0000-0000-000000000000")
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.opds("/search").await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    drop_db(app.db_name, app.db_url).await;
+}
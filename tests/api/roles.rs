@@ -0,0 +1,277 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use midnight_library::auth::Role;
+use serde_json::Value;
+
+async fn assert_forbidden(response: reqwest::Response, detail: &str) {
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(problem["status"], 403);
+    assert_eq!(problem["code"], "forbidden");
+    assert_eq!(problem["detail"], detail);
+}
+
+async fn create_book_with_copy(app: &TestApp) -> String {
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    let body = app
+        .create_book(
+            r#"{"title":"Lord of the Rings", "author":"JRR Tolkien", "genre": "Fiction"}"#.into(),
+        )
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let book_id = body["book_id"].as_str().unwrap().to_string();
+    app.create_item(
+        &book_id,
+        r#"{"barcode":"ML-0001", "condition":"New", "shelf_location":"Fiction A-3"}"#.into(),
+    )
+    .await;
+    book_id
+}
+
+#[tokio::test]
+async fn patrons_cannot_change_the_catalog() {
+    let app = spawn_app().await;
+    let (_, patron) = app.session_for(Role::Patron).await;
+    let book_id = create_book_with_copy(&app).await;
+
+    let book = r#"{"title":"The Hobbit", "author":"JRR Tolkien", "genre":"Fantasy"}"#;
+    let response = app
+        .request_as(reqwest::Method::POST, &patron, "/books", book)
+        .await;
+    assert_forbidden(response, "Requires the librarian role").await;
+
+    let response = app
+        .request_as(reqwest::Method::POST, "", "/books", book)
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.book_delete(&book_id, &patron).await;
+    assert_forbidden(response, "Requires the librarian role").await;
+    let response = app
+        .request_as(reqwest::Method::POST, &patron, "/imports/gutendex", "")
+        .await;
+    assert_forbidden(response, "Requires the librarian role").await;
+    let response = app
+        .request_as(
+            reqwest::Method::GET,
+            &patron,
+            &format!("/books/{}/holds", book_id),
+            "",
+        )
+        .await;
+    assert_forbidden(response, "Requires the librarian role").await;
+
+    let (_, admin) = app.session_for(Role::Admin).await;
+    let response = app
+        .request_as(reqwest::Method::POST, &admin, "/books", book)
+        .await;
+    assert!(response.status().is_success());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn patrons_act_only_on_their_own_loans_and_holds() {
+    let app = spawn_app().await;
+    let book_id = create_book_with_copy(&app).await;
+    let (ann_id, ann) = app.session_for(Role::Patron).await;
+    let (bob_id, bob) = app.session_for(Role::Patron).await;
+
+    let loan_for = |user_id| format!(r#"{{"user_id": "{}", "book_id": "{}"}}"#, user_id, book_id);
+    let response = app
+        .request_as(reqwest::Method::POST, &bob, "/loans", &loan_for(ann_id))
+        .await;
+    assert_forbidden(response, "Patrons can only act on their own account").await;
+
+    let response = app
+        .request_as(reqwest::Method::POST, &ann, "/loans", &loan_for(ann_id))
+        .await;
+    assert!(response.status().is_success());
+    let loan = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let loan_id = loan["loan_id"].as_str().unwrap();
+
+    let renew = format!("/loans/{}/renew", loan_id);
+    let response = app
+        .request_as(reqwest::Method::POST, &bob, &renew, "")
+        .await;
+    assert_forbidden(response, "Patrons can only act on their own account").await;
+    let response = app
+        .request_as(reqwest::Method::POST, &ann, &renew, "")
+        .await;
+    assert!(response.status().is_success());
+
+    let response = app
+        .request_as(
+            reqwest::Method::POST,
+            &bob,
+            &format!("/loans/{}/return", loan_id),
+            "",
+        )
+        .await;
+    assert_forbidden(response, "Patrons can only act on their own account").await;
+    let active = sqlx::query!("SELECT returned_at FROM loans")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch loan.");
+    assert!(active.returned_at.is_none());
+
+    let hold = format!(r#"{{"user_id": "{}"}}"#, bob_id);
+    let path = format!("/books/{}/holds", book_id);
+    let response = app
+        .request_as(reqwest::Method::POST, &ann, &path, &hold)
+        .await;
+    assert_forbidden(response, "Patrons can only act on their own account").await;
+    let response = app
+        .request_as(reqwest::Method::POST, &bob, &path, &hold)
+        .await;
+    assert!(response.status().is_success());
+
+    for path in [
+        format!("/users/{}/holds", bob_id),
+        format!("/users/{}/balance", bob_id),
+    ] {
+        let response = app.request_as(reqwest::Method::GET, &ann, &path, "").await;
+        assert_forbidden(response, "Patrons can only act on their own account").await;
+        let response = app.request_as(reqwest::Method::GET, &bob, &path, "").await;
+        assert!(response.status().is_success());
+    }
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn admins_hand_out_roles() {
+    let app = spawn_app().await;
+    let (patron_id, patron) = app.session_for(Role::Patron).await;
+    let (admin_id, admin) = app.session_for(Role::Admin).await;
+    let path = format!("/users/{}/role", patron_id);
+    let librarian = r#"{"role":"librarian"}"#;
+
+    let response = app
+        .request_as(reqwest::Method::PUT, &app.staff_token, &path, librarian)
+        .await;
+    assert_forbidden(response, "Requires the admin role").await;
+
+    let response = app
+        .request_as(reqwest::Method::PUT, &admin, &path, r#"{"role":"owner"}"#)
+        .await;
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(problem["status"], 422);
+    assert_eq!(problem["errors"][0]["field"], "role");
+
+    let response = app
+        .request_as(reqwest::Method::PUT, &admin, &path, librarian)
+        .await;
+    assert!(response.status().is_success());
+    let user = app
+        .me(&patron)
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(user["role"], "librarian");
+    let response = app
+        .request_as(
+            reqwest::Method::POST,
+            &patron,
+            "/authors",
+            r#"{"name":"Ursula K. Le Guin", "nationality":"American"}"#,
+        )
+        .await;
+    assert!(response.status().is_success());
+
+    let response = app
+        .request_as(
+            reqwest::Method::PUT,
+            &admin,
+            &format!("/users/{}/role", admin_id),
+            r#"{"role":"patron"}"#,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn staff_edit_only_accounts_ranked_below_them() {
+    let app = spawn_app().await;
+    let (patron_id, _) = app.session_for(Role::Patron).await;
+    let (admin_id, admin) = app.session_for(Role::Admin).await;
+    let librarian = &app.staff_token;
+    let path = |user_id| format!("/users/{}", user_id);
+
+    for (method, body) in [
+        (reqwest::Method::PATCH, r#"{"email":"taken@example.com"}"#),
+        (reqwest::Method::PATCH, r#"{"name":"Not The Admin"}"#),
+        (
+            reqwest::Method::PUT,
+            r#"{"name":"Not The Admin", "email":"taken@example.com"}"#,
+        ),
+    ] {
+        let response = app
+            .request_as(method, librarian, &path(admin_id), body)
+            .await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+    let email = sqlx::query_scalar!("SELECT email FROM users WHERE id = $1", admin_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved user.");
+    assert_ne!(email, "taken@example.com");
+
+    let response = app
+        .request_as(
+            reqwest::Method::PATCH,
+            librarian,
+            &path(patron_id),
+            r#"{"email":"taken@example.com"}"#,
+        )
+        .await;
+    assert_forbidden(response, "Only admins can change someone else's email").await;
+    let response = app
+        .request_as(
+            reqwest::Method::PATCH,
+            librarian,
+            &path(patron_id),
+            r#"{"name":"Renamed Patron"}"#,
+        )
+        .await;
+    assert!(response.status().is_success());
+
+    let response = app
+        .request_as(
+            reqwest::Method::PATCH,
+            &admin,
+            &path(patron_id),
+            r#"{"email":"patron@example.com"}"#,
+        )
+        .await;
+    assert!(response.status().is_success());
+    let (other_admin_id, _) = app.session_for(Role::Admin).await;
+    let response = app
+        .request_as(
+            reqwest::Method::PATCH,
+            &admin,
+            &path(other_admin_id),
+            r#"{"name":"Demoted In Name"}"#,
+        )
+        .await;
+    assert_forbidden(
+        response,
+        "Staff can only edit accounts ranked below their own",
+    )
+    .await;
+
+    drop_db(app.db_name, app.db_url).await;
+}
@@ -0,0 +1,187 @@
+use crate::test_helpers::{drop_db, spawn_app, TestApp};
+use serde_json::Value;
+
+async fn seed_catalog(app: &TestApp) -> String {
+    let response = app
+        .create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    app.create_author(r#"{"name":"Herman Melville", "nationality":"American"}"#.into())
+        .await;
+    app.create_book(
+        r#"{"title":"Lord of the Rings", "author":"JRR Tolkien", "genre": "Fantasy"}"#.into(),
+    )
+    .await;
+    app.create_book(r#"{"title":"The Hobbit", "author":"JRR Tolkien", "genre": "Fantasy"}"#.into())
+        .await;
+    app.create_book(
+        r#"{"title":"Moby Dick", "author":"Herman Melville", "genre": "Adventure"}"#.into(),
+    )
+    .await;
+    app.create_book(
+        r#"{"title":"Fantasy Worlds of Melville", "author":"Herman Melville", "genre": "Essay"}"#
+            .into(),
+    )
+    .await;
+
+    response["author_id"]
+        .as_str()
+        .expect("Failed to extract author id from response.")
+        .into()
+}
+
+async fn search_results(app: &TestApp, query: &str) -> Vec<Value> {
+    let response = app.search(query).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<Vec<Value>>()
+        .await
+        .expect("Failed to deserialize response body.")
+}
+
+#[tokio::test]
+async fn search_matches_word_prefixes() {
+    let app = spawn_app().await;
+    seed_catalog(&app).await;
+
+    let results = search_results(&app, "q=hobb").await;
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["title"], "The Hobbit");
+    assert_eq!(results[0]["author"], "JRR Tolkien");
+    assert_eq!(
+        results[0]["snippet"],
+        "The <mark>Hobbit</mark> · JRR Tolkien · Fantasy"
+    );
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn search_matches_phrases() {
+    let app = spawn_app().await;
+    seed_catalog(&app).await;
+
+    let results = search_results(&app, "q=%22lord+of+the+rings%22").await;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["title"], "Lord of the Rings");
+
+    let results = search_results(&app, "q=%22rings+of+the+lord%22").await;
+    assert!(results.is_empty());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn search_ranks_title_matches_first() {
+    let app = spawn_app().await;
+    seed_catalog(&app).await;
+
+    let results = search_results(&app, "q=fantasy").await;
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["title"], "Fantasy Worlds of Melville");
+    assert!(results[0]["rank"].as_f64() > results[1]["rank"].as_f64());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn search_finds_books_by_author() {
+    let app = spawn_app().await;
+    seed_catalog(&app).await;
+
+    let results = search_results(&app, "q=melville+moby").await;
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["title"], "Moby Dick");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn search_tolerates_typos() {
+    let app = spawn_app().await;
+    seed_catalog(&app).await;
+
+    let results = search_results(&app, "q=hobit").await;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["title"], "The Hobbit");
+
+    let results = search_results(&app, "q=melvile").await;
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["author"], "Herman Melville");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn search_snippets_escape_book_data() {
+    let app = spawn_app().await;
+    seed_catalog(&app).await;
+    app.create_book(
+        r#"{"title":"<b>Whales</b> & Wonders", "author":"Herman Melville", "genre": "Essay"}"#
+            .into(),
+    )
+    .await;
+
+    let results = search_results(&app, "q=wonders").await;
+    assert_eq!(
+        results[0]["snippet"],
+        "&lt;b&gt;Whales&lt;/b&gt; &amp; <mark>Wonders</mark> · Herman Melville · Essay"
+    );
+
+    let results = search_results(&app, "q=wonderz").await;
+    assert_eq!(
+        results[0]["snippet"],
+        "&lt;b&gt;Whales&lt;/b&gt; &amp; Wonders · Herman Melville · Essay"
+    );
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn search_follows_renamed_authors() {
+    let app = spawn_app().await;
+    let author_id = seed_catalog(&app).await;
+
+    app.patch_author(&author_id, r#"{"name":"John Ronald Reuel Tolkien"}"#.into())
+        .await;
+    let results = search_results(&app, "q=ronald").await;
+
+    assert_eq!(results.len(), 2);
+    assert!(search_results(&app, "q=jrr").await.is_empty());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn search_limits_results() {
+    let app = spawn_app().await;
+    seed_catalog(&app).await;
+
+    let results = search_results(&app, "q=fantasy&limit=1").await;
+    assert_eq!(results.len(), 1);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn search_without_query_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app.search("q=+").await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let body = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(body["errors"][0]["field"], "q");
+    assert_eq!(body["errors"][0]["rule"], "empty");
+
+    drop_db(app.db_name, app.db_url).await;
+}
@@ -0,0 +1,210 @@
+use crate::test_helpers::{drop_db, spawn_app};
+use serde_json::Value;
+
+const REGISTRATION: &str =
+    r#"{"name":"Richard", "email":"richard@example.com", "password":"correct horse battery"}"#;
+const LOGIN: &str = r#"{"email":"richard@example.com", "password":"correct horse battery"}"#;
+
+#[tokio::test]
+async fn registration_stores_a_password_hash() {
+    let app = spawn_app().await;
+
+    let response = app.create_user(REGISTRATION.into()).await;
+    let record = sqlx::query!("SELECT password_hash FROM users WHERE role = 'patron'")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved user.");
+
+    assert!(response.status().is_success());
+    let hash = record.password_hash.unwrap();
+    assert!(hash.starts_with("$argon2id$"));
+    assert!(!hash.contains("correct horse battery"));
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn login_resolves_the_current_user() {
+    let app = spawn_app().await;
+    app.create_user(REGISTRATION.into()).await;
+
+    let response = app.login(LOGIN.into()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let cookie = response.headers()["Set-Cookie"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let token = body["token"].as_str().unwrap();
+    assert_eq!(body["message"], "Logged in successfully!");
+    assert!(cookie.starts_with(&format!("session={};", token)));
+    assert!(cookie.contains("HttpOnly"));
+
+    let user = app
+        .me(token)
+        .await
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(user["id"], body["user_id"]);
+    assert_eq!(user["email"], "richard@example.com");
+    assert_eq!(user["role"], "patron");
+
+    let response = reqwest::Client::new()
+        .get(format!("http://{}/api/v1/me", &app.address))
+        .header("Cookie", format!("session={}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn login_with_bad_credentials_is_unauthorized() {
+    let app = spawn_app().await;
+    app.create_user(REGISTRATION.into()).await;
+    app.create_user(r#"{"name":"Ann", "email":"ann@example.com"}"#.into())
+        .await;
+
+    for body in [
+        r#"{"email":"richard@example.com", "password":"battery staple horse"}"#,
+        r#"{"email":"nobody@example.com", "password":"correct horse battery"}"#,
+        r#"{"email":"ann@example.com", "password":"correct horse battery"}"#,
+    ] {
+        let response = app.login(body.into()).await;
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(response.headers()["WWW-Authenticate"], "Bearer");
+        let problem = response
+            .json::<Value>()
+            .await
+            .expect("Failed to deserialize response body.");
+        assert_eq!(problem["code"], "unauthorized");
+        assert_eq!(problem["detail"], "Invalid email or password");
+    }
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn logout_ends_the_session() {
+    let app = spawn_app().await;
+    let token = app.log_in_user().await;
+
+    let response = app.logout(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let cookie = response.headers()["Set-Cookie"].to_str().unwrap();
+    assert!(cookie.contains("Max-Age=0"));
+
+    let response = app.me(&token).await;
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(problem["status"], 401);
+    assert_eq!(problem["detail"], "Session expired or logged out");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn expired_and_forged_tokens_are_unauthorized() {
+    let app = spawn_app().await;
+    let token = app.log_in_user().await;
+
+    let (session_id, tag) = token.split_once('.').unwrap();
+    let forged = format!("{}.{}", uuid::Uuid::new_v4(), tag);
+    let response = app.me(&forged).await;
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(problem["status"], 401);
+    assert_eq!(problem["detail"], "Invalid session token");
+
+    sqlx::query("UPDATE sessions SET expires_at = now() - interval '1 minute' WHERE id = $1::uuid")
+        .bind(session_id)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to expire session.");
+    let response = app.me(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn deletion_requires_a_login() {
+    let app = spawn_app().await;
+    app.create_author(r#"{"name":"JRR Tolkien", "nationality":"British"}"#.into())
+        .await;
+    let response = app
+        .create_book(r#"{"title":"The Hobbit", "author":"JRR Tolkien", "genre":"Fantasy"}"#.into())
+        .await;
+    let body = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let book_id = body["book_id"].as_str().unwrap();
+
+    let response = reqwest::Client::new()
+        .delete(format!("http://{}/api/v1/books/{}", &app.address, book_id))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(problem["status"], 401);
+    assert_eq!(problem["detail"], "Log in first");
+
+    let response = app.book_delete(book_id, &app.staff_token).await;
+    assert!(response.status().is_success());
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn passwords_are_checked_and_only_set_at_registration() {
+    let app = spawn_app().await;
+
+    let response = app
+        .create_user(
+            r#"{"name":"Richard", "email":"richard@example.com", "password":"short"}"#.into(),
+        )
+        .await;
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(problem["status"], 422);
+    assert_eq!(problem["errors"][0]["field"], "password");
+    assert_eq!(problem["errors"][0]["rule"], "too_short");
+
+    let response = app.create_user(REGISTRATION.into()).await;
+    let body = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let user_id = body["user_id"].as_str().unwrap();
+    let response = app
+        .update_user(
+            user_id,
+            r#"{"name":"Richard", "email":"richard@example.com", "password":"a new password"}"#
+                .into(),
+        )
+        .await;
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    assert_eq!(problem["status"], 422);
+    assert_eq!(problem["errors"][0]["rule"], "not_allowed");
+
+    drop_db(app.db_name, app.db_url).await;
+}
@@ -1,6 +1,14 @@
-use midnight_library::{configuration, startup::run};
+use midnight_library::{
+    auth::{session_token, Role},
+    configuration::{self, ApplicationConfigs, MailTransportConfig, MetadataConfig},
+    startup::run,
+};
+use secrecy::ExposeSecret;
+use serde_json::Value;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
 use uuid::Uuid;
 
 pub struct TestApp {
@@ -8,26 +16,55 @@ pub struct TestApp {
     pub db_pool: PgPool,
     pub db_name: String,
     pub db_url: String,
+    pub session_secret: String,
+    // A librarian's session, sent by the helpers for staff only endpoints
+    pub staff_token: String,
+    pub mail_directory: PathBuf,
 }
 
+pub const METADATA_FIXTURE: &str = "tests/fixtures/open_library.json";
+
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+// ISBN lookups answer from the fixture file, mail is captured in a directory of the app's own
+// and job workers poll and retry quickly, unless `configure` says otherwise
+pub async fn spawn_app_with(configure: impl FnOnce(&mut ApplicationConfigs)) -> TestApp {
     let tcp_listener = TcpListener::bind("localhost:0").expect("Failed to bind random port");
     let address = tcp_listener
         .local_addr()
         .expect("Failed to get local address")
         .to_string();
 
+    let mut config = configuration::get_configuration().expect("Failed to read configuration.");
+    config.metadata = MetadataConfig::Fixture {
+        path: METADATA_FIXTURE.into(),
+    };
+    config.jobs.poll_interval_ms = 100;
+    config.jobs.retry_backoff_ms = 20;
+    let mail_directory = std::env::temp_dir().join(format!("midnight-mail-{}", Uuid::new_v4()));
+    config.mailer.transport = MailTransportConfig::File {
+        directory: mail_directory.to_string_lossy().into(),
+    };
+    configure(&mut config);
     let (db_pool, db_name, db_url) = setup_db().await;
+    let session_secret = config.auth.session_secret.expose_secret().clone();
 
-    let server = run(tcp_listener, db_pool.clone()).expect("Failed to bind address");
+    let server = run(tcp_listener, db_pool.clone(), config).expect("Failed to bind address");
     tokio::spawn(server);
 
-    TestApp {
+    let mut app = TestApp {
         address,
         db_pool,
         db_name,
         db_url,
-    }
+        session_secret,
+        staff_token: String::new(),
+        mail_directory,
+    };
+    app.staff_token = app.session_for(Role::Librarian).await.1;
+    app
 }
 
 async fn setup_db() -> (PgPool, String, String) {
@@ -35,7 +72,7 @@ async fn setup_db() -> (PgPool, String, String) {
     let db_url = format!(
         "postgres://{}:{}@{}:{}",
         config.database.username,
-        config.database.password,
+        config.database.password.expose_secret(),
         config.database.host,
         config.database.port,
     );
@@ -90,19 +127,50 @@ pub async fn drop_db(name: String, db_url: String) {
 impl TestApp {
     pub async fn create_author(&self, body: String) -> reqwest::Response {
         reqwest::Client::new()
-            .post(format!("http://{}/authors/create", &self.address))
+            .post(format!("http://{}/api/v1/authors", &self.address))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_author(&self, author_id: &str, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!(
+                "http://{}/api/v1/authors/{}",
+                &self.address, author_id
+            ))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn update_author(&self, author_id: &str, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!(
+                "http://{}/api/v1/authors/{}",
+                &self.address, author_id
+            ))
             .header("Content-Type", "application/json")
             .body(body)
+            .bearer_auth(&self.staff_token)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
-    pub async fn delete_author(&self, body: String) -> reqwest::Response {
+    pub async fn patch_author(&self, author_id: &str, body: String) -> reqwest::Response {
         reqwest::Client::new()
-            .post(format!("http://{}/authors/delete", &self.address))
+            .patch(format!(
+                "http://{}/api/v1/authors/{}",
+                &self.address, author_id
+            ))
             .header("Content-Type", "application/json")
             .body(body)
+            .bearer_auth(&self.staff_token)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -110,7 +178,26 @@ impl TestApp {
 
     pub async fn author_index(&self) -> reqwest::Response {
         reqwest::Client::new()
-            .get(format!("http://{}/authors", &self.address))
+            .get(format!("http://{}/api/v1/authors", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn author_index_query(&self, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("http://{}/api/v1/authors?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn suggest_authors(&self, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "http://{}/api/v1/authors/suggest?{}",
+                &self.address, query
+            ))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -118,7 +205,10 @@ impl TestApp {
 
     pub async fn show_author(&self, author_id: String) -> reqwest::Response {
         reqwest::Client::new()
-            .get(format!("http://{}/authors/{}", &self.address, author_id))
+            .get(format!(
+                "http://{}/api/v1/authors/{}",
+                &self.address, author_id
+            ))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -126,9 +216,32 @@ impl TestApp {
 
     pub async fn create_book(&self, body: String) -> reqwest::Response {
         reqwest::Client::new()
-            .post(format!("http://{}/books/create", &self.address))
+            .post(format!("http://{}/api/v1/books", &self.address))
             .header("Content-Type", "application/json")
             .body(body)
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn update_book(&self, book_id: &str, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!("http://{}/api/v1/books/{}", &self.address, book_id))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn patch_book(&self, book_id: &str, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .patch(format!("http://{}/api/v1/books/{}", &self.address, book_id))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .bearer_auth(&self.staff_token)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -136,7 +249,139 @@ impl TestApp {
 
     pub async fn book_index(&self) -> reqwest::Response {
         reqwest::Client::new()
-            .get(format!("http://{}/books", &self.address))
+            .get(format!("http://{}/api/v1/books", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn book_index_query(&self, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("http://{}/api/v1/books?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn search(&self, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("http://{}/api/v1/search?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn import_gutendex(&self) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("http://{}/api/v1/imports/gutendex", &self.address))
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn show_job(&self, job_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("http://{}/api/v1/jobs/{}", &self.address, job_id))
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn cancel_job(&self, job_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "http://{}/api/v1/jobs/{}/cancel",
+                &self.address, job_id
+            ))
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Polls the job until the workers are done with it, one way or another
+    pub async fn wait_for_job(&self, job_id: &str) -> Value {
+        for _ in 0..250 {
+            let job = self
+                .show_job(job_id)
+                .await
+                .json::<Value>()
+                .await
+                .expect("Failed to deserialize response body.");
+            if !["queued", "running"].contains(&job["status"].as_str().unwrap()) {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("Job {} did not finish", job_id);
+    }
+
+    pub async fn import_csv(&self, resource: &str, query: &str, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "http://{}/api/v1/{}/import?{}",
+                &self.address, resource, query
+            ))
+            .header("Content-Type", "text/csv")
+            .body(body)
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn export_csv(&self, resource: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "http://{}/api/v1/{}/export.csv",
+                &self.address, resource
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn import_marc(&self, query: &str, body: Vec<u8>) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "http://{}/api/v1/books/import_marc?{}",
+                &self.address, query
+            ))
+            .header("Content-Type", "application/marc")
+            .body(body)
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn export_marc(&self, book_id: &str, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "http://{}/api/v1/books/{}/marc?{}",
+                &self.address, book_id, query
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn opds(&self, path: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("http://{}/opds{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn import_isbn(&self, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("http://{}/api/v1/books/import_isbn", &self.address))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .bearer_auth(&self.staff_token)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -144,27 +389,368 @@ impl TestApp {
 
     pub async fn show_book(&self, book_id: String) -> reqwest::Response {
         reqwest::Client::new()
-            .get(format!("http://{}/books/{}", &self.address, book_id))
+            .get(format!("http://{}/api/v1/books/{}", &self.address, book_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn book_delete(&self, book_id: &str, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!("http://{}/api/v1/books/{}", &self.address, book_id))
+            .bearer_auth(token)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
-    pub async fn book_delete(&self, body: String) -> reqwest::Response {
+    pub async fn create_item(&self, book_id: &str, body: String) -> reqwest::Response {
         reqwest::Client::new()
-            .post(format!("http://{}/books/delete", &self.address))
+            .post(format!(
+                "http://{}/api/v1/books/{}/items",
+                &self.address, book_id
+            ))
             .header("Content-Type", "application/json")
             .body(body)
+            .bearer_auth(&self.staff_token)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn item_index(&self, book_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "http://{}/api/v1/books/{}/items",
+                &self.address, book_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn show_item(&self, book_id: &str, item_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "http://{}/api/v1/books/{}/items/{}",
+                &self.address, book_id, item_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn update_item(
+        &self,
+        book_id: &str,
+        item_id: &str,
+        body: String,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!(
+                "http://{}/api/v1/books/{}/items/{}",
+                &self.address, book_id, item_id
+            ))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_item(&self, book_id: &str, item_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!(
+                "http://{}/api/v1/books/{}/items/{}",
+                &self.address, book_id, item_id
+            ))
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn create_hold(&self, book_id: &str, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "http://{}/api/v1/books/{}/holds",
+                &self.address, book_id
+            ))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn book_holds(&self, book_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "http://{}/api/v1/books/{}/holds",
+                &self.address, book_id
+            ))
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn user_holds(&self, user_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "http://{}/api/v1/users/{}/holds",
+                &self.address, user_id
+            ))
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn cancel_hold(&self, user_id: &str, hold_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "http://{}/api/v1/users/{}/holds/{}/cancel",
+                &self.address, user_id, hold_id
+            ))
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn show_balance(&self, user_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "http://{}/api/v1/users/{}/balance",
+                &self.address, user_id
+            ))
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn create_payment(&self, user_id: &str, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "http://{}/api/v1/users/{}/payments",
+                &self.address, user_id
+            ))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn create_waiver(&self, user_id: &str, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "http://{}/api/v1/users/{}/waivers",
+                &self.address, user_id
+            ))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Mail captured for `to`, oldest first
+    pub fn emails_to(&self, to: &str) -> Vec<Value> {
+        let Ok(entries) = std::fs::read_dir(&self.mail_directory) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries.map(|entry| entry.unwrap().path()).collect();
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|path| {
+                serde_json::from_slice::<Value>(&std::fs::read(path).unwrap())
+                    .expect("Captured mail is not JSON.")
+            })
+            .filter(|email| email["to"] == to)
+            .collect()
+    }
+
+    // The token in the latest mail to `to`
+    pub fn last_token_sent_to(&self, to: &str) -> String {
+        let emails = self.emails_to(to);
+        let body = emails.last().expect("No mail was sent.")["body"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        body.lines()
+            .find(|line| line.len() == 64 && line.chars().all(|c| c.is_ascii_hexdigit()))
+            .expect("No token in the mail.")
+            .into()
+    }
+
+    // Any endpoint under /api/v1 as whoever `token` belongs to
+    pub async fn request_as(
+        &self,
+        method: reqwest::Method,
+        token: &str,
+        path: &str,
+        body: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .request(method, format!("http://{}/api/v1{}", &self.address, path))
+            .bearer_auth(token)
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn login(&self, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("http://{}/api/v1/login", &self.address))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn logout(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("http://{}/api/v1/logout", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn me(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("http://{}/api/v1/me", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // A user with `role` and a live session, made straight in the database to skip hashing a
+    // password, with the token to act as them
+    pub async fn session_for(&self, role: Role) -> (Uuid, String) {
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (name, email, role, created_at)
+            VALUES ($1, $2, $3, now())
+            RETURNING id",
+            format!("Test {}", role),
+            format!("{}@example.com", Uuid::new_v4()),
+            role.as_str()
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .expect("Failed to save user.");
+        let session_id = sqlx::query_scalar!(
+            "INSERT INTO sessions (user_id, created_at, expires_at)
+            VALUES ($1, now(), now() + interval '1 hour')
+            RETURNING id",
+            user_id
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .expect("Failed to save session.");
+
+        (user_id, session_token(session_id, &self.session_secret))
+    }
+
+    // Registers a user with a password and logs them in, for tests of endpoints that need a
+    // session and don't care whose it is
+    pub async fn log_in_user(&self) -> String {
+        let email = format!("{}@example.com", Uuid::new_v4());
+        self.create_user(format!(
+            r#"{{"name":"Librarian", "email":"{}", "password":"correct horse battery"}}"#,
+            email
+        ))
+        .await;
+        let response = self
+            .login(format!(
+                r#"{{"email":"{}", "password":"correct horse battery"}}"#,
+                email
+            ))
+            .await;
+        let body = response
+            .json::<Value>()
+            .await
+            .expect("Failed to deserialize response body.");
+        body["token"].as_str().expect("Login failed.").into()
+    }
+
     pub async fn create_user(&self, body: String) -> reqwest::Response {
         reqwest::Client::new()
-            .post(format!("http://{}/users/create", &self.address))
+            .post(format!("http://{}/api/v1/users", &self.address))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn checkout_loan(&self, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("http://{}/api/v1/loans", &self.address))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn return_loan(&self, loan_id: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "http://{}/api/v1/loans/{}/return",
+                &self.address, loan_id
+            ))
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn renew_loan(&self, loan_id: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "http://{}/api/v1/loans/{}/renew",
+                &self.address, loan_id
+            ))
+            .bearer_auth(&self.staff_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Only admins change another user's email
+    pub async fn update_user(&self, user_id: &str, body: String) -> reqwest::Response {
+        let (_, admin) = self.session_for(Role::Admin).await;
+        reqwest::Client::new()
+            .put(format!("http://{}/api/v1/users/{}", &self.address, user_id))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .bearer_auth(&admin)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn patch_user(&self, user_id: &str, body: String) -> reqwest::Response {
+        let (_, admin) = self.session_for(Role::Admin).await;
+        reqwest::Client::new()
+            .patch(format!("http://{}/api/v1/users/{}", &self.address, user_id))
             .header("Content-Type", "application/json")
             .body(body)
+            .bearer_auth(&admin)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -1,4 +1,5 @@
 use crate::test_helpers::{drop_db, spawn_app};
+use serde_json::Value;
 
 #[tokio::test]
 async fn user_creation() {
@@ -7,7 +8,7 @@ async fn user_creation() {
     let response = app
         .create_user(r#"{"name":"Richard", "email":"example@email.com"}"#.into())
         .await;
-    let record = sqlx::query!("SELECT * FROM users")
+    let record = sqlx::query!("SELECT * FROM users WHERE role = 'patron'")
         .fetch_one(&app.db_pool)
         .await
         .expect("Failed to fetch saved user.");
@@ -19,6 +20,47 @@ async fn user_creation() {
     drop_db(app.db_name, app.db_url).await;
 }
 
+#[tokio::test]
+async fn user_creation_with_invalid_data_is_unprocessable() {
+    let app = spawn_app().await;
+
+    let response = app
+        .create_user(r#"{"name":"Richard", "email":"example.com"}"#.into())
+        .await;
+    let content_type = response.headers()["Content-Type"].clone();
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(content_type, "application/problem+json");
+    assert_eq!(problem["status"], 422);
+    assert_eq!(problem["code"], "validation_failed");
+    assert_eq!(problem["errors"][0]["field"], "email");
+    assert_eq!(problem["errors"][0]["rule"], "bad_format");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn user_creation_with_email_in_use() {
+    let app = spawn_app().await;
+    let body = r#"{"name":"Richard", "email":"example@email.com"}"#;
+
+    app.create_user(body.into()).await;
+    let response = app.create_user(body.into()).await;
+    let problem = response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+
+    assert_eq!(problem["status"], 409);
+    assert_eq!(problem["code"], "conflict");
+    assert_eq!(problem["detail"], "User email already in use");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
 #[tokio::test]
 async fn user_creation_with_invalid_data() {
     let app = spawn_app().await;
@@ -26,7 +68,7 @@ async fn user_creation_with_invalid_data() {
     let response = app
         .create_user(r#"{"name":"Richard", "email":"example.com"}"#.into())
         .await;
-    let record = sqlx::query!("SELECT * FROM users")
+    let record = sqlx::query!("SELECT * FROM users WHERE role = 'patron'")
         .fetch_optional(&app.db_pool)
         .await
         .expect("Failed to fetch saved user.");
@@ -36,3 +78,93 @@ async fn user_creation_with_invalid_data() {
 
     drop_db(app.db_name, app.db_url).await;
 }
+
+#[tokio::test]
+async fn user_update() {
+    let app = spawn_app().await;
+    let create_response = app
+        .create_user(r#"{"name":"Richard", "email":"example@email.com"}"#.into())
+        .await;
+    let response_body = create_response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let user_id = response_body["user_id"]
+        .as_str()
+        .expect("Failed to extract user id from response.");
+
+    let response = app
+        .update_user(
+            user_id,
+            r#"{"name":"Richard Roe", "email":"richard@email.com"}"#.into(),
+        )
+        .await;
+    let record = sqlx::query!("SELECT * FROM users WHERE role = 'patron'")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved user.");
+
+    assert!(response.status().is_success());
+    assert_eq!(record.name, "Richard Roe");
+    assert_eq!(record.email, "richard@email.com");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn user_partial_update() {
+    let app = spawn_app().await;
+    let create_response = app
+        .create_user(r#"{"name":"Richard", "email":"example@email.com"}"#.into())
+        .await;
+    let response_body = create_response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let user_id = response_body["user_id"]
+        .as_str()
+        .expect("Failed to extract user id from response.");
+
+    let response = app
+        .patch_user(user_id, r#"{"email":"richard@email.com"}"#.into())
+        .await;
+    let invalid_response = app
+        .patch_user(user_id, r#"{"email":"richard.com"}"#.into())
+        .await;
+    let record = sqlx::query!("SELECT * FROM users WHERE role = 'patron'")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved user.");
+
+    assert!(response.status().is_success());
+    assert!(invalid_response.status().is_client_error());
+    assert_eq!(record.name, "Richard");
+    assert_eq!(record.email, "richard@email.com");
+
+    drop_db(app.db_name, app.db_url).await;
+}
+
+#[tokio::test]
+async fn user_update_with_email_in_use() {
+    let app = spawn_app().await;
+    app.create_user(r#"{"name":"Richard", "email":"richard@email.com"}"#.into())
+        .await;
+    let create_response = app
+        .create_user(r#"{"name":"Jane", "email":"jane@email.com"}"#.into())
+        .await;
+    let response_body = create_response
+        .json::<Value>()
+        .await
+        .expect("Failed to deserialize response body.");
+    let user_id = response_body["user_id"]
+        .as_str()
+        .expect("Failed to extract user id from response.");
+
+    let response = app
+        .patch_user(user_id, r#"{"email":"richard@email.com"}"#.into())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+
+    drop_db(app.db_name, app.db_url).await;
+}